@@ -0,0 +1,74 @@
+//! Benchmarks for the full parse pipeline.
+//!
+//! Run with `cargo bench`. Each iteration of `parse_args` builds a fresh clap
+//! `Command` from the config and then matches the arguments, so the expected
+//! hotspots are:
+//!
+//! - command building (`build_command`/`build_arg`): one clap `Arg` per config
+//!   arg, including env lookups and value parser setup; this grows with the
+//!   size of the config regardless of how many arguments are passed
+//! - matching (`try_get_matches_from`): grows with the number of arguments
+//!   passed
+//! - value extraction: small in comparison
+//!
+//! `parse_medium_config_10k` repeats a parse of a 50-arg config 10,000 times
+//! per iteration to make regressions in command building visible.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use shclap::{parse_args, Config, ParseOutcome};
+
+/// Build a schema v2 config with 50 args: 20 flags, 20 options, 10 positionals.
+fn medium_config() -> Config {
+    let mut args = Vec::new();
+    for i in 0..20 {
+        args.push(format!(
+            r#"{{"name":"flag{i}","type":"flag","help":"Flag {i}"}}"#
+        ));
+    }
+    for i in 0..20 {
+        args.push(format!(
+            r#"{{"name":"opt{i}","type":"option","default":"d{i}","help":"Option {i}"}}"#
+        ));
+    }
+    for i in 0..10 {
+        args.push(format!(
+            r#"{{"name":"pos{i}","type":"positional","help":"Positional {i}"}}"#
+        ));
+    }
+    let json = format!(
+        r#"{{"schema_version":2,"name":"bench","args":[{}]}}"#,
+        args.join(",")
+    );
+    let config = Config::from_json(&json).expect("bench config parses");
+    config.validate().expect("bench config is valid");
+    config
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let config = medium_config();
+    let args: Vec<String> = ["--flag3", "--opt7", "value", "--flag11", "a", "b", "c"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    c.bench_function("parse_medium_config", |b| {
+        b.iter(|| {
+            let outcome = parse_args(black_box(&config), black_box(&args), "bench");
+            assert!(matches!(outcome, ParseOutcome::Success(_)));
+        })
+    });
+
+    let mut group = c.benchmark_group("parse_medium_config_10k");
+    group.sample_size(10);
+    group.bench_function("10k", |b| {
+        b.iter(|| {
+            for _ in 0..10_000 {
+                black_box(parse_args(black_box(&config), black_box(&args), "bench"));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
@@ -0,0 +1,579 @@
+//! Building a dynamic Clap command from a config.
+//!
+//! Parsing and help generation share this builder, so the command that
+//! validates arguments is exactly the one whose help the user sees.
+
+use crate::config::{
+    normalize_hex, parse_finite_double, ArgConfig, ArgType, Config, DefaultValue, SubcommandConfig,
+    ValueType,
+};
+use clap::builder::TypedValueParser;
+use clap::{Arg, ArgAction, Command};
+
+/// Build a Clap Command from a Config with an effective name.
+pub(crate) fn build_command(config: &Config, effective_name: &str) -> Command {
+    let mut cmd = Command::new(effective_name.to_string())
+        .disable_help_subcommand(true)
+        .disable_version_flag(false)
+        .disable_help_flag(false);
+
+    // Set version if provided
+    if let Some(ref version) = config.version {
+        cmd = cmd.version(version.clone());
+    }
+
+    // Set description if provided
+    if let Some(ref description) = config.description {
+        cmd = cmd.about(description.clone());
+    }
+
+    // Clap shows this for --help and falls back to the description for -h
+    if let Some(ref long_description) = config.long_description {
+        cmd = cmd.long_about(long_description.clone());
+    }
+
+    if let Some(ref banner) = config.before_help {
+        cmd = cmd.before_help(banner.clone());
+    }
+
+    // Use a custom help layout if provided
+    if let Some(ref template) = config.help_template {
+        cmd = cmd.help_template(template.clone());
+    }
+
+    // Free text and contact details go in a footer below the arguments
+    if let Some(footer) = config.help_footer() {
+        cmd = cmd.after_help(footer);
+    }
+
+    if config.next_line_help {
+        cmd = cmd.next_line_help(true);
+    }
+
+    // Resolve unambiguous long-option prefixes (e.g. --verb for --verbose)
+    cmd = cmd.infer_long_args(config.allow_abbreviations);
+
+    // Resolve unambiguous subcommand prefixes (e.g. `in` for `init`)
+    cmd = cmd.infer_subcommands(config.infer_subcommands);
+
+    let prefix = &config.resolve_prefix(None);
+    let schema_version = config.schema_version;
+    let auto_env = config.auto_env_enabled();
+    let strip_name_prefix = config.strip_name_prefix.as_deref();
+    let empty_env_unset = config.strict;
+    let group_by_kind = config.group_by_kind;
+
+    // Track positional index for ordering
+    let mut positional_index = 1usize;
+
+    // Add arguments from config
+    for arg_config in ordered_args(&config.args, group_by_kind) {
+        let arg = build_arg(
+            arg_config,
+            &mut positional_index,
+            prefix,
+            schema_version,
+            auto_env,
+            strip_name_prefix,
+            empty_env_unset,
+        );
+        cmd = cmd.arg(kind_heading(arg, arg_config, group_by_kind));
+    }
+
+    // Add subcommands (schema v2)
+    for subcmd_config in &config.subcommands {
+        cmd = cmd.subcommand(build_subcommand(subcmd_config, config, prefix));
+    }
+
+    // Require subcommand if any defined
+    if !config.subcommands.is_empty() {
+        cmd = cmd.subcommand_required(true);
+        cmd = cmd.arg_required_else_help(true);
+    }
+
+    // Show help instead of parsing when invoked with no args
+    if config.help_if_no_args {
+        cmd = cmd.arg_required_else_help(true);
+    }
+
+    cmd
+}
+
+/// The args in the order they're added to the command: as declared, or with
+/// `group_by_kind`, flags and options first and then positionals, each kind
+/// keeping its declared order.
+fn ordered_args(args: &[ArgConfig], group_by_kind: bool) -> Vec<&ArgConfig> {
+    let mut ordered: Vec<&ArgConfig> = args.iter().collect();
+    if group_by_kind {
+        // Stable sort, so relative order within each kind is preserved
+        ordered.sort_by_key(|arg| arg.arg_type == ArgType::Positional);
+    }
+    ordered
+}
+
+/// With `group_by_kind`, give positionals an explicit "Arguments" heading:
+/// Clap renders custom headings after "Options", so positionals move below.
+/// Clap sorts a custom heading by display order, so pin it to the index.
+fn kind_heading(arg: Arg, arg_config: &ArgConfig, group_by_kind: bool) -> Arg {
+    if group_by_kind && arg_config.arg_type == ArgType::Positional {
+        let index = arg.get_index().unwrap_or_default();
+        arg.help_heading("Arguments").display_order(index)
+    } else {
+        arg
+    }
+}
+
+/// Build a Clap Command for a subcommand config, including its own
+/// subcommands; `root` supplies the config-wide settings.
+fn build_subcommand(config: &SubcommandConfig, root: &Config, prefix: &str) -> Command {
+    let group_by_kind = root.group_by_kind;
+    let mut cmd = Command::new(config.name.clone()).infer_long_args(root.allow_abbreviations);
+
+    if let Some(ref help) = config.help {
+        cmd = cmd.about(help.clone());
+    }
+
+    // Keep internal subcommands out of the top-level help
+    if config.hidden {
+        cmd = cmd.hide(true);
+    }
+
+    if !config.aliases.is_empty() {
+        cmd = cmd.visible_aliases(config.aliases.clone());
+    }
+
+    if let Some(ref footer) = config.after_help {
+        cmd = cmd.after_help(footer.clone());
+    }
+
+    // Track positional index for ordering
+    let mut positional_index = 1usize;
+
+    // Add arguments
+    for arg_config in ordered_args(&config.args, group_by_kind) {
+        let arg = build_arg(
+            arg_config,
+            &mut positional_index,
+            prefix,
+            root.schema_version,
+            root.auto_env_enabled(),
+            root.strip_name_prefix.as_deref(),
+            root.strict,
+        );
+        cmd = cmd.arg(kind_heading(arg, arg_config, group_by_kind));
+    }
+
+    // Nested subcommands (e.g. `remote add`): one of them must be given
+    if !config.subcommands.is_empty() {
+        for child in &config.subcommands {
+            cmd = cmd.subcommand(build_subcommand(child, root, prefix));
+        }
+        cmd = cmd
+            .disable_help_subcommand(true)
+            .infer_subcommands(root.infer_subcommands)
+            .subcommand_required(true)
+            .arg_required_else_help(true);
+    }
+
+    cmd
+}
+
+/// Build a Clap Arg from an ArgConfig.
+fn build_arg(
+    arg_config: &ArgConfig,
+    positional_index: &mut usize,
+    prefix: &str,
+    schema_version: u32,
+    auto_env: bool,
+    strip_name_prefix: Option<&str>,
+    empty_env_unset: bool,
+) -> Arg {
+    let mut arg = Arg::new(arg_config.name.clone());
+
+    match arg_config.arg_type {
+        ArgType::Flag => {
+            // For flags, use Count if multiple, SetTrue otherwise
+            if arg_config.multiple {
+                arg = arg.action(ArgAction::Count);
+            } else {
+                arg = arg.action(ArgAction::SetTrue);
+            }
+
+            // Add short option
+            if let Some(short) = arg_config.effective_short() {
+                arg = arg.short(short);
+            }
+
+            // Add long option (with fallback to name if neither short nor long specified)
+            if let Some(long) = arg_config.effective_long() {
+                arg = arg.long(long.to_string());
+            }
+        }
+        ArgType::Option => {
+            // For options, use Append if multiple, Set otherwise
+            if arg_config.multiple {
+                arg = arg.action(ArgAction::Append);
+            } else {
+                arg = arg.action(ArgAction::Set);
+            }
+
+            // Add short option
+            if let Some(short) = arg_config.effective_short() {
+                arg = arg.short(short);
+            }
+
+            // Add long option (with fallback to name if neither short nor long specified)
+            if let Some(long) = arg_config.effective_long() {
+                arg = arg.long(long.to_string());
+            }
+
+            // Set value name for help display
+            arg = arg.value_name("VALUE");
+
+            // Allow attached values like -ofile.txt. Schema v2 can narrow this to
+            // negative numbers so a following --flag is never swallowed as the value.
+            if arg_config.negative_numbers_only {
+                arg = arg.allow_negative_numbers(true);
+            } else {
+                arg = arg.allow_hyphen_values(true);
+            }
+
+            // Schema v2: Only accept --long=value
+            if arg_config.require_equals {
+                arg = arg.require_equals(true);
+            }
+        }
+        ArgType::Positional => {
+            arg = arg.index(*positional_index);
+            *positional_index += 1;
+
+            // Allow values that look like flags (e.g., after --)
+            arg = arg.allow_hyphen_values(true);
+
+            // For multiple positionals
+            if arg_config.multiple {
+                arg = arg.action(ArgAction::Append);
+            }
+        }
+    }
+
+    // Set required status
+    if arg_config.required {
+        arg = arg.required(true);
+    }
+
+    // Schema v2: Extra long names, listed in help
+    if !arg_config.aliases.is_empty() {
+        arg = arg.visible_aliases(&arg_config.aliases);
+    }
+
+    // Schema v2: Args that can't be given together with this one
+    if !arg_config.conflicts_with.is_empty() {
+        arg = arg.conflicts_with_all(&arg_config.conflicts_with);
+    }
+
+    // Schema v2: Args that must be given whenever this one is
+    for required in &arg_config.requires {
+        arg = arg.requires(required.clone());
+    }
+
+    // Set default value
+    match arg_config.default {
+        Some(DefaultValue::Single(ref default)) => {
+            arg = arg.default_value(default.clone());
+        }
+        Some(DefaultValue::Multiple(ref defaults)) => {
+            arg = arg.default_values(defaults.clone());
+        }
+        None => {}
+    }
+
+    // Set help text
+    match (&arg_config.help, arg_config.deprecation_message()) {
+        (Some(help), Some(deprecation)) => {
+            arg = arg.help(format!("{} [deprecated: {}]", help, deprecation));
+        }
+        (None, Some(deprecation)) => {
+            arg = arg.help(format!("[deprecated: {}]", deprecation));
+        }
+        (Some(help), None) => {
+            arg = arg.help(help.clone());
+        }
+        (None, None) => {}
+    }
+
+    if arg_config.next_line_help {
+        arg = arg.next_line_help(true);
+    }
+
+    // Schema v2: keep internal args out of help
+    if arg_config.hidden {
+        arg = arg.hide(true);
+    }
+
+    // Schema v2: Environment variable fallback (auto-env or custom)
+    if let Some(env_var) =
+        arg_config.effective_env_with_strip(prefix, schema_version, auto_env, strip_name_prefix)
+    {
+        // Strict mode: a variable set to "" counts as unset
+        let set_but_empty =
+            empty_env_unset && std::env::var_os(&env_var).is_some_and(|value| value.is_empty());
+        if !set_but_empty {
+            arg = arg.env(env_var);
+        }
+    }
+
+    // Schema v2: num_args range
+    if let Some(ref num_args) = arg_config.num_args {
+        if let Some(range) = parse_num_args_range(num_args) {
+            arg = arg.num_args(range);
+        }
+    }
+
+    // Schema v2: Value delimiter
+    if let Some(delim) = arg_config.delimiter {
+        arg = arg.value_delimiter(delim);
+    }
+
+    // Schema v2: Choices (possible values) - takes precedence over value_type
+    if let Some(ref choices) = arg_config.choices {
+        if arg_config.case_insensitive {
+            // Clap matches in any case but keeps the user's spelling, so map
+            // each value back to the declared choice
+            let declared = choices.clone();
+            arg = arg.ignore_case(true).value_parser(
+                clap::builder::PossibleValuesParser::new(choices.clone()).map(
+                    move |value: String| {
+                        declared
+                            .iter()
+                            .find(|choice| choice.eq_ignore_ascii_case(&value))
+                            .cloned()
+                            .unwrap_or(value)
+                    },
+                ),
+            );
+        } else {
+            arg = arg.value_parser(clap::builder::PossibleValuesParser::new(choices.clone()));
+        }
+    } else {
+        // Schema v2: Apply value_type parser if no choices specified
+        match arg_config.value_type {
+            ValueType::String => {} // Default, no special parser
+            ValueType::Int if arg_config.range_display().is_some() => {
+                arg = arg.value_parser(bounded_parser::<i64>(arg_config, |n| *n as f64));
+            }
+            ValueType::Int => {
+                arg = arg.value_parser(clap::value_parser!(i64));
+            }
+            ValueType::Bool => {
+                arg = arg.value_parser(clap::builder::PossibleValuesParser::new(["true", "false"]));
+            }
+            ValueType::Double if arg_config.range_display().is_some() => {
+                arg = arg.value_parser(bounded_parser::<f64>(arg_config, |n| *n));
+            }
+            ValueType::Double if arg_config.decimal_comma => {
+                let decimal = arg_config.clone();
+                arg = arg.value_parser(move |value: &str| {
+                    parse_finite_double(&decimal.decimal_point(value))
+                });
+            }
+            ValueType::Double => {
+                arg = arg.value_parser(parse_finite_double);
+            }
+            ValueType::Json => {
+                arg = arg.value_parser(parse_json_value);
+            }
+            ValueType::Path if arg_config.path_must_exist => {
+                arg = arg.value_parser(parse_existing_path);
+            }
+            ValueType::Path => {} // Any string is a path; nothing to check
+            ValueType::Base64 => {
+                arg = arg.value_parser(parse_base64_value);
+            }
+            ValueType::Base64Url => {
+                arg = arg.value_parser(parse_base64url_value);
+            }
+            ValueType::Hex => {
+                arg = arg.value_parser(normalize_hex);
+            }
+        }
+    }
+
+    arg
+}
+
+/// Check one raw `value` the way the arg's value parser would, so every
+/// invalid value can be reported at once. The error says what was expected,
+/// e.g. "expected int" or "value 99 out of range (1..=10)".
+pub(crate) fn check_value(arg_config: &ArgConfig, value: &str) -> Result<(), String> {
+    if let Some(ref choices) = arg_config.choices {
+        return match arg_config.matching_choice(value) {
+            Some(_) => Ok(()),
+            None => Err(format!("expected one of {}", choices.join(", "))),
+        };
+    }
+    if !arg_config.accepts(value) {
+        return Err(format!("expected {}", arg_config.value_type));
+    }
+    match arg_config.value_type {
+        ValueType::Int if arg_config.range_display().is_some() => {
+            bounded_parser::<i64>(arg_config, |n| *n as f64)(value).map(drop)
+        }
+        ValueType::Double if arg_config.range_display().is_some() => {
+            bounded_parser::<f64>(arg_config, |n| *n)(value).map(drop)
+        }
+        ValueType::Path if arg_config.path_must_exist => parse_existing_path(value).map(drop),
+        _ => Ok(()),
+    }
+}
+
+/// Value parser for a numeric arg with `min`/`max`: parses the number, then
+/// rejects it as "value N out of range (MIN..=MAX)" when outside the bounds.
+fn bounded_parser<T>(
+    arg_config: &ArgConfig,
+    as_f64: fn(&T) -> f64,
+) -> impl Fn(&str) -> Result<T, String> + Clone + Send + Sync + 'static
+where
+    T: std::str::FromStr + Send + Sync + Clone + 'static,
+    T::Err: std::fmt::Display,
+{
+    let bounds = arg_config.clone();
+    let range = arg_config.range_display().unwrap_or_default();
+    move |value: &str| {
+        let number = bounds
+            .decimal_point(value)
+            .parse::<T>()
+            .map_err(|e| e.to_string())?;
+        if !as_f64(&number).is_finite() {
+            Err(format!("{} is not a finite number", value))
+        } else if bounds.in_range(as_f64(&number)) {
+            Ok(number)
+        } else {
+            Err(format!("value {} out of range ({})", value, range))
+        }
+    }
+}
+
+/// Value parser for `value_type: path` with `path_must_exist`: checks that
+/// the file or directory exists and passes the original string through.
+fn parse_existing_path(value: &str) -> Result<String, String> {
+    if std::path::Path::new(value).exists() {
+        Ok(value.to_string())
+    } else {
+        Err(format!("path does not exist: {}", value))
+    }
+}
+
+/// Value parser for `value_type: json`: checks the value is well-formed JSON
+/// and passes the original string through unchanged.
+fn parse_json_value(value: &str) -> Result<String, String> {
+    serde_json::from_str::<serde_json::Value>(value)
+        .map(|_| value.to_string())
+        .map_err(|e| format!("invalid JSON: {}", e))
+}
+
+/// Value parser for `value_type: base64`: checks the value decodes and
+/// passes the original encoded string through unchanged.
+fn parse_base64_value(value: &str) -> Result<String, String> {
+    ValueType::Base64
+        .decode_base64(value)
+        .map(|_| value.to_string())
+        .map_err(|e| format!("invalid base64: {}", e))
+}
+
+/// Value parser for `value_type: base64url`; see `parse_base64_value`.
+fn parse_base64url_value(value: &str) -> Result<String, String> {
+    ValueType::Base64Url
+        .decode_base64(value)
+        .map(|_| value.to_string())
+        .map_err(|e| format!("invalid base64url: {}", e))
+}
+
+/// Parse a num_args string into a Clap ValueRange.
+fn parse_num_args_range(s: &str) -> Option<clap::builder::ValueRange> {
+    let s = s.trim();
+
+    // Single number, bare or as explicit "=N"
+    if let Ok(n) = s.strip_prefix('=').unwrap_or(s).parse::<usize>() {
+        return Some(clap::builder::ValueRange::new(n..=n));
+    }
+
+    // Range formats
+    if let Some(idx) = s.find("..") {
+        let start: usize = s[..idx].parse().ok()?;
+        let rest = &s[idx + 2..];
+
+        if rest.is_empty() {
+            // Unbounded: "N.."
+            return Some(clap::builder::ValueRange::new(start..));
+        }
+        if let Ok(end) = rest.parse::<usize>() {
+            // Exclusive: "N..M"
+            return Some(clap::builder::ValueRange::new(start..end));
+        }
+        if let Some(stripped) = rest.strip_prefix('=') {
+            if let Ok(end) = stripped.parse::<usize>() {
+                // Inclusive: "N..=M"
+                return Some(clap::builder::ValueRange::new(start..=end));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_num_args_formats() {
+        // Single number
+        assert!(parse_num_args_range("3").is_some());
+        // Unbounded
+        assert!(parse_num_args_range("1..").is_some());
+        // Exclusive range
+        assert!(parse_num_args_range("2..5").is_some());
+        // Inclusive range
+        assert!(parse_num_args_range("1..=3").is_some());
+        // Invalid
+        assert!(parse_num_args_range("abc").is_none());
+        assert!(parse_num_args_range("=abc").is_none());
+    }
+
+    #[test]
+    fn test_parse_num_args_exact_shorthand() {
+        let range = parse_num_args_range("=3").unwrap();
+        assert_eq!(range.min_values(), 3);
+        assert_eq!(range.max_values(), 3);
+        assert_eq!(range, parse_num_args_range("3").unwrap());
+    }
+
+    #[test]
+    fn test_built_command_enforces_value_type_and_choices() {
+        let config = Config::from_json(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "count", "long": "count", "type": "option", "value_type": "int"},
+                {"name": "format", "long": "format", "type": "option", "choices": ["json", "yaml"]}
+            ]
+        }"#,
+        )
+        .unwrap();
+        let cmd = build_command(&config, "test");
+
+        assert!(cmd
+            .clone()
+            .try_get_matches_from(["test", "--count", "abc"])
+            .is_err());
+        assert!(cmd
+            .clone()
+            .try_get_matches_from(["test", "--format", "xml"])
+            .is_err());
+        assert!(cmd
+            .try_get_matches_from(["test", "--count", "3", "--format", "yaml"])
+            .is_ok());
+    }
+}
@@ -1,6 +1,6 @@
 //! JSON configuration parsing and types for shclap.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// The minimum supported schema version.
@@ -11,8 +11,11 @@ pub const MAX_SCHEMA_VERSION: u32 = 2;
 /// Errors that can occur during config parsing and validation.
 #[derive(Debug, Error)]
 pub enum ConfigError {
-    #[error("failed to parse JSON config: {0}")]
-    ParseError(#[from] serde_json::Error),
+    #[error(
+        "failed to parse JSON config: {0}{}",
+        .1.as_deref().map(|hint| format!(" (hint: {})", hint)).unwrap_or_default()
+    )]
+    ParseError(serde_json::Error, Option<String>),
 
     #[error("duplicate argument name: {0}")]
     DuplicateName(String),
@@ -32,11 +35,23 @@ pub enum ConfigError {
     #[error("subcommands require schema_version >= 2")]
     SubcommandsRequireV2,
 
+    #[error("'auto_env' requires schema_version >= 2")]
+    AutoEnvRequiresV2,
+
+    #[error("config requires shclap {0} or newer, but this is shclap {1}")]
+    ShclapVersionTooOld(String, String),
+
+    #[error("invalid min_shclap_version '{0}': expected a version like '0.3.0'")]
+    InvalidMinShclapVersion(String),
+
     #[error("duplicate subcommand name: {0}")]
     DuplicateSubcommandName(String),
 
+    #[error("alias '{1}' on subcommand '{0}' is already a name or alias of subcommand '{2}'")]
+    SubcommandAliasCollision(String, String, String),
+
     #[error(
-        "invalid num_args format '{0}': expected a number or range like '1..', '2..5', or '1..=3'"
+        "invalid num_args format '{0}': expected a number like '3' or '=3', or a range like '1..', '2..5', or '1..=3'"
     )]
     InvalidNumArgsFormat(String),
 
@@ -46,15 +61,113 @@ pub enum ConfigError {
     #[error("'choices' on argument '{0}' has duplicate value: {1}")]
     DuplicateChoice(String, String),
 
-    #[error("'choices' cannot be used with flag type on argument '{0}'")]
+    #[error(
+        "'choices' requires type 'option' or 'positional', not 'flag', on argument '{0}'; \
+         a flag takes no value, so use \"type\": \"option\" to limit a value to these choices"
+    )]
     ChoicesOnFlag(String),
 
     #[error("'value_type' cannot be used with flag type on argument '{0}'")]
     ValueTypeOnFlag(String),
+
+    #[error("'choices' cannot be used with value_type '{1}' on argument '{0}'")]
+    ChoicesWithValueType(String, ValueType),
+
+    #[error("choice '{1}' on argument '{0}' is not a valid {2}")]
+    ChoiceInvalidForValueType(String, String, ValueType),
+
+    #[error("arguments '{0}' and '{1}' both map to variable name '{2}'")]
+    VariableNameCollision(String, String, String),
+
+    #[error("argument '{1}' maps to variable name '{2}', which is also set for argument '{0}'")]
+    DerivedVariableNameCollision(String, String, String),
+
+    #[error("optional positional '{0}' cannot come before required positional '{1}'")]
+    OptionalPositionalBeforeRequired(String, String),
+
+    #[error("'aliases' cannot be used with positional argument '{0}'")]
+    AliasesOnPositional(String),
+
+    #[error("alias '--{1}' on argument '{0}' is already a long option of argument '{2}'")]
+    AliasCollision(String, String, String),
+
+    #[error("'max_occurrences' on argument '{0}' requires type 'flag' with 'multiple: true'")]
+    MaxOccurrencesRequiresCountFlag(String),
+
+    #[error("'case_insensitive' on argument '{0}' requires 'choices'")]
+    CaseInsensitiveRequiresChoices(String),
+
+    #[error("'unique' on argument '{0}' requires an option or positional with 'multiple: true'")]
+    UniqueRequiresMultiple(String),
+
+    #[error("'decimal_comma' on argument '{0}' requires value_type 'double' without 'choices'")]
+    DecimalCommaRequiresDouble(String),
+
+    #[error("'require_equals' can only be used with option type on argument '{0}'")]
+    RequireEqualsOnNonOption(String),
+
+    #[error("'emit_index' on argument '{0}' requires 'choices'")]
+    EmitIndexRequiresChoices(String),
+
+    #[error("argument '{0}' is required, so its default can never apply; drop one of them")]
+    RequiredWithDefault(String),
+
+    #[error("array 'default' on argument '{0}' requires 'multiple: true'")]
+    DefaultArrayRequiresMultiple(String),
+
+    #[error("'negative_numbers_only' can only be used with option type on argument '{0}'")]
+    NegativeNumbersOnlyOnNonOption(String),
+
+    #[error("unknown field '{0}' in config (strict mode)")]
+    UnknownField(String),
+
+    #[error("argument '{0}' uses reserved {1} (strict mode)")]
+    ReservedName(String, String),
+
+    #[error(
+        "argument '{0}' conflicts with '{1}', which is not another argument in the same scope"
+    )]
+    UnknownConflictTarget(String, String),
+
+    #[error("argument '{0}' requires '{1}', which is not another argument in the same scope")]
+    UnknownRequiresTarget(String, String),
+
+    #[error("argument '{0}' conflicts with unknown subcommand '{1}'")]
+    UnknownConflictingSubcommand(String, String),
+
+    #[error("argument '{0}' is deprecated in favor of unknown argument '{1}'")]
+    UnknownDeprecatedInFavorOf(String, String),
+
+    #[error("'grouped' on argument '{0}' requires 'multiple' and 'num_args'")]
+    GroupedRequiresMultiple(String),
+
+    #[error("default '{1}' on argument '{0}' is not one of its choices")]
+    DefaultNotInChoices(String, String),
+
+    #[error("default '{1}' on argument '{0}' is not a valid {2}")]
+    InvalidDefaultForValueType(String, String, ValueType),
+
+    #[error("'path_must_exist' on argument '{0}' requires value_type 'path'")]
+    PathMustExistRequiresPath(String),
+
+    #[error("'min'/'max' on argument '{0}' require value_type 'int' or 'double'")]
+    RangeOnNonNumeric(String),
+
+    #[error("argument '{0}' has min {1} greater than max {2}")]
+    InvalidRange(String, f64, f64),
+
+    #[error("default '{1}' on argument '{0}' is out of range ({2})")]
+    DefaultOutOfRange(String, String, String),
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(err: serde_json::Error) -> Self {
+        ConfigError::ParseError(err, None)
+    }
 }
 
 /// The type of argument.
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ArgType {
     /// A boolean flag (e.g., --verbose)
@@ -67,7 +180,7 @@ pub enum ArgType {
 
 /// Value type for validation (schema_version >= 2).
 /// Determines how argument values are validated.
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum ValueType {
     /// Any string value (default, no validation)
@@ -79,6 +192,106 @@ pub enum ValueType {
     Bool,
     /// 64-bit floating-point number
     Double,
+    /// Well-formed JSON document, emitted unchanged
+    Json,
+    /// Filesystem path; must exist only when `path_must_exist` is set
+    Path,
+    /// Standard base64 (`+` and `/`, with `=` padding), emitted unchanged
+    Base64,
+    /// URL-safe base64 (`-` and `_`, with `=` padding), emitted unchanged
+    Base64Url,
+    /// Even-length hex string, optionally `0x`-prefixed; emitted lowercase without the prefix
+    Hex,
+}
+
+/// What to do with undefined variables when expanding `expand_env` values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UndefinedEnv {
+    /// Undefined variables expand to an empty string (default)
+    #[default]
+    Empty,
+    /// Undefined variables are a parse error
+    Error,
+}
+
+impl ValueType {
+    /// Get the name of this value type as used in the JSON config.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValueType::String => "string",
+            ValueType::Int => "int",
+            ValueType::Bool => "bool",
+            ValueType::Double => "double",
+            ValueType::Json => "json",
+            ValueType::Path => "path",
+            ValueType::Base64 => "base64",
+            ValueType::Base64Url => "base64url",
+            ValueType::Hex => "hex",
+        }
+    }
+
+    /// Check whether a raw string value is valid for this value type.
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            ValueType::String => true,
+            ValueType::Int => value.parse::<i64>().is_ok(),
+            ValueType::Bool => value == "true" || value == "false",
+            ValueType::Double => parse_finite_double(value).is_ok(),
+            ValueType::Json => serde_json::from_str::<serde_json::Value>(value).is_ok(),
+            ValueType::Path => true,
+            ValueType::Base64 | ValueType::Base64Url => self.decode_base64(value).is_ok(),
+            ValueType::Hex => normalize_hex(value).is_ok(),
+        }
+    }
+
+    /// Decode `value` with this type's base64 alphabet; any other type is an error.
+    pub fn decode_base64(&self, value: &str) -> Result<Vec<u8>, base64::DecodeError> {
+        use base64::engine::general_purpose::{STANDARD, URL_SAFE};
+        use base64::Engine;
+
+        match self {
+            ValueType::Base64Url => URL_SAFE.decode(value),
+            _ => STANDARD.decode(value),
+        }
+    }
+}
+
+/// Parse a `double` value, rejecting `nan`, `inf` and values too large for
+/// an f64, which no shell arithmetic can use.
+pub(crate) fn parse_finite_double(value: &str) -> Result<f64, String> {
+    let number = value.parse::<f64>().map_err(|e| e.to_string())?;
+    if number.is_finite() {
+        Ok(number)
+    } else {
+        Err(format!("{} is not a finite number", value))
+    }
+}
+
+/// Check a `hex` value and return it lowercase without its `0x`/`0X` prefix.
+// `is_multiple_of` needs Rust 1.87, newer than the pinned toolchain
+#[allow(unknown_lints, clippy::manual_is_multiple_of)]
+pub(crate) fn normalize_hex(value: &str) -> Result<String, String> {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    if let Some(bad) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex: '{}' is not a hex digit", bad));
+    }
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return Err(format!(
+            "invalid hex: expected an even number of digits, got {}",
+            digits.len()
+        ));
+    }
+    Ok(digits.to_ascii_lowercase())
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// Environment variable fallback setting (schema_version >= 2).
@@ -95,6 +308,18 @@ pub enum EnvSetting {
     Custom(String),
 }
 
+impl Serialize for EnvSetting {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            EnvSetting::Disabled => serializer.serialize_bool(false),
+            EnvSetting::Custom(name) => serializer.serialize_str(name),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for EnvSetting {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -145,8 +370,156 @@ impl<'de> Deserialize<'de> for EnvSetting {
     }
 }
 
+/// Default value for an argument.
+///
+/// - `"x"`: a single value
+/// - `["a", "b"]`: several values, for `multiple` arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefaultValue {
+    /// A single value, `default: "x"`
+    Single(String),
+    /// Several values, `default: ["a", "b"]`
+    Multiple(Vec<String>),
+}
+
+impl DefaultValue {
+    /// All values of this default, one for `Single`.
+    pub fn values(&self) -> &[String] {
+        match self {
+            DefaultValue::Single(value) => std::slice::from_ref(value),
+            DefaultValue::Multiple(values) => values,
+        }
+    }
+}
+
+impl std::fmt::Display for DefaultValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.values().join(", "))
+    }
+}
+
+impl Serialize for DefaultValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            DefaultValue::Single(value) => serializer.serialize_str(value),
+            DefaultValue::Multiple(values) => values.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DefaultValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{self, SeqAccess, Visitor};
+
+        struct DefaultValueVisitor;
+
+        impl<'de> Visitor<'de> for DefaultValueVisitor {
+            type Value = DefaultValue;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a string or an array of strings")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(DefaultValue::Single(value.to_string()))
+            }
+
+            fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(DefaultValue::Single(value))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut values = Vec::new();
+                while let Some(value) = seq.next_element::<String>()? {
+                    values.push(value);
+                }
+                Ok(DefaultValue::Multiple(values))
+            }
+        }
+
+        deserializer.deserialize_any(DefaultValueVisitor)
+    }
+}
+
+/// Deserialize a `help` field given either inline (`"help": "..."`) or as a
+/// file reference (`"help": {"file": "path/to/help.txt"}`). Referenced files
+/// are read at load time, relative to the working directory.
+fn deserialize_help<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::{self, MapAccess, Visitor};
+
+    struct HelpVisitor;
+
+    impl<'de> Visitor<'de> for HelpVisitor {
+        type Value = Option<String>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a string or an object with a \"file\" path")
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(None)
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Some(value.to_string()))
+        }
+
+        fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Some(value))
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut file: Option<String> = None;
+            while let Some(key) = map.next_key::<String>()? {
+                match key.as_str() {
+                    "file" => file = Some(map.next_value()?),
+                    other => return Err(de::Error::unknown_field(other, &["file"])),
+                }
+            }
+            let file = file.ok_or_else(|| de::Error::missing_field("file"))?;
+            let text = std::fs::read_to_string(&file).map_err(|e| {
+                de::Error::custom(format!("cannot read help file '{}': {}", file, e))
+            })?;
+            // Files usually end with a newline that isn't part of the help text
+            Ok(Some(text.trim_end().to_string()))
+        }
+    }
+
+    deserializer.deserialize_any(HelpVisitor)
+}
+
 /// Configuration for a single argument.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArgConfig {
     /// The name of the argument (used for the environment variable)
     pub name: String,
@@ -160,9 +533,10 @@ pub struct ArgConfig {
     /// Whether this argument is required
     #[serde(default)]
     pub required: bool,
-    /// Default value if not provided
-    pub default: Option<String>,
-    /// Help text for this argument
+    /// Default value if not provided; an array seeds several values for `multiple`
+    pub default: Option<DefaultValue>,
+    /// Help text for this argument, inline or loaded from `{"file": "..."}`
+    #[serde(default, deserialize_with = "deserialize_help")]
     pub help: Option<String>,
 
     // Schema version 2 fields:
@@ -185,18 +559,111 @@ pub struct ArgConfig {
     /// Options: "string" (default), "int", "bool", "double"
     #[serde(default)]
     pub value_type: ValueType,
+    /// Expand `$VAR`/`${VAR}` in provided values (schema_version >= 2)
+    #[serde(default)]
+    pub expand_env: bool,
+    /// Keep values grouped per occurrence; needs `multiple` and `num_args` (schema_version >= 2)
+    #[serde(default)]
+    pub grouped: bool,
+    /// Name of the argument replacing this one; marks this one deprecated (schema_version >= 2)
+    pub deprecated_in_favor_of: Option<String>,
+    /// Only accept `--long=value`, rejecting `--long value` (schema_version >= 2)
+    #[serde(default)]
+    pub require_equals: bool,
+    /// Accept values starting with `-` only when they are negative numbers, so a
+    /// following `--flag` is never taken as the value (schema_version >= 2)
+    #[serde(default)]
+    pub negative_numbers_only: bool,
+    /// Show this argument's help on the line below it rather than beside it
+    #[serde(default)]
+    pub next_line_help: bool,
+    /// Emit the zero-based index of the matched choice instead of its text (schema_version >= 2)
+    #[serde(default)]
+    pub emit_index: bool,
+    /// Subcommands this top-level argument can't be combined with (schema_version >= 2)
+    #[serde(default)]
+    pub conflicts_with_subcommand: Vec<String>,
+    /// Smallest accepted value for an int or double argument (schema_version >= 2)
+    pub min: Option<f64>,
+    /// Largest accepted value for an int or double argument (schema_version >= 2)
+    pub max: Option<f64>,
+    /// Require a `path` value to name an existing file or directory (schema_version >= 2)
+    #[serde(default)]
+    pub path_must_exist: bool,
+    /// Names of args in the same scope that can't be given together with this one (schema_version >= 2)
+    #[serde(default)]
+    pub conflicts_with: Vec<String>,
+    /// Names of args in the same scope that must also be given when this one is (schema_version >= 2)
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Extra long names for a flag or option, shown in help (schema_version >= 2)
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Most times a counting flag may be given, e.g. 3 allows `-vvv` but not `-vvvv`
+    /// (schema_version >= 2)
+    pub max_occurrences: Option<usize>,
+    /// Match `choices` in any case, storing the value as spelled in `choices`
+    /// (schema_version >= 2)
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Drop repeated values of a `multiple` arg, keeping the first of each;
+    /// zsh output also marks the array `typeset -U` (schema_version >= 2)
+    #[serde(default)]
+    pub unique: bool,
+    /// Leave the argument out of help output; it still parses normally (schema_version >= 2)
+    #[serde(default)]
+    pub hidden: bool,
+    /// Also accept a comma as the decimal separator of a `double` (e.g. `1,5`);
+    /// the value is still output with a dot (schema_version >= 2)
+    #[serde(default)]
+    pub decimal_comma: bool,
+
+    // Computed fields (not part of the JSON schema):
+    /// 1-based index this positional receives, in declaration order.
+    /// Populated by `Config::compute_positional_indices`; `None` for flags and options.
+    #[serde(skip)]
+    pub positional_index: Option<usize>,
 }
 
 /// Configuration for a subcommand (schema_version >= 2).
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubcommandConfig {
     /// The name of the subcommand
     pub name: String,
-    /// Help text for this subcommand
+    /// Help text for this subcommand, inline or loaded from `{"file": "..."}`
+    #[serde(default, deserialize_with = "deserialize_help")]
     pub help: Option<String>,
     /// Arguments for this subcommand
     #[serde(default)]
     pub args: Vec<ArgConfig>,
+    /// Leave the subcommand out of help and `list-subcommands`; it still parses
+    #[serde(default)]
+    pub hidden: bool,
+    /// Nested subcommands (e.g. `add` under `remote`); when present, one is required
+    #[serde(default)]
+    pub subcommands: Vec<SubcommandConfig>,
+    /// Other names the subcommand can be invoked by (e.g. `rm` for `remove`);
+    /// `SUBCOMMAND` always holds the canonical name
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Free text shown below the subcommand's options in its help
+    pub after_help: Option<String>,
+}
+
+/// Extra build metadata shown in extended version output.
+///
+/// Any field left unset is omitted, except `build_date` and `git_hash`, which
+/// fall back to the `VERGEN_BUILD_DATE` and `VERGEN_GIT_SHA` environment variables.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct VersionInfo {
+    /// Build date (falls back to `$VERGEN_BUILD_DATE`, then "unknown")
+    pub build_date: Option<String>,
+    /// Git commit hash (falls back to `$VERGEN_GIT_SHA`)
+    pub git_hash: Option<String>,
+    /// Target architecture
+    pub arch: Option<String>,
+    /// Target operating system
+    pub os: Option<String>,
 }
 
 fn default_schema_version() -> u32 {
@@ -204,7 +671,7 @@ fn default_schema_version() -> u32 {
 }
 
 /// Top-level configuration for a script.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     /// Schema version for the config format (default: 1)
     #[serde(default = "default_schema_version")]
@@ -213,6 +680,9 @@ pub struct Config {
     pub name: Option<String>,
     /// Description of the script
     pub description: Option<String>,
+    /// Detailed description shown instead of `description` in full `--help`
+    /// output; `-h` keeps the one-line `description`
+    pub long_description: Option<String>,
     /// Version of the script
     pub version: Option<String>,
     /// Environment variable prefix (default: "SHCLAP_")
@@ -223,19 +693,294 @@ pub struct Config {
     /// Subcommands (schema_version >= 2)
     #[serde(default)]
     pub subcommands: Vec<SubcommandConfig>,
+    /// Common prefix stripped from arg names before building variable names
+    /// (e.g. "opt_" turns `opt_output` into `PREFIX_OUTPUT`)
+    pub strip_name_prefix: Option<String>,
+    /// Policy for undefined variables in `expand_env` values: "empty" (default) or "error"
+    #[serde(default)]
+    pub undefined_env: UndefinedEnv,
+    /// Build metadata; when set, version output uses the extended format
+    pub version_info: Option<VersionInfo>,
+    /// Show help when the script is invoked with no arguments
+    #[serde(default)]
+    pub help_if_no_args: bool,
+    /// Clap help template (e.g. "{usage-heading} {usage}\n\n{about}\n\n{all-args}")
+    pub help_template: Option<String>,
+    /// Author or contact shown in the help footer
+    pub author: Option<String>,
+    /// Project homepage shown in the help footer
+    pub homepage: Option<String>,
+    /// Banner shown above the usage line in help
+    pub before_help: Option<String>,
+    /// Free text shown below the options in help, such as examples or where
+    /// to report bugs; precedes the `author`/`homepage` lines
+    pub after_help: Option<String>,
+    /// Show every argument's help on the line below it rather than beside it
+    #[serde(default)]
+    pub next_line_help: bool,
+    /// Accept unambiguous prefixes of long options (e.g. `--verb` for `--verbose`)
+    #[serde(default)]
+    pub allow_abbreviations: bool,
+    /// Accept unambiguous prefixes of subcommand names (e.g. `in` for `init`)
+    #[serde(default)]
+    pub infer_subcommands: bool,
+    /// Oldest shclap release that can handle this config (e.g. "0.3.0")
+    pub min_shclap_version: Option<String>,
+    /// Uppercase the prefix so `myapp_` yields `MYAPP_OUTPUT`, matching the name part
+    #[serde(default)]
+    pub uppercase_prefix: bool,
+    /// Enable every strict check: unknown config fields, reserved names, and
+    /// empty environment variables treated as unset
+    #[serde(default)]
+    pub strict: bool,
+    /// Give args without an `env` field an auto-env fallback (default: true);
+    /// `false` makes env fallback opt-in via `env: "VAR"` (schema_version >= 2)
+    pub auto_env: Option<bool>,
+    /// List flags and options before positionals in help, whatever the declaration order
+    #[serde(default)]
+    pub group_by_kind: bool,
 }
 
 impl Config {
     /// Parse a JSON string into a Config.
+    ///
+    /// If parsing fails and the input looks like YAML, the error carries a hint.
     pub fn from_json(json: &str) -> Result<Config, ConfigError> {
-        let config: Config = serde_json::from_str(json)?;
+        let mut config: Config = serde_json::from_str(json).map_err(|e| {
+            let hint = looks_like_yaml(json)
+                .then(|| "this looks like YAML — --config expects JSON".to_string());
+            ConfigError::ParseError(e, hint)
+        })?;
+        config.compute_positional_indices();
+        if config.strict {
+            config.check_unknown_fields(json)?;
+        }
         Ok(config)
     }
 
+    /// Reject keys in `json` that don't match any config field, such as a
+    /// misspelled `"requird"`. `from_json` runs this itself for strict configs.
+    pub fn check_unknown_fields(&self, json: &str) -> Result<(), ConfigError> {
+        let raw: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| ConfigError::ParseError(e, None))?;
+        // Serializing the parsed config yields every known key, under its JSON name
+        let known = serde_json::to_value(self).map_err(|e| ConfigError::ParseError(e, None))?;
+        match find_unknown_field(&raw, &known, "") {
+            Some(path) => Err(ConfigError::UnknownField(path)),
+            None => Ok(()),
+        }
+    }
+
+    /// Assign 1-based positional indices to positional args, in declaration order.
+    ///
+    /// Top-level args and each subcommand's args are numbered independently,
+    /// matching the indices given to Clap when the command is built.
+    pub fn compute_positional_indices(&mut self) {
+        assign_positional_indices(&mut self.args);
+        let mut pending: Vec<&mut SubcommandConfig> = self.subcommands.iter_mut().collect();
+        while let Some(subcmd) = pending.pop() {
+            assign_positional_indices(&mut subcmd.args);
+            pending.extend(subcmd.subcommands.iter_mut());
+        }
+    }
+
+    /// Every subcommand at any depth, depth-first, with its dotted path
+    /// (e.g. `remote.add`), which is also the parsed `SUBCOMMAND` value.
+    pub fn all_subcommands(&self) -> Vec<(String, &SubcommandConfig)> {
+        fn walk<'a>(
+            out: &mut Vec<(String, &'a SubcommandConfig)>,
+            parent: Option<&str>,
+            subcommands: &'a [SubcommandConfig],
+        ) {
+            for subcmd in subcommands {
+                let path = match parent {
+                    Some(parent) => format!("{}.{}", parent, subcmd.name),
+                    None => subcmd.name.clone(),
+                };
+                out.push((path.clone(), subcmd));
+                walk(out, Some(&path), &subcmd.subcommands);
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(&mut out, None, &self.subcommands);
+        out
+    }
+
+    /// The subcommands along a dotted `path` (e.g. `remote.add` yields
+    /// `remote`, then `add`), or `None` if any step doesn't exist.
+    pub fn subcommand_chain(&self, path: &str) -> Option<Vec<&SubcommandConfig>> {
+        let mut chain = Vec::new();
+        let mut level = &self.subcommands;
+        for name in path.split('.') {
+            let subcmd = level.iter().find(|s| s.name == name)?;
+            chain.push(subcmd);
+            level = &subcmd.subcommands;
+        }
+        Some(chain)
+    }
+
+    /// Expected number of output variables for a parse: one per top-level arg,
+    /// plus one for the subcommand. Used to pre-size the parsed value map.
+    pub fn expected_output_count(&self) -> usize {
+        self.args.len() + 1
+    }
+
+    /// List every variable name a successful parse could set, in output order:
+    /// `{prefix}SUBCOMMAND` (if subcommands exist), then top-level args, then
+    /// subcommand args. Names shared by several subcommands appear once.
+    pub fn expected_env_vars(&self, prefix: &str) -> Vec<String> {
+        self.expected_env_vars_with(prefix, false, false)
+    }
+
+    /// Like `expected_env_vars`, but each arg is followed by the `_GROUPS`,
+    /// `_COUNT` (with `posix`) and `_OCCURRENCES` (with `occurrences`)
+    /// variables derived from it. Numbered `_1`..`_N` variables are left out:
+    /// without their count they are never read.
+    pub fn expected_env_vars_with(
+        &self,
+        prefix: &str,
+        posix: bool,
+        occurrences: bool,
+    ) -> Vec<String> {
+        let mut vars: Vec<String> = Vec::new();
+        if !self.subcommands.is_empty() {
+            vars.push(format!("{}SUBCOMMAND", prefix));
+        }
+
+        let subcommands = self.all_subcommands();
+        let all_args = self
+            .args
+            .iter()
+            .chain(subcommands.iter().flat_map(|(_, s)| &s.args));
+        for arg in all_args {
+            let var = self.arg_var_name(&arg.name, prefix);
+            let (suffixes, _) = derived_var_suffixes(arg, posix, occurrences);
+            let derived = suffixes.iter().map(|suffix| format!("{}_{}", var, suffix));
+            for name in std::iter::once(var.clone()).chain(derived) {
+                if !vars.contains(&name) {
+                    vars.push(name);
+                }
+            }
+        }
+        vars
+    }
+
+    /// Required top-level args the environment alone would leave without a
+    /// value, each paired with the variable that would supply it (`None` when
+    /// env fallback is off for the arg). `lookup` reads a variable; in strict
+    /// mode an empty value counts as unset, as it does when parsing.
+    pub fn unsatisfied_env_args(
+        &self,
+        prefix: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Vec<(&ArgConfig, Option<String>)> {
+        self.args
+            .iter()
+            .filter(|arg| arg.required)
+            .filter_map(|arg| {
+                let var = arg.effective_env_with_strip(
+                    prefix,
+                    self.schema_version,
+                    self.auto_env_enabled(),
+                    self.strip_name_prefix.as_deref(),
+                );
+                let satisfied = var
+                    .as_deref()
+                    .and_then(&lookup)
+                    .is_some_and(|value| !(self.strict && value.is_empty()));
+                (!satisfied).then_some((arg, var))
+            })
+            .collect()
+    }
+
+    /// Help footer built from `after_help`, then `author` and `homepage`,
+    /// if any is set.
+    pub fn help_footer(&self) -> Option<String> {
+        let lines: Vec<String> = [("Author", &self.author), ("Homepage", &self.homepage)]
+            .into_iter()
+            .filter_map(|(label, value)| value.as_ref().map(|v| format!("{}: {}", label, v)))
+            .collect();
+        let contact = (!lines.is_empty()).then(|| lines.join("\n"));
+        match (self.after_help.as_deref(), contact) {
+            (Some(text), Some(contact)) => Some(format!("{}\n\n{}", text, contact)),
+            (Some(text), None) => Some(text.to_string()),
+            (None, contact) => contact,
+        }
+    }
+
+    /// Output variable name for an arg, after `strip_name_prefix` (e.g. `SHCLAP_OUTPUT`).
+    pub fn arg_var_name(&self, arg_name: &str, prefix: &str) -> String {
+        format!("{}{}", prefix, to_var_suffix(self.strip_arg_name(arg_name)))
+    }
+
+    /// Stable SHA-256 hex digest of the config, for use as a cache key.
+    ///
+    /// Hashes the parsed config rather than the input text, so key order,
+    /// whitespace, and spelled-out defaults don't change the fingerprint.
+    pub fn fingerprint(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        // Struct fields serialize in declaration order, giving a canonical form
+        let canonical = serde_json::to_vec(self).unwrap_or_default();
+        Sha256::digest(&canonical)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Look up a top-level positional argument by its 1-based index.
+    pub fn positional_by_index(&self, index: usize) -> Option<&ArgConfig> {
+        self.args
+            .iter()
+            .find(|arg| arg.positional_index == Some(index))
+    }
+
+    /// Return a copy of the config with common, non-destructive mistakes corrected.
+    ///
+    /// - Bumps `schema_version` to 2 when v2-only fields or subcommands are used
+    /// - Trims trailing whitespace from names and help text
+    /// - Drops duplicate `choices`, keeping the first occurrence
+    /// - Normalises `long` names to lowercase-with-hyphens
+    pub fn autofix(&self) -> Config {
+        let mut fixed = self.clone();
+
+        if fixed.schema_version < 2
+            && (!fixed.subcommands.is_empty() || fixed.args.iter().any(ArgConfig::uses_v2_features))
+        {
+            fixed.schema_version = 2;
+        }
+
+        if let Some(ref mut name) = fixed.name {
+            trim_end_in_place(name);
+        }
+        for arg in &mut fixed.args {
+            autofix_arg(arg);
+        }
+        let mut pending: Vec<&mut SubcommandConfig> = fixed.subcommands.iter_mut().collect();
+        while let Some(subcmd) = pending.pop() {
+            trim_end_in_place(&mut subcmd.name);
+            if let Some(ref mut help) = subcmd.help {
+                trim_end_in_place(help);
+            }
+            for arg in &mut subcmd.args {
+                autofix_arg(arg);
+            }
+            pending.extend(subcmd.subcommands.iter_mut());
+        }
+
+        fixed
+    }
+
     /// Validate the configuration.
     pub fn validate(&self) -> Result<(), ConfigError> {
         use std::collections::HashSet;
 
+        // Fail fast on binaries too old for this config, before anything else
+        if let Some(ref required) = self.min_shclap_version {
+            check_shclap_version(required, env!("CARGO_PKG_VERSION"))?;
+        }
+
         // Validate schema version
         if self.schema_version < MIN_SCHEMA_VERSION || self.schema_version > MAX_SCHEMA_VERSION {
             return Err(ConfigError::UnsupportedSchemaVersion(self.schema_version));
@@ -246,6 +991,9 @@ impl Config {
             if !self.subcommands.is_empty() {
                 return Err(ConfigError::SubcommandsRequireV2);
             }
+            if self.auto_env.is_some() {
+                return Err(ConfigError::AutoEnvRequiresV2);
+            }
             for arg in &self.args {
                 Self::validate_no_v2_fields(arg)?;
             }
@@ -261,40 +1009,288 @@ impl Config {
 
             Self::validate_arg(arg, self.schema_version)?;
         }
+        self.validate_var_names(&self.args)?;
+        if self.strict {
+            self.validate_reserved_names(&self.args)?;
+        }
+        Self::validate_deprecations(&self.args)?;
+        Self::validate_conflicts(&self.args)?;
+        Self::validate_requires(&self.args)?;
+        Self::validate_aliases(&self.args)?;
+        Self::validate_positional_order(&self.args)?;
 
         // Validate subcommands
         if self.schema_version >= 2 {
-            let mut subcmd_names = HashSet::new();
-            for subcmd in &self.subcommands {
-                if !subcmd_names.insert(&subcmd.name) {
-                    return Err(ConfigError::DuplicateSubcommandName(subcmd.name.clone()));
+            self.validate_subcommands(&self.subcommands)?;
+            Self::validate_subcommand_conflicts(&self.args, &self.subcommands)?;
+        }
+
+        Ok(())
+    }
+
+    /// Validate one level of subcommands and, recursively, their children.
+    /// Names must be unique among siblings; args are checked per subcommand.
+    fn validate_subcommands(&self, subcommands: &[SubcommandConfig]) -> Result<(), ConfigError> {
+        use std::collections::{HashMap, HashSet};
+
+        let mut subcmd_names = HashSet::new();
+        for subcmd in subcommands {
+            if !subcmd_names.insert(&subcmd.name) {
+                return Err(ConfigError::DuplicateSubcommandName(subcmd.name.clone()));
+            }
+        }
+
+        // Every name and alias must pick out a single sibling
+        let mut invoked_as: HashMap<&str, &str> = subcommands
+            .iter()
+            .map(|subcmd| (subcmd.name.as_str(), subcmd.name.as_str()))
+            .collect();
+        for subcmd in subcommands {
+            for alias in &subcmd.aliases {
+                if let Some(owner) = invoked_as.insert(alias, &subcmd.name) {
+                    return Err(ConfigError::SubcommandAliasCollision(
+                        subcmd.name.clone(),
+                        alias.clone(),
+                        owner.to_string(),
+                    ));
                 }
+            }
+        }
 
-                let mut subcmd_arg_names = HashSet::new();
-                for arg in &subcmd.args {
-                    if !subcmd_arg_names.insert(&arg.name) {
-                        return Err(ConfigError::DuplicateName(arg.name.clone()));
-                    }
-                    Self::validate_arg(arg, self.schema_version)?;
+        for subcmd in subcommands {
+            let mut subcmd_arg_names = HashSet::new();
+            for arg in &subcmd.args {
+                if !subcmd_arg_names.insert(&arg.name) {
+                    return Err(ConfigError::DuplicateName(arg.name.clone()));
                 }
+                Self::validate_arg(arg, self.schema_version)?;
+            }
+            self.validate_var_names(&subcmd.args)?;
+            if self.strict {
+                self.validate_reserved_names(&subcmd.args)?;
             }
+            Self::validate_deprecations(&subcmd.args)?;
+            Self::validate_conflicts(&subcmd.args)?;
+            Self::validate_requires(&subcmd.args)?;
+            Self::validate_aliases(&subcmd.args)?;
+            Self::validate_positional_order(&subcmd.args)?;
+            // A subcommand's args may conflict with its own children
+            Self::validate_subcommand_conflicts(&subcmd.args, &subcmd.subcommands)?;
+            self.validate_subcommands(&subcmd.subcommands)?;
         }
+        Ok(())
+    }
 
+    /// Validate that no two args in the same scope produce the same variable name.
+    fn validate_var_names(&self, args: &[ArgConfig]) -> Result<(), ConfigError> {
+        use std::collections::HashMap;
+
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for arg in args {
+            let var_name = to_var_suffix(self.strip_arg_name(&arg.name));
+            if let Some(other) = seen.insert(var_name.clone(), &arg.name) {
+                return Err(ConfigError::VariableNameCollision(
+                    other.to_string(),
+                    arg.name.clone(),
+                    var_name,
+                ));
+            }
+        }
         Ok(())
     }
 
-    /// Validate that an argument doesn't use v2-only fields.
-    fn validate_no_v2_fields(arg: &ArgConfig) -> Result<(), ConfigError> {
-        if arg.env.is_some() {
-            return Err(ConfigError::FieldRequiresV2(
-                "env".to_string(),
-                arg.name.clone(),
-            ));
+    /// Reject args whose variable name is also one shclap derives from another
+    /// arg's: `_GROUPS` and `_0`..`_N` for grouped values, `_COUNT` and
+    /// `_1`..`_N` for multiple values with `posix`, and `_OCCURRENCES` for
+    /// multiple options with `occurrences`. Subcommand args are checked
+    /// together with their parents', since they are emitted together.
+    pub fn validate_derived_var_names(
+        &self,
+        posix: bool,
+        occurrences: bool,
+    ) -> Result<(), ConfigError> {
+        let mut scopes: Vec<Vec<&ArgConfig>> = vec![self.args.iter().collect()];
+        for (path, _) in self.all_subcommands() {
+            let chain = self.subcommand_chain(&path).unwrap_or_default();
+            scopes.push(
+                self.args
+                    .iter()
+                    .chain(chain.iter().flat_map(|subcmd| &subcmd.args))
+                    .collect(),
+            );
         }
-        if arg.multiple {
-            return Err(ConfigError::FieldRequiresV2(
-                "multiple".to_string(),
-                arg.name.clone(),
+
+        for args in scopes {
+            let vars: Vec<(&ArgConfig, String)> = args
+                .into_iter()
+                .map(|arg| (arg, to_var_suffix(self.strip_arg_name(&arg.name))))
+                .collect();
+            for (arg, var) in &vars {
+                let (suffixes, indexed) = derived_var_suffixes(arg, posix, occurrences);
+                for (other, other_var) in &vars {
+                    let Some(suffix) = other_var
+                        .strip_prefix(var.as_str())
+                        .and_then(|rest| rest.strip_prefix('_'))
+                    else {
+                        continue;
+                    };
+                    let index = !suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_digit());
+                    if suffixes.contains(&suffix) || (indexed && index) {
+                        return Err(ConfigError::DerivedVariableNameCollision(
+                            arg.name.clone(),
+                            other.name.clone(),
+                            other_var.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Strict mode: reject args that clash with names shclap or clap use
+    /// themselves: the `SUBCOMMAND` variable and the help/version options.
+    fn validate_reserved_names(&self, args: &[ArgConfig]) -> Result<(), ConfigError> {
+        for arg in args {
+            let reserved = if to_var_suffix(self.strip_arg_name(&arg.name)) == "SUBCOMMAND" {
+                Some("variable name SUBCOMMAND".to_string())
+            } else if let Some(short @ ('h' | 'V')) = arg.effective_short() {
+                Some(format!("short option -{}", short))
+            } else {
+                arg.long_with_prefix()
+                    .filter(|long| long == "--help" || long == "--version")
+                    .map(|long| format!("option {}", long))
+            };
+            if let Some(what) = reserved {
+                return Err(ConfigError::ReservedName(arg.name.clone(), what));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that no optional positional comes before a required one, since
+    /// a value could never reach the later positional.
+    fn validate_positional_order(args: &[ArgConfig]) -> Result<(), ConfigError> {
+        let mut optional: Option<&ArgConfig> = None;
+        for arg in args.iter().filter(|a| a.arg_type == ArgType::Positional) {
+            if arg.required {
+                if let Some(earlier) = optional {
+                    return Err(ConfigError::OptionalPositionalBeforeRequired(
+                        earlier.name.clone(),
+                        arg.name.clone(),
+                    ));
+                }
+            } else if optional.is_none() {
+                optional = Some(arg);
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that each `conflicts_with` entry names another arg in the same scope.
+    fn validate_conflicts(args: &[ArgConfig]) -> Result<(), ConfigError> {
+        for arg in args {
+            for target in &arg.conflicts_with {
+                if target == &arg.name || !args.iter().any(|other| &other.name == target) {
+                    return Err(ConfigError::UnknownConflictTarget(
+                        arg.name.clone(),
+                        target.clone(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that each `requires` entry names another arg in the same scope.
+    fn validate_requires(args: &[ArgConfig]) -> Result<(), ConfigError> {
+        for arg in args {
+            for target in &arg.requires {
+                if target == &arg.name || !args.iter().any(|other| &other.name == target) {
+                    return Err(ConfigError::UnknownRequiresTarget(
+                        arg.name.clone(),
+                        target.clone(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that no alias repeats a long option or alias already used in the same scope.
+    fn validate_aliases(args: &[ArgConfig]) -> Result<(), ConfigError> {
+        use std::collections::HashMap;
+
+        let mut longs: HashMap<&str, &str> = args
+            .iter()
+            .filter_map(|arg| Some((arg.effective_long()?, arg.name.as_str())))
+            .collect();
+        for arg in args {
+            for alias in &arg.aliases {
+                if let Some(owner) = longs.insert(alias, &arg.name) {
+                    return Err(ConfigError::AliasCollision(
+                        arg.name.clone(),
+                        alias.clone(),
+                        owner.to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that each `conflicts_with_subcommand` entry names one of `subcommands`.
+    fn validate_subcommand_conflicts(
+        args: &[ArgConfig],
+        subcommands: &[SubcommandConfig],
+    ) -> Result<(), ConfigError> {
+        for arg in args {
+            for conflict in &arg.conflicts_with_subcommand {
+                if !subcommands.iter().any(|subcmd| &subcmd.name == conflict) {
+                    return Err(ConfigError::UnknownConflictingSubcommand(
+                        arg.name.clone(),
+                        conflict.clone(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that each `deprecated_in_favor_of` names an arg in the same scope.
+    fn validate_deprecations(args: &[ArgConfig]) -> Result<(), ConfigError> {
+        for arg in args {
+            if let Some(ref replacement) = arg.deprecated_in_favor_of {
+                if !args.iter().any(|other| &other.name == replacement) {
+                    return Err(ConfigError::UnknownDeprecatedInFavorOf(
+                        arg.name.clone(),
+                        replacement.clone(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Strip the configured `strip_name_prefix` from an arg name.
+    ///
+    /// Names that don't start with the prefix, or would become empty, are returned unchanged.
+    pub fn strip_arg_name<'a>(&self, name: &'a str) -> &'a str {
+        strip_name(name, self.strip_name_prefix.as_deref())
+    }
+
+    /// Validate that an argument doesn't use v2-only fields.
+    fn validate_no_v2_fields(arg: &ArgConfig) -> Result<(), ConfigError> {
+        if arg.env.is_some() {
+            return Err(ConfigError::FieldRequiresV2(
+                "env".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.multiple {
+            return Err(ConfigError::FieldRequiresV2(
+                "multiple".to_string(),
+                arg.name.clone(),
             ));
         }
         if arg.num_args.is_some() {
@@ -321,6 +1317,109 @@ impl Config {
                 arg.name.clone(),
             ));
         }
+        if arg.expand_env {
+            return Err(ConfigError::FieldRequiresV2(
+                "expand_env".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.grouped {
+            return Err(ConfigError::FieldRequiresV2(
+                "grouped".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.deprecated_in_favor_of.is_some() {
+            return Err(ConfigError::FieldRequiresV2(
+                "deprecated_in_favor_of".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.require_equals {
+            return Err(ConfigError::FieldRequiresV2(
+                "require_equals".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.negative_numbers_only {
+            return Err(ConfigError::FieldRequiresV2(
+                "negative_numbers_only".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if !arg.aliases.is_empty() {
+            return Err(ConfigError::FieldRequiresV2(
+                "aliases".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if !arg.requires.is_empty() {
+            return Err(ConfigError::FieldRequiresV2(
+                "requires".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.max_occurrences.is_some() {
+            return Err(ConfigError::FieldRequiresV2(
+                "max_occurrences".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.case_insensitive {
+            return Err(ConfigError::FieldRequiresV2(
+                "case_insensitive".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.unique {
+            return Err(ConfigError::FieldRequiresV2(
+                "unique".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.hidden {
+            return Err(ConfigError::FieldRequiresV2(
+                "hidden".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.decimal_comma {
+            return Err(ConfigError::FieldRequiresV2(
+                "decimal_comma".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if !arg.conflicts_with.is_empty() {
+            return Err(ConfigError::FieldRequiresV2(
+                "conflicts_with".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.path_must_exist {
+            return Err(ConfigError::FieldRequiresV2(
+                "path_must_exist".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.min.is_some() || arg.max.is_some() {
+            let field = if arg.min.is_some() { "min" } else { "max" };
+            return Err(ConfigError::FieldRequiresV2(
+                field.to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if !arg.conflicts_with_subcommand.is_empty() {
+            return Err(ConfigError::FieldRequiresV2(
+                "conflicts_with_subcommand".to_string(),
+                arg.name.clone(),
+            ));
+        }
+        if arg.emit_index {
+            return Err(ConfigError::FieldRequiresV2(
+                "emit_index".to_string(),
+                arg.name.clone(),
+            ));
+        }
         Ok(())
     }
 
@@ -335,6 +1434,14 @@ impl Config {
             }
         }
 
+        // A default can never apply to an arg that must be given
+        if arg.required && arg.default.is_some() {
+            return Err(ConfigError::RequiredWithDefault(arg.name.clone()));
+        }
+        if matches!(arg.default, Some(DefaultValue::Multiple(_))) && !arg.multiple {
+            return Err(ConfigError::DefaultArrayRequiresMultiple(arg.name.clone()));
+        }
+
         // Note: We no longer error if neither short nor long is specified.
         // The name will be used as the long option when building the command.
 
@@ -345,6 +1452,40 @@ impl Config {
             }
             Self::validate_choices(arg)?;
             Self::validate_value_type(arg)?;
+            Self::validate_default(arg)?;
+            if arg.grouped && (!arg.multiple || arg.num_args.is_none()) {
+                return Err(ConfigError::GroupedRequiresMultiple(arg.name.clone()));
+            }
+            if !arg.aliases.is_empty() && arg.arg_type == ArgType::Positional {
+                return Err(ConfigError::AliasesOnPositional(arg.name.clone()));
+            }
+            if arg.max_occurrences.is_some() && (arg.arg_type != ArgType::Flag || !arg.multiple) {
+                return Err(ConfigError::MaxOccurrencesRequiresCountFlag(
+                    arg.name.clone(),
+                ));
+            }
+            if arg.case_insensitive && arg.choices.is_none() {
+                return Err(ConfigError::CaseInsensitiveRequiresChoices(
+                    arg.name.clone(),
+                ));
+            }
+            if arg.unique && (arg.arg_type == ArgType::Flag || !arg.multiple) {
+                return Err(ConfigError::UniqueRequiresMultiple(arg.name.clone()));
+            }
+            if arg.decimal_comma && (arg.value_type != ValueType::Double || arg.choices.is_some()) {
+                return Err(ConfigError::DecimalCommaRequiresDouble(arg.name.clone()));
+            }
+            if arg.require_equals && arg.arg_type != ArgType::Option {
+                return Err(ConfigError::RequireEqualsOnNonOption(arg.name.clone()));
+            }
+            if arg.emit_index && arg.choices.is_none() {
+                return Err(ConfigError::EmitIndexRequiresChoices(arg.name.clone()));
+            }
+            if arg.negative_numbers_only && arg.arg_type != ArgType::Option {
+                return Err(ConfigError::NegativeNumbersOnlyOnNonOption(
+                    arg.name.clone(),
+                ));
+            }
         }
 
         Ok(())
@@ -363,10 +1504,15 @@ impl Config {
                 return Err(ConfigError::EmptyChoices(arg.name.clone()));
             }
 
-            // Check for duplicates
+            // Check for duplicates (ignoring case when matching ignores it)
             let mut seen = std::collections::HashSet::new();
             for choice in choices {
-                if !seen.insert(choice) {
+                let key = if arg.case_insensitive {
+                    choice.to_ascii_lowercase()
+                } else {
+                    choice.clone()
+                };
+                if !seen.insert(key) {
                     return Err(ConfigError::DuplicateChoice(
                         arg.name.clone(),
                         choice.clone(),
@@ -383,6 +1529,80 @@ impl Config {
         if arg.value_type != ValueType::String && arg.arg_type == ArgType::Flag {
             return Err(ConfigError::ValueTypeOnFlag(arg.name.clone()));
         }
+        // A JSON document can't be usefully matched against a fixed list, and
+        // encoded payloads are opaque; listing either as choices makes no sense
+        if matches!(
+            arg.value_type,
+            ValueType::Json | ValueType::Base64 | ValueType::Base64Url | ValueType::Hex
+        ) && arg.choices.is_some()
+        {
+            return Err(ConfigError::ChoicesWithValueType(
+                arg.name.clone(),
+                arg.value_type.clone(),
+            ));
+        }
+        // Choices take precedence in the parser, so make sure they agree with the type
+        if let Some(ref choices) = arg.choices {
+            if let Some(bad) = choices.iter().find(|c| !arg.value_type.accepts(c)) {
+                return Err(ConfigError::ChoiceInvalidForValueType(
+                    arg.name.clone(),
+                    bad.clone(),
+                    arg.value_type.clone(),
+                ));
+            }
+        }
+        if arg.path_must_exist && arg.value_type != ValueType::Path {
+            return Err(ConfigError::PathMustExistRequiresPath(arg.name.clone()));
+        }
+        if arg.min.is_some() || arg.max.is_some() {
+            if !matches!(arg.value_type, ValueType::Int | ValueType::Double) {
+                return Err(ConfigError::RangeOnNonNumeric(arg.name.clone()));
+            }
+            if let (Some(min), Some(max)) = (arg.min, arg.max) {
+                if min > max {
+                    return Err(ConfigError::InvalidRange(arg.name.clone(), min, max));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that a default value satisfies the argument's choices and value_type.
+    fn validate_default(arg: &ArgConfig) -> Result<(), ConfigError> {
+        let Some(ref default) = arg.default else {
+            return Ok(());
+        };
+
+        for default in default.values() {
+            // Choices take precedence over value_type, matching the parser
+            if arg.choices.is_some() {
+                if arg.matching_choice(default).is_none() {
+                    return Err(ConfigError::DefaultNotInChoices(
+                        arg.name.clone(),
+                        default.clone(),
+                    ));
+                }
+            } else if !arg.accepts(default) {
+                return Err(ConfigError::InvalidDefaultForValueType(
+                    arg.name.clone(),
+                    default.clone(),
+                    arg.value_type.clone(),
+                ));
+            } else if let Some(range) = arg.range_display() {
+                // The type check above guarantees a numeric default here
+                if !arg
+                    .decimal_point(default)
+                    .parse::<f64>()
+                    .is_ok_and(|value| arg.in_range(value))
+                {
+                    return Err(ConfigError::DefaultOutOfRange(
+                        arg.name.clone(),
+                        default.clone(),
+                        range,
+                    ));
+                }
+            }
+        }
         Ok(())
     }
 
@@ -390,14 +1610,228 @@ impl Config {
     pub fn effective_prefix(&self) -> &str {
         self.prefix.as_deref().unwrap_or("SHCLAP_")
     }
+
+    /// Whether the config declares schema version 2 or later.
+    pub fn is_v2(&self) -> bool {
+        self.schema_version >= 2
+    }
+
+    /// Whether the config defines any subcommands.
+    pub fn uses_subcommands(&self) -> bool {
+        !self.subcommands.is_empty()
+    }
+
+    /// Lowest schema version that supports every field this config sets, so
+    /// authors can tell whether `schema_version` could be lowered. Note that
+    /// lowering it also turns off v2's automatic env fallback.
+    pub fn required_schema_version(&self) -> u32 {
+        let needs_v2 = self.uses_subcommands()
+            || self.auto_env.is_some()
+            || self.args.iter().any(ArgConfig::uses_v2_features);
+        if needs_v2 {
+            2
+        } else {
+            MIN_SCHEMA_VERSION
+        }
+    }
+
+    /// Whether args without an `env` field fall back to auto-env (default: true).
+    pub fn auto_env_enabled(&self) -> bool {
+        self.auto_env.unwrap_or(true)
+    }
+
+    /// Resolve the prefix used for variable names: the CLI override if given,
+    /// otherwise the configured prefix, uppercased when `uppercase_prefix` is set.
+    pub fn resolve_prefix(&self, cli_prefix: Option<&str>) -> String {
+        let prefix = cli_prefix.unwrap_or_else(|| self.effective_prefix());
+        if self.uppercase_prefix {
+            prefix.to_uppercase()
+        } else {
+            prefix.to_string()
+        }
+    }
+}
+
+/// Quick heuristic for YAML input: a `---` document start, or (outside a JSON
+/// object/array) a `key: value` or `- item` line.
+fn looks_like_yaml(input: &str) -> bool {
+    let trimmed = input.trim_start();
+    if trimmed.starts_with("---") {
+        return true;
+    }
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        return false;
+    }
+
+    trimmed.lines().any(|line| {
+        let line = line.trim();
+        if line.starts_with("- ") {
+            return true;
+        }
+        match line.split_once(':') {
+            Some((key, rest)) => {
+                !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                    && (rest.is_empty() || rest.starts_with(' '))
+            }
+            None => false,
+        }
+    })
+}
+
+/// Check that `actual` is at least the `required` version.
+///
+/// Versions are compared component by component (`major.minor.patch`), with
+/// missing components treated as 0.
+fn check_shclap_version(required: &str, actual: &str) -> Result<(), ConfigError> {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        let parts: Vec<u64> = version
+            .trim()
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect::<Option<_>>()?;
+        (1..=3).contains(&parts.len()).then(|| {
+            let mut padded = parts;
+            padded.resize(3, 0);
+            padded
+        })
+    };
+
+    let required_parts = parse(required)
+        .ok_or_else(|| ConfigError::InvalidMinShclapVersion(required.to_string()))?;
+    // Our own version always parses; pre-release suffixes are ignored
+    let actual_core = actual.split(['-', '+']).next().unwrap_or(actual);
+    let actual_parts = parse(actual_core).unwrap_or_default();
+
+    if actual_parts < required_parts {
+        return Err(ConfigError::ShclapVersionTooOld(
+            required.to_string(),
+            actual.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Find the first key in `raw` with no counterpart in `known`, as a path
+/// such as `args[0].requird`.
+fn find_unknown_field(
+    raw: &serde_json::Value,
+    known: &serde_json::Value,
+    path: &str,
+) -> Option<String> {
+    use serde_json::Value;
+
+    match (raw, known) {
+        (Value::Object(raw), Value::Object(known)) => raw.iter().find_map(|(key, value)| {
+            let path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            match known.get(key) {
+                Some(known) => find_unknown_field(value, known, &path),
+                None => Some(path),
+            }
+        }),
+        (Value::Array(raw), Value::Array(known)) => {
+            raw.iter()
+                .zip(known)
+                .enumerate()
+                .find_map(|(i, (raw, known))| {
+                    find_unknown_field(raw, known, &format!("{}[{}]", path, i))
+                })
+        }
+        _ => None,
+    }
+}
+
+/// Strip `strip` (a `strip_name_prefix`) from an arg name, keeping the name
+/// unchanged when it lacks the prefix or would become empty.
+fn strip_name<'a>(name: &'a str, strip: Option<&str>) -> &'a str {
+    match strip.and_then(|strip| name.strip_prefix(strip)) {
+        Some(stripped) if !stripped.is_empty() => stripped,
+        _ => name,
+    }
+}
+
+/// Convert an arg name to its variable name suffix (uppercased, hyphens to underscores).
+fn to_var_suffix(name: &str) -> String {
+    name.to_uppercase().replace('-', "_")
+}
+
+/// Suffixes of the variables shclap sets next to `arg`'s own (`_GROUPS`,
+/// `_COUNT`, `_OCCURRENCES`), and whether it also sets numbered `_0`/`_1`..
+/// variables; see `Config::validate_derived_var_names`.
+fn derived_var_suffixes(
+    arg: &ArgConfig,
+    posix: bool,
+    occurrences: bool,
+) -> (Vec<&'static str>, bool) {
+    let list = arg.multiple && arg.arg_type != ArgType::Flag;
+    let mut suffixes = Vec::new();
+    if arg.grouped {
+        suffixes.push("GROUPS");
+    }
+    if posix && list {
+        suffixes.push("COUNT");
+    }
+    if occurrences && list && arg.arg_type == ArgType::Option {
+        suffixes.push("OCCURRENCES");
+    }
+    (suffixes, arg.grouped || (posix && list))
+}
+
+/// Apply the per-argument fixes used by `Config::autofix`.
+fn autofix_arg(arg: &mut ArgConfig) {
+    trim_end_in_place(&mut arg.name);
+    if let Some(ref mut help) = arg.help {
+        trim_end_in_place(help);
+    }
+
+    if let Some(ref mut long) = arg.long {
+        *long = long.trim().to_lowercase().replace(['_', ' '], "-");
+    }
+
+    if let Some(ref mut choices) = arg.choices {
+        let mut seen = std::collections::HashSet::new();
+        let case_insensitive = arg.case_insensitive;
+        choices.retain(|choice| {
+            seen.insert(if case_insensitive {
+                choice.to_ascii_lowercase()
+            } else {
+                choice.clone()
+            })
+        });
+    }
+}
+
+/// Remove trailing whitespace from a string without reallocating.
+fn trim_end_in_place(s: &mut String) {
+    let len = s.trim_end().len();
+    s.truncate(len);
+}
+
+/// Number positional args sequentially from 1, clearing the index on other args.
+fn assign_positional_indices(args: &mut [ArgConfig]) {
+    let mut next_index = 1usize;
+    for arg in args {
+        if arg.arg_type == ArgType::Positional {
+            arg.positional_index = Some(next_index);
+            next_index += 1;
+        } else {
+            arg.positional_index = None;
+        }
+    }
 }
 
 /// Validate num_args format (e.g., "1", "1..", "2..5", "1..=3").
 fn validate_num_args_format(num_args: &str) -> Result<(), ConfigError> {
     let s = num_args.trim();
 
-    // Single number
-    if s.parse::<usize>().is_ok() {
+    // Single number, bare or as explicit "=N"
+    if s.strip_prefix('=').unwrap_or(s).parse::<usize>().is_ok() {
         return Ok(());
     }
 
@@ -428,6 +1862,13 @@ fn validate_num_args_format(num_args: &str) -> Result<(), ConfigError> {
     Err(ConfigError::InvalidNumArgsFormat(num_args.to_string()))
 }
 
+impl SubcommandConfig {
+    /// Whether `name` invokes this subcommand, by its own name or an alias.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|alias| alias == name)
+    }
+}
+
 impl ArgConfig {
     /// Check if this argument uses any v2-only features.
     pub fn uses_v2_features(&self) -> bool {
@@ -437,6 +1878,78 @@ impl ArgConfig {
             || self.delimiter.is_some()
             || self.choices.is_some()
             || self.value_type != ValueType::String
+            || self.expand_env
+            || self.grouped
+            || self.deprecated_in_favor_of.is_some()
+            || self.require_equals
+            || self.negative_numbers_only
+            || self.emit_index
+            || !self.conflicts_with_subcommand.is_empty()
+            || self.min.is_some()
+            || self.max.is_some()
+            || self.path_must_exist
+            || !self.conflicts_with.is_empty()
+            || !self.requires.is_empty()
+            || !self.aliases.is_empty()
+            || self.max_occurrences.is_some()
+            || self.case_insensitive
+            || self.unique
+            || self.hidden
+            || self.decimal_comma
+    }
+
+    /// The declared choice `value` selects, honoring `case_insensitive`.
+    pub fn matching_choice(&self, value: &str) -> Option<&String> {
+        self.choices.as_ref()?.iter().find(|choice| {
+            if self.case_insensitive {
+                choice.eq_ignore_ascii_case(value)
+            } else {
+                *choice == value
+            }
+        })
+    }
+
+    /// `value` with a decimal comma (`1,5`) turned into a point when
+    /// `decimal_comma` is set. Only one comma is replaced, so digit grouping
+    /// such as `1,000,5` still fails to parse.
+    pub fn decimal_point<'a>(&self, value: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.decimal_comma {
+            value.replacen(',', ".", 1).into()
+        } else {
+            value.into()
+        }
+    }
+
+    /// Check whether a raw `value` is valid for this argument's `value_type`,
+    /// accepting a decimal comma when `decimal_comma` is set.
+    pub fn accepts(&self, value: &str) -> bool {
+        self.value_type.accepts(&self.decimal_point(value))
+    }
+
+    /// Check whether `value` lies within this argument's `min`/`max` bounds.
+    pub fn in_range(&self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+
+    /// The `min`/`max` bounds as a Rust-style range (e.g. `1..=65535`, `0..`),
+    /// or `None` when neither is set.
+    pub fn range_display(&self) -> Option<String> {
+        match (self.min, self.max) {
+            (None, None) => None,
+            (min, max) => Some(format!(
+                "{}..{}",
+                min.map(|m| m.to_string()).unwrap_or_default(),
+                max.map(|m| format!("={}", m)).unwrap_or_default()
+            )),
+        }
+    }
+
+    /// Deprecation message for this argument (e.g. `"use --new-name instead"`),
+    /// derived from `deprecated_in_favor_of`.
+    pub fn deprecation_message(&self) -> Option<String> {
+        self.deprecated_in_favor_of
+            .as_ref()
+            .map(|replacement| format!("use --{} instead", replacement))
     }
 
     /// Get the effective long option for this argument.
@@ -447,22 +1960,66 @@ impl ArgConfig {
             return self.long.as_deref();
         }
         // For non-positional args without short, use name as long
-        if self.arg_type != ArgType::Positional && self.short.is_none() {
+        if self.arg_type != ArgType::Positional && self.effective_short().is_none() {
             return Some(&self.name);
         }
         None
     }
 
-    /// Get the effective environment variable name for this argument.
-    ///
+    /// Get the effective short option for this argument.
+    /// Returns the specified short option; positional arguments never have one.
+    pub fn effective_short(&self) -> Option<char> {
+        if self.arg_type == ArgType::Positional {
+            return None;
+        }
+        self.short
+    }
+
+    /// Get the effective long option with its dashes (e.g. `"--verbose"`).
+    pub fn long_with_prefix(&self) -> Option<String> {
+        self.effective_long().map(|long| format!("--{}", long))
+    }
+
+    /// Get the short option with its dash (e.g. `"-v"`).
+    pub fn short_with_prefix(&self) -> Option<String> {
+        self.effective_short().map(|short| format!("-{}", short))
+    }
+
+    /// Get every option string for this argument, short first
+    /// (e.g. `["-v", "--verbose"]`). Empty for positionals.
+    pub fn option_strings(&self) -> Vec<String> {
+        self.short_with_prefix()
+            .into_iter()
+            .chain(self.long_with_prefix())
+            .collect()
+    }
+
+    /// Get the effective environment variable name for this argument.
+    ///
     /// For schema v2+:
-    /// - `None` field: Auto-env using `PREFIX + ARG_NAME`
+    /// - `None` field: Auto-env using `PREFIX + ARG_NAME`, unless `auto_env` is false
     /// - `env: false`: Disabled
     /// - `env: "VAR"`: Custom var name
     ///
     /// For schema v1:
     /// - Always returns `None` (no env fallback in v1)
-    pub fn effective_env(&self, prefix: &str, schema_version: u32) -> Option<String> {
+    pub fn effective_env(
+        &self,
+        prefix: &str,
+        schema_version: u32,
+        auto_env: bool,
+    ) -> Option<String> {
+        self.effective_env_with_strip(prefix, schema_version, auto_env, None)
+    }
+
+    /// Like `effective_env`, but strips `strip_name_prefix` from the name for auto-env.
+    pub fn effective_env_with_strip(
+        &self,
+        prefix: &str,
+        schema_version: u32,
+        auto_env: bool,
+        strip_name_prefix: Option<&str>,
+    ) -> Option<String> {
         // v1 doesn't support env fallback
         if schema_version < 2 {
             return None;
@@ -471,10 +2028,11 @@ impl ArgConfig {
         match &self.env {
             Some(EnvSetting::Disabled) => None,
             Some(EnvSetting::Custom(var)) => Some(var.clone()),
+            None if !auto_env => None,
             None => {
                 // Auto-env: PREFIX + ARG_NAME (uppercased, hyphens to underscores)
-                let var_name = self.name.to_uppercase().replace('-', "_");
-                Some(format!("{}{}", prefix, var_name))
+                let name = strip_name(&self.name, strip_name_prefix);
+                Some(format!("{}{}", prefix, to_var_suffix(name)))
             }
         }
     }
@@ -631,7 +2189,10 @@ mod tests {
             ]
         }"#;
         let config = Config::from_json(json).unwrap();
-        assert_eq!(config.args[0].default, Some("out.txt".to_string()));
+        assert_eq!(
+            config.args[0].default,
+            Some(DefaultValue::Single("out.txt".to_string()))
+        );
     }
 
     #[test]
@@ -728,7 +2289,7 @@ mod tests {
         assert_eq!(config.args[0].env, None);
         // effective_env should return auto-generated name
         assert_eq!(
-            config.args[0].effective_env("SHCLAP_", 2),
+            config.args[0].effective_env("SHCLAP_", 2, true),
             Some("SHCLAP_CONFIG".to_string())
         );
     }
@@ -865,10 +2426,93 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_error_subcommand_alias_collision() {
+        let config = |subcommands: &str| {
+            Config::from_json(&format!(
+                r#"{{"schema_version": 2, "name": "test", "subcommands": [{}]}}"#,
+                subcommands
+            ))
+            .unwrap()
+        };
+
+        config(r#"{"name": "remove", "aliases": ["rm"]}, {"name": "list", "aliases": ["ls"]}"#)
+            .validate()
+            .unwrap();
+
+        // Alias shadows another subcommand's name
+        let result =
+            config(r#"{"name": "remove", "aliases": ["list"]}, {"name": "list"}"#).validate();
+        assert!(matches!(
+            result,
+            Err(ConfigError::SubcommandAliasCollision(subcmd, alias, other))
+                if subcmd == "remove" && alias == "list" && other == "list"
+        ));
+
+        // Two subcommands share an alias
+        let result =
+            config(r#"{"name": "remove", "aliases": ["r"]}, {"name": "rename", "aliases": ["r"]}"#)
+                .validate();
+        assert!(matches!(
+            result,
+            Err(ConfigError::SubcommandAliasCollision(subcmd, alias, other))
+                if subcmd == "rename" && alias == "r" && other == "remove"
+        ));
+    }
+
+    #[test]
+    fn test_nested_subcommands_validate_each_level() {
+        let config = |subcommands: &str| {
+            Config::from_json(&format!(
+                r#"{{"schema_version": 2, "name": "test", "subcommands": [{}]}}"#,
+                subcommands
+            ))
+            .unwrap()
+        };
+
+        // The same name may appear under different parents
+        let ok = config(
+            r#"{"name": "remote", "subcommands": [{"name": "add"}, {"name": "remove"}]},
+               {"name": "branch", "subcommands": [{"name": "add"}]}"#,
+        );
+        ok.validate().unwrap();
+        let paths: Vec<String> = ok.all_subcommands().into_iter().map(|(p, _)| p).collect();
+        assert_eq!(
+            paths,
+            [
+                "remote",
+                "remote.add",
+                "remote.remove",
+                "branch",
+                "branch.add"
+            ]
+        );
+        assert_eq!(ok.subcommand_chain("remote.add").unwrap().len(), 2);
+        assert!(ok.subcommand_chain("remote.rename").is_none());
+
+        let dup =
+            config(r#"{"name": "remote", "subcommands": [{"name": "add"}, {"name": "add"}]}"#);
+        assert!(matches!(
+            dup.validate(),
+            Err(ConfigError::DuplicateSubcommandName(name)) if name == "add"
+        ));
+
+        // Args of nested subcommands are validated too
+        let dup_arg = config(
+            r#"{"name": "remote", "subcommands": [{"name": "add", "args": [
+                {"name": "url", "type": "positional"}, {"name": "url", "type": "option"}
+            ]}]}"#,
+        );
+        assert!(matches!(
+            dup_arg.validate(),
+            Err(ConfigError::DuplicateName(name)) if name == "url"
+        ));
+    }
+
     #[test]
     fn test_valid_num_args_formats() {
         let formats = vec![
-            "1", "2", "10", "1..", "2..", "1..3", "2..5", "1..=3", "0..=10",
+            "1", "2", "10", "=3", "1..", "2..", "1..3", "2..5", "1..=3", "0..=10",
         ];
         for fmt in formats {
             assert!(
@@ -881,7 +2525,9 @@ mod tests {
 
     #[test]
     fn test_invalid_num_args_formats() {
-        let formats = vec!["abc", "..", "..3", "a..b", "1..=", "1..=abc", "-1", "1...3"];
+        let formats = vec![
+            "abc", "..", "..3", "a..b", "1..=", "1..=abc", "-1", "1...3", "=abc", "=", "==3",
+        ];
         for fmt in formats {
             assert!(
                 validate_num_args_format(fmt).is_err(),
@@ -907,6 +2553,26 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::String,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         };
         assert!(!v1_arg.uses_v2_features());
 
@@ -965,6 +2631,26 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::String,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         };
         assert_eq!(arg.effective_long(), Some("verbose"));
     }
@@ -986,6 +2672,26 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::String,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         };
         assert_eq!(arg.effective_long(), Some("verbose"));
     }
@@ -1007,6 +2713,26 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::String,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         };
         assert_eq!(arg.effective_long(), None);
     }
@@ -1028,10 +2754,97 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::String,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         };
         assert_eq!(arg.effective_long(), None);
     }
 
+    fn make_arg(json: &str) -> ArgConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_effective_short_explicit() {
+        // When short is explicitly specified, use it
+        let arg =
+            make_arg(r#"{"name": "verbose", "short": "v", "long": "verbose", "type": "flag"}"#);
+        assert_eq!(arg.effective_short(), Some('v'));
+        assert_eq!(arg.short_with_prefix().as_deref(), Some("-v"));
+    }
+
+    #[test]
+    fn test_effective_short_with_short_only() {
+        // A short-only option keeps its short and gets no long
+        let arg = make_arg(r#"{"name": "output", "short": "o", "type": "option"}"#);
+        assert_eq!(arg.effective_short(), Some('o'));
+        assert_eq!(arg.effective_long(), None);
+    }
+
+    #[test]
+    fn test_effective_short_none_when_unspecified() {
+        // No short is ever derived from the name
+        let arg = make_arg(r#"{"name": "verbose", "type": "flag"}"#);
+        assert_eq!(arg.effective_short(), None);
+        assert_eq!(arg.short_with_prefix(), None);
+    }
+
+    #[test]
+    fn test_effective_short_positional() {
+        // Positional args never have short options
+        let arg = make_arg(r#"{"name": "input", "short": "i", "type": "positional"}"#);
+        assert_eq!(arg.effective_short(), None);
+    }
+
+    #[test]
+    fn test_option_strings_short_and_long() {
+        let arg = make_arg(r#"{"name":"verbose","short":"v","long":"verbose","type":"flag"}"#);
+        assert_eq!(arg.short_with_prefix(), Some("-v".to_string()));
+        assert_eq!(arg.long_with_prefix(), Some("--verbose".to_string()));
+        assert_eq!(arg.option_strings(), vec!["-v", "--verbose"]);
+    }
+
+    #[test]
+    fn test_option_strings_long_fallback() {
+        let arg = make_arg(r#"{"name":"output","type":"option"}"#);
+        assert_eq!(arg.short_with_prefix(), None);
+        assert_eq!(arg.long_with_prefix(), Some("--output".to_string()));
+        assert_eq!(arg.option_strings(), vec!["--output"]);
+    }
+
+    #[test]
+    fn test_option_strings_short_only() {
+        let arg = make_arg(r#"{"name":"verbose","short":"v","type":"flag"}"#);
+        assert_eq!(arg.long_with_prefix(), None);
+        assert_eq!(arg.option_strings(), vec!["-v"]);
+    }
+
+    #[test]
+    fn test_option_strings_positional() {
+        let arg = make_arg(r#"{"name":"input","type":"positional"}"#);
+        assert_eq!(arg.short_with_prefix(), None);
+        assert_eq!(arg.long_with_prefix(), None);
+        assert!(arg.option_strings().is_empty());
+    }
+
     // Choices validation tests
 
     #[test]
@@ -1098,6 +2911,16 @@ mod tests {
         assert!(matches!(result, Err(ConfigError::ChoicesOnFlag(name)) if name == "verbose"));
     }
 
+    #[test]
+    fn test_error_choices_on_flag_message() {
+        let err = ConfigError::ChoicesOnFlag("level".to_string());
+        assert_eq!(
+            err.to_string(),
+            "'choices' requires type 'option' or 'positional', not 'flag', on argument 'level'; \
+             a flag takes no value, so use \"type\": \"option\" to limit a value to these choices"
+        );
+    }
+
     #[test]
     fn test_error_empty_choices() {
         let json = r#"{
@@ -1144,6 +2967,26 @@ mod tests {
             delimiter: None,
             choices: Some(vec!["json".to_string(), "yaml".to_string()]),
             value_type: ValueType::String,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         };
         assert!(arg.uses_v2_features());
     }
@@ -1251,6 +3094,25 @@ mod tests {
         assert!(config.validate().is_ok());
     }
 
+    #[test]
+    fn test_double_accepts_only_finite_numbers() {
+        assert!(ValueType::Double.accepts("1.5e3"));
+        for value in ["nan", "NaN", "inf", "-infinity", "1e400"] {
+            assert!(!ValueType::Double.accepts(value), "{}", value);
+        }
+
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "ratio", "long": "ratio", "type": "option", "value_type": "double", "default": "inf"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidDefaultForValueType(_, _, _))
+        ));
+    }
+
     #[test]
     fn test_validate_accepts_double_on_positional() {
         let json = r#"{
@@ -1313,6 +3175,26 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::Double,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         };
         assert!(arg.uses_v2_features());
     }
@@ -1346,6 +3228,26 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::Int,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         };
         assert!(arg.uses_v2_features());
 
@@ -1355,4 +3257,1509 @@ mod tests {
         };
         assert!(!string_arg.uses_v2_features());
     }
+
+    // Default value validation tests
+
+    #[test]
+    fn test_valid_default_in_choices() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "format", "long": "format", "type": "option", "choices": ["a", "b"], "default": "b"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_error_default_not_in_choices() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "format", "long": "format", "type": "option", "choices": ["a", "b"], "default": "c"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        let result = config.validate();
+        assert!(matches!(
+            result,
+            Err(ConfigError::DefaultNotInChoices(name, default)) if name == "format" && default == "c"
+        ));
+    }
+
+    #[test]
+    fn test_error_int_default_not_numeric() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "count", "long": "count", "type": "option", "value_type": "int", "default": "ten"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        let result = config.validate();
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidDefaultForValueType(name, default, ValueType::Int))
+                if name == "count" && default == "ten"
+        ));
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "default 'ten' on argument 'count' is not a valid int"
+        );
+    }
+
+    #[test]
+    fn test_valid_typed_defaults() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "count", "long": "count", "type": "option", "value_type": "int", "default": "-3"},
+                {"name": "ratio", "long": "ratio", "type": "option", "value_type": "double", "default": "0.5"},
+                {"name": "enabled", "long": "enabled", "type": "option", "value_type": "bool", "default": "false"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_expected_output_count() {
+        let json = r#"{
+            "name": "test",
+            "args": [
+                {"name": "verbose", "short": "v", "type": "flag"},
+                {"name": "input", "type": "positional"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.expected_output_count(), 3);
+
+        let empty = Config::from_json(r#"{"name": "test"}"#).unwrap();
+        assert_eq!(empty.expected_output_count(), 1);
+    }
+
+    // Positional index tests
+
+    #[test]
+    fn test_positional_indices_sequential_in_declaration_order() {
+        let json = r#"{
+            "name": "test",
+            "args": [
+                {"name": "src", "type": "positional"},
+                {"name": "verbose", "short": "v", "type": "flag"},
+                {"name": "dest", "type": "positional"},
+                {"name": "output", "long": "output", "type": "option"},
+                {"name": "extra", "type": "positional"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        let indices: Vec<_> = config.args.iter().map(|a| a.positional_index).collect();
+        assert_eq!(indices, vec![Some(1), None, Some(2), None, Some(3)]);
+    }
+
+    #[test]
+    fn test_positional_by_index() {
+        let json = r#"{
+            "name": "test",
+            "args": [
+                {"name": "src", "type": "positional"},
+                {"name": "verbose", "short": "v", "type": "flag"},
+                {"name": "dest", "type": "positional"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.positional_by_index(1).unwrap().name, "src");
+        assert_eq!(config.positional_by_index(2).unwrap().name, "dest");
+        assert!(config.positional_by_index(0).is_none());
+        assert!(config.positional_by_index(3).is_none());
+    }
+
+    #[test]
+    fn test_positional_indices_per_subcommand() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "target", "type": "positional"}
+            ],
+            "subcommands": [
+                {
+                    "name": "copy",
+                    "args": [
+                        {"name": "from", "type": "positional"},
+                        {"name": "to", "type": "positional"}
+                    ]
+                }
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.args[0].positional_index, Some(1));
+        let copy_args = &config.subcommands[0].args;
+        assert_eq!(copy_args[0].positional_index, Some(1));
+        assert_eq!(copy_args[1].positional_index, Some(2));
+    }
+
+    // Name prefix stripping tests
+
+    #[test]
+    fn test_strip_arg_name() {
+        let json = r#"{"name": "test", "strip_name_prefix": "opt_"}"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.strip_arg_name("opt_output"), "output");
+        assert_eq!(config.strip_arg_name("verbose"), "verbose");
+        // Stripping never produces an empty name
+        assert_eq!(config.strip_arg_name("opt_"), "opt_");
+    }
+
+    #[test]
+    fn test_strip_name_prefix_applies_to_auto_env() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "strip_name_prefix": "opt_",
+            "args": [
+                {"name": "opt_output", "long": "output", "type": "option"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        config.validate().unwrap();
+        assert_eq!(
+            config.args[0].effective_env_with_strip(
+                "SHCLAP_",
+                2,
+                true,
+                config.strip_name_prefix.as_deref()
+            ),
+            Some("SHCLAP_OUTPUT".to_string())
+        );
+    }
+
+    #[test]
+    fn test_error_strip_name_prefix_collision() {
+        let json = r#"{
+            "name": "test",
+            "strip_name_prefix": "opt_",
+            "args": [
+                {"name": "opt_output", "long": "opt-output", "type": "option"},
+                {"name": "output", "long": "output", "type": "option"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        let result = config.validate();
+        assert!(matches!(
+            result,
+            Err(ConfigError::VariableNameCollision(a, b, var))
+                if a == "opt_output" && b == "output" && var == "OUTPUT"
+        ));
+    }
+
+    #[test]
+    fn test_error_hyphen_underscore_collision() {
+        let json = r#"{
+            "name": "test",
+            "args": [
+                {"name": "dry-run", "type": "flag"},
+                {"name": "dry_run", "type": "flag"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        let result = config.validate();
+        assert!(matches!(
+            result,
+            Err(ConfigError::VariableNameCollision(_, _, var)) if var == "DRY_RUN"
+        ));
+    }
+
+    #[test]
+    fn test_derived_var_name_collisions() {
+        let config = Config::from_json(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "file", "long": "file", "type": "option", "multiple": true},
+                {"name": "file_count", "long": "file-count", "type": "option"},
+                {"name": "file-occurrences", "long": "file-occurrences", "type": "option"}
+            ]
+        }"#,
+        )
+        .unwrap();
+        assert!(config.validate_derived_var_names(false, false).is_ok());
+        assert!(matches!(
+            config.validate_derived_var_names(true, false),
+            Err(ConfigError::DerivedVariableNameCollision(a, b, var))
+                if a == "file" && b == "file_count" && var == "FILE_COUNT"
+        ));
+        assert!(matches!(
+            config.validate_derived_var_names(false, true),
+            Err(ConfigError::DerivedVariableNameCollision(_, _, var)) if var == "FILE_OCCURRENCES"
+        ));
+
+        // Grouped values add _GROUPS and _0.._N in every dialect, and a
+        // subcommand arg is emitted next to its parent's
+        let config = Config::from_json(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "point", "long": "point", "type": "option", "multiple": true, "num_args": "2", "grouped": true}
+            ],
+            "subcommands": [
+                {"name": "run", "args": [{"name": "point_0", "long": "point-0", "type": "option"}]}
+            ]
+        }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.validate_derived_var_names(false, false),
+            Err(ConfigError::DerivedVariableNameCollision(_, b, _)) if b == "point_0"
+        ));
+    }
+
+    #[test]
+    fn test_autofix_bumps_schema_version() {
+        let json = r#"{
+            "name": "test",
+            "args": [{"name": "files", "type": "option", "multiple": true}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(config.validate().is_err());
+
+        let fixed = config.autofix();
+        assert_eq!(fixed.schema_version, 2);
+        assert_eq!(config.schema_version, 1);
+        assert!(fixed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_autofix_trims_trailing_whitespace() {
+        let json = r#"{
+            "name": "test  ",
+            "args": [{"name": "verbose ", "type": "flag", "help": "Be loud\n"}]
+        }"#;
+        let fixed = Config::from_json(json).unwrap().autofix();
+        assert_eq!(fixed.name.as_deref(), Some("test"));
+        assert_eq!(fixed.args[0].name, "verbose");
+        assert_eq!(fixed.args[0].help.as_deref(), Some("Be loud"));
+        assert!(fixed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_autofix_dedups_choices() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "format", "type": "option", "choices": ["json", "yaml", "json"]}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::DuplicateChoice(_, _))
+        ));
+
+        let fixed = config.autofix();
+        assert_eq!(
+            fixed.args[0].choices,
+            Some(vec!["json".to_string(), "yaml".to_string()])
+        );
+        assert!(fixed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_autofix_normalises_long() {
+        let json = r#"{
+            "name": "test",
+            "subcommands": [{"name": "run", "args": [
+                {"name": "dry_run", "long": "Dry_Run", "type": "flag"}
+            ]}]
+        }"#;
+        let fixed = Config::from_json(json).unwrap().autofix();
+        assert_eq!(fixed.schema_version, 2);
+        assert_eq!(
+            fixed.subcommands[0].args[0].long.as_deref(),
+            Some("dry-run")
+        );
+        assert!(fixed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_error_expand_env_requires_v2() {
+        let json = r#"{
+            "name": "test",
+            "args": [{"name": "dir", "type": "option", "expand_env": true}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::FieldRequiresV2(field, _)) if field == "expand_env"
+        ));
+    }
+
+    #[test]
+    fn test_parse_undefined_env_policy() {
+        let config = Config::from_json(r#"{"name":"test"}"#).unwrap();
+        assert_eq!(config.undefined_env, UndefinedEnv::Empty);
+
+        let config = Config::from_json(r#"{"name":"test","undefined_env":"error"}"#).unwrap();
+        assert_eq!(config.undefined_env, UndefinedEnv::Error);
+    }
+
+    #[test]
+    fn test_yaml_config_gets_hint() {
+        let yaml = "name: myapp\nargs:\n  - name: verbose\n    type: flag\n";
+        let err = Config::from_json(yaml).unwrap_err();
+        assert!(matches!(err, ConfigError::ParseError(_, Some(_))));
+        assert!(err.to_string().contains("(hint: this looks like YAML"));
+    }
+
+    #[test]
+    fn test_yaml_document_start_gets_hint() {
+        let err = Config::from_json("---\nname: myapp\n").unwrap_err();
+        assert!(err.to_string().contains("hint: this looks like YAML"));
+    }
+
+    #[test]
+    fn test_invalid_json_has_no_yaml_hint() {
+        let err = Config::from_json(r#"{"name": "myapp",}"#).unwrap_err();
+        assert!(matches!(err, ConfigError::ParseError(_, None)));
+        assert!(!err.to_string().contains("hint"));
+    }
+
+    #[test]
+    fn test_error_grouped_requires_multiple_and_num_args() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "coord", "type": "option", "multiple": true, "grouped": true}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::GroupedRequiresMultiple(name)) if name == "coord"
+        ));
+    }
+
+    #[test]
+    fn test_error_unknown_deprecated_in_favor_of() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "out", "type": "option", "deprecated_in_favor_of": "output"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UnknownDeprecatedInFavorOf(name, target))
+                if name == "out" && target == "output"
+        ));
+    }
+
+    #[test]
+    fn test_deprecated_in_favor_of_checks_same_scope() {
+        // The replacement exists at the top level but not in the subcommand
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "output", "type": "option"}],
+            "subcommands": [{"name": "run", "args": [
+                {"name": "out", "type": "option", "deprecated_in_favor_of": "output"}
+            ]}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UnknownDeprecatedInFavorOf(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_expected_env_vars() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "verbose", "short": "v", "type": "flag"},
+                {"name": "log-level", "type": "option"}
+            ],
+            "subcommands": [
+                {"name": "build", "args": [{"name": "release", "type": "flag"}]},
+                {"name": "test", "args": [
+                    {"name": "release", "type": "flag"},
+                    {"name": "filter", "type": "positional"}
+                ]}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(
+            config.expected_env_vars("APP_"),
+            vec![
+                "APP_SUBCOMMAND",
+                "APP_VERBOSE",
+                "APP_LOG_LEVEL",
+                "APP_RELEASE",
+                "APP_FILTER"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsatisfied_env_args() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "token", "type": "option", "required": true},
+                {"name": "region", "type": "option", "required": true, "env": "AWS_REGION"},
+                {"name": "input", "type": "positional", "required": true, "env": false},
+                {"name": "level", "type": "option"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        let lookup = |var: &str| (var == "APP_TOKEN").then(|| "secret".to_string());
+
+        let missing: Vec<(&str, Option<String>)> = config
+            .unsatisfied_env_args("APP_", lookup)
+            .into_iter()
+            .map(|(arg, var)| (arg.name.as_str(), var))
+            .collect();
+        assert_eq!(
+            missing,
+            vec![("region", Some("AWS_REGION".to_string())), ("input", None)]
+        );
+    }
+
+    #[test]
+    fn test_unsatisfied_env_args_empty_value_in_strict_mode() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "token", "type": "option", "required": true}]
+        }"#;
+        let mut config = Config::from_json(json).unwrap();
+        let lookup = |_: &str| Some(String::new());
+        assert!(config.unsatisfied_env_args("APP_", lookup).is_empty());
+
+        config.strict = true;
+        let missing = config.unsatisfied_env_args("APP_", lookup);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].1.as_deref(), Some("APP_TOKEN"));
+    }
+
+    #[test]
+    fn test_expected_env_vars_with_derived_names() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "tag", "long": "tag", "type": "option", "multiple": true},
+                {"name": "point", "long": "point", "type": "option", "multiple": true, "num_args": "2", "grouped": true},
+                {"name": "verbose", "short": "v", "type": "flag", "multiple": true}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(
+            config.expected_env_vars_with("APP_", false, false),
+            vec!["APP_TAG", "APP_POINT", "APP_POINT_GROUPS", "APP_VERBOSE"]
+        );
+        assert_eq!(
+            config.expected_env_vars_with("APP_", true, true),
+            vec![
+                "APP_TAG",
+                "APP_TAG_COUNT",
+                "APP_TAG_OCCURRENCES",
+                "APP_POINT",
+                "APP_POINT_GROUPS",
+                "APP_POINT_COUNT",
+                "APP_POINT_OCCURRENCES",
+                "APP_VERBOSE"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expected_env_vars_without_subcommands() {
+        let json = r#"{
+            "name": "test",
+            "strip_name_prefix": "opt_",
+            "args": [{"name": "opt_output", "type": "option"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.expected_env_vars("SHCLAP_"), vec!["SHCLAP_OUTPUT"]);
+    }
+
+    #[test]
+    fn test_error_require_equals_on_flag() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "verbose", "type": "flag", "require_equals": true}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::RequireEqualsOnNonOption(name)) if name == "verbose"
+        ));
+    }
+
+    #[test]
+    fn test_error_negative_numbers_only_on_positional() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "offset", "type": "positional", "negative_numbers_only": true}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::NegativeNumbersOnlyOnNonOption(name)) if name == "offset"
+        ));
+    }
+
+    #[test]
+    fn test_error_negative_numbers_only_requires_v2() {
+        let json = r#"{
+            "name": "test",
+            "args": [{"name": "offset", "type": "option", "negative_numbers_only": true}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::FieldRequiresV2(field, name))
+                if field == "negative_numbers_only" && name == "offset"
+        ));
+    }
+
+    #[test]
+    fn test_error_emit_index_requires_choices() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "level", "type": "option", "emit_index": true}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::EmitIndexRequiresChoices(name)) if name == "level"
+        ));
+    }
+
+    #[test]
+    fn test_choices_matching_value_type_are_valid() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "level", "type": "option", "value_type": "int", "choices": ["1", "2"]}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_error_choice_invalid_for_value_type() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "level", "type": "option", "value_type": "int", "choices": ["a", "2"]}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ChoiceInvalidForValueType(name, choice, ValueType::Int))
+                if name == "level" && choice == "a"
+        ));
+    }
+
+    #[test]
+    fn test_min_shclap_version_satisfied() {
+        let json = r#"{"name": "test", "min_shclap_version": "0.1.0"}"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(config.validate().is_ok());
+
+        let current = format!(
+            r#"{{"name": "test", "min_shclap_version": "{}"}}"#,
+            env!("CARGO_PKG_VERSION")
+        );
+        assert!(Config::from_json(&current).unwrap().validate().is_ok());
+    }
+
+    #[test]
+    fn test_error_min_shclap_version_too_old() {
+        let json = r#"{"name": "test", "min_shclap_version": "999.0.0"}"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ShclapVersionTooOld(required, actual))
+                if required == "999.0.0" && actual == env!("CARGO_PKG_VERSION")
+        ));
+    }
+
+    #[test]
+    fn test_check_shclap_version() {
+        assert!(check_shclap_version("0.2", "0.2.0").is_ok());
+        assert!(check_shclap_version("0.2.1", "0.2.0").is_err());
+        assert!(check_shclap_version("0.10.0", "0.9.5").is_err());
+        assert!(check_shclap_version("1.0.0", "1.0.0-rc.1").is_ok());
+        assert!(matches!(
+            check_shclap_version("latest", "0.2.0"),
+            Err(ConfigError::InvalidMinShclapVersion(v)) if v == "latest"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_prefix_uppercase() {
+        let json = r#"{"name": "test", "prefix": "myapp_", "uppercase_prefix": true}"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.resolve_prefix(None), "MYAPP_");
+        assert_eq!(config.resolve_prefix(Some("cli_")), "CLI_");
+        assert_eq!(
+            config.arg_var_name("output", &config.resolve_prefix(None)),
+            "MYAPP_OUTPUT"
+        );
+    }
+
+    #[test]
+    fn test_resolve_prefix_preserves_case_by_default() {
+        let json = r#"{"name": "test", "prefix": "myapp_"}"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.resolve_prefix(None), "myapp_");
+        assert_eq!(config.resolve_prefix(Some("cli_")), "cli_");
+        assert_eq!(
+            config.arg_var_name("output", &config.resolve_prefix(None)),
+            "myapp_OUTPUT"
+        );
+    }
+
+    #[test]
+    fn test_error_required_with_default() {
+        let json = r#"{
+            "name": "test",
+            "args": [{"name": "output", "type": "option", "required": true, "default": "out.txt"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::RequiredWithDefault(name)) if name == "output"
+        ));
+    }
+
+    #[test]
+    fn test_required_without_default_is_valid() {
+        let json = r#"{
+            "name": "test",
+            "args": [
+                {"name": "output", "type": "option", "required": true},
+                {"name": "mode", "type": "option", "default": "fast"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_conflicts_with_subcommand_valid() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "all", "type": "flag", "conflicts_with_subcommand": ["single"]}],
+            "subcommands": [{"name": "single"}, {"name": "batch"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_error_conflicts_with_unknown_subcommand() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "all", "type": "flag", "conflicts_with_subcommand": ["singel"]}],
+            "subcommands": [{"name": "single"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UnknownConflictingSubcommand(arg, subcmd))
+                if arg == "all" && subcmd == "singel"
+        ));
+    }
+
+    #[test]
+    fn test_error_conflicts_with_subcommand_requires_v2() {
+        let json = r#"{
+            "name": "test",
+            "args": [{"name": "all", "type": "flag", "conflicts_with_subcommand": ["single"]}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::FieldRequiresV2(field, _)) if field == "conflicts_with_subcommand"
+        ));
+    }
+
+    #[test]
+    fn test_min_max_on_int_valid() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "port", "type": "option", "value_type": "int", "min": 1, "max": 65535, "default": "8080"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.args[0].range_display().as_deref(), Some("1..=65535"));
+    }
+
+    #[test]
+    fn test_error_min_greater_than_max() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "port", "type": "option", "value_type": "int", "min": 10, "max": 1}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidRange(name, min, max)) if name == "port" && min == 10.0 && max == 1.0
+        ));
+    }
+
+    #[test]
+    fn test_error_min_max_on_non_numeric() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "name", "type": "option", "min": 1}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::RangeOnNonNumeric(name)) if name == "name"
+        ));
+    }
+
+    #[test]
+    fn test_error_default_out_of_range() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "ratio", "type": "option", "value_type": "double", "max": 1.0, "default": "1.5"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::DefaultOutOfRange(name, default, range))
+                if name == "ratio" && default == "1.5" && range == "..=1"
+        ));
+    }
+
+    #[test]
+    fn test_error_min_requires_v2() {
+        let json = r#"{
+            "name": "test",
+            "args": [{"name": "port", "type": "option", "min": 1}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::FieldRequiresV2(field, _)) if field == "min"
+        ));
+    }
+
+    #[test]
+    fn test_path_value_type_deserialises() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "input", "type": "option", "value_type": "path", "path_must_exist": true}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.args[0].value_type, ValueType::Path);
+        assert!(config.args[0].path_must_exist);
+        assert!(config.validate().is_ok());
+        assert!(ValueType::Path.accepts("/does/not/matter"));
+    }
+
+    #[test]
+    fn test_error_path_value_type_on_flag_and_v1() {
+        let on_flag = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "input", "type": "flag", "value_type": "path"}]
+        }"#;
+        assert!(matches!(
+            Config::from_json(on_flag).unwrap().validate(),
+            Err(ConfigError::ValueTypeOnFlag(_))
+        ));
+
+        let in_v1 = r#"{
+            "name": "test",
+            "args": [{"name": "input", "type": "option", "value_type": "path"}]
+        }"#;
+        assert!(matches!(
+            Config::from_json(in_v1).unwrap().validate(),
+            Err(ConfigError::FieldRequiresV2(field, _)) if field == "value_type"
+        ));
+    }
+
+    #[test]
+    fn test_error_path_must_exist_requires_path() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "input", "type": "option", "path_must_exist": true}]
+        }"#;
+        assert!(matches!(
+            Config::from_json(json).unwrap().validate(),
+            Err(ConfigError::PathMustExistRequiresPath(name)) if name == "input"
+        ));
+    }
+
+    #[test]
+    fn test_conflicts_with_valid() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "json", "type": "flag", "conflicts_with": ["yaml"]},
+                {"name": "yaml", "type": "flag"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_error_unknown_conflict_target() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "json", "type": "flag", "conflicts_with": ["yml"]},
+                {"name": "yaml", "type": "flag"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UnknownConflictTarget(arg, target)) if arg == "json" && target == "yml"
+        ));
+
+        // An arg can't conflict with itself
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "json", "type": "flag", "conflicts_with": ["json"]}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UnknownConflictTarget(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_error_unknown_requires_target() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "compress", "type": "flag", "requires": ["outptu"]},
+                {"name": "output", "type": "option"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UnknownRequiresTarget(arg, target))
+                if arg == "compress" && target == "outptu"
+        ));
+    }
+
+    #[test]
+    fn test_strict_rejects_unknown_fields() {
+        let lenient =
+            r#"{"name": "test", "args": [{"name": "output", "type": "option", "requird": true}]}"#;
+        let config = Config::from_json(lenient).unwrap();
+        assert!(config.validate().is_ok());
+        assert!(matches!(
+            config.check_unknown_fields(lenient),
+            Err(ConfigError::UnknownField(path)) if path == "args[0].requird"
+        ));
+
+        let strict = r#"{"name": "test", "strict": true, "descripton": "typo"}"#;
+        assert!(matches!(
+            Config::from_json(strict),
+            Err(ConfigError::UnknownField(path)) if path == "descripton"
+        ));
+
+        // Every real field is known, including renamed and nested ones
+        let valid = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "strict": true,
+            "version_info": {"git_hash": "abc"},
+            "args": [{"name": "level", "type": "option", "env": false, "choices": ["a", "b"]}],
+            "subcommands": [{"name": "run", "args": [{"name": "fast", "type": "flag"}]}]
+        }"#;
+        assert!(Config::from_json(valid).is_ok());
+    }
+
+    #[test]
+    fn test_strict_rejects_reserved_names() {
+        let cases = [
+            (
+                r#"{"name": "subcommand", "type": "option"}"#,
+                "variable name SUBCOMMAND",
+            ),
+            (
+                r#"{"name": "hint", "short": "h", "type": "flag"}"#,
+                "short option -h",
+            ),
+            (
+                r#"{"name": "ver", "long": "version", "type": "flag"}"#,
+                "option --version",
+            ),
+        ];
+        for (arg, reserved) in cases {
+            let lenient = format!(r#"{{"name": "test", "args": [{}]}}"#, arg);
+            assert!(Config::from_json(&lenient).unwrap().validate().is_ok());
+
+            let strict = format!(r#"{{"name": "test", "strict": true, "args": [{}]}}"#, arg);
+            assert!(matches!(
+                Config::from_json(&strict).unwrap().validate(),
+                Err(ConfigError::ReservedName(_, what)) if what == reserved
+            ));
+        }
+    }
+
+    #[test]
+    fn test_error_alias_collides_with_long() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "path", "type": "option", "aliases": ["dir", "output"]},
+                {"name": "output", "type": "option"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::AliasCollision(arg, alias, owner))
+                if arg == "path" && alias == "output" && owner == "output"
+        ));
+    }
+
+    #[test]
+    fn test_error_alias_repeated_and_on_positional() {
+        let repeated = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "path", "type": "option", "aliases": ["dir"]},
+                {"name": "folder", "type": "option", "aliases": ["dir"]}
+            ]
+        }"#;
+        assert!(matches!(
+            Config::from_json(repeated).unwrap().validate(),
+            Err(ConfigError::AliasCollision(arg, _, owner)) if arg == "folder" && owner == "path"
+        ));
+
+        let positional = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "path", "type": "positional", "aliases": ["dir"]}]
+        }"#;
+        assert!(matches!(
+            Config::from_json(positional).unwrap().validate(),
+            Err(ConfigError::AliasesOnPositional(name)) if name == "path"
+        ));
+    }
+
+    #[test]
+    fn test_max_occurrences_requires_counting_flag() {
+        let valid = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "verbose", "short": "v", "type": "flag", "multiple": true, "max_occurrences": 3}]
+        }"#;
+        let config = Config::from_json(valid).unwrap();
+        assert_eq!(config.args[0].max_occurrences, Some(3));
+        assert!(config.validate().is_ok());
+
+        for arg in [
+            r#"{"name": "output", "type": "option", "multiple": true, "max_occurrences": 2}"#,
+            r#"{"name": "verbose", "type": "flag", "max_occurrences": 2}"#,
+        ] {
+            let json = format!(
+                r#"{{"schema_version": 2, "name": "test", "args": [{}]}}"#,
+                arg
+            );
+            let err = Config::from_json(&json).unwrap().validate().unwrap_err();
+            assert!(
+                matches!(err, ConfigError::MaxOccurrencesRequiresCountFlag(_)),
+                "{}",
+                err
+            );
+        }
+
+        let v1 = r#"{"name": "test", "args": [{"name": "verbose", "type": "flag", "max_occurrences": 3}]}"#;
+        assert!(matches!(
+            Config::from_json(v1).unwrap().validate(),
+            Err(ConfigError::FieldRequiresV2(field, _)) if field == "max_occurrences"
+        ));
+    }
+
+    #[test]
+    fn test_array_default_for_multiple() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "tags", "type": "option", "multiple": true, "default": ["a", "b"]}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(
+            config.args[0].default,
+            Some(DefaultValue::Multiple(vec![
+                "a".to_string(),
+                "b".to_string()
+            ]))
+        );
+        assert!(config.validate().is_ok());
+
+        let single = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "tag", "type": "option", "default": ["a", "b"]}]
+        }"#;
+        assert!(matches!(
+            Config::from_json(single).unwrap().validate(),
+            Err(ConfigError::DefaultArrayRequiresMultiple(name)) if name == "tag"
+        ));
+
+        // Every element is checked against choices
+        let bad_choice = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "tags", "type": "option", "multiple": true,
+                      "choices": ["a", "b"], "default": ["a", "c"]}]
+        }"#;
+        assert!(matches!(
+            Config::from_json(bad_choice).unwrap().validate(),
+            Err(ConfigError::DefaultNotInChoices(_, default)) if default == "c"
+        ));
+
+        let number = r#"{"name": "test", "args": [{"name": "n", "type": "option", "default": 5}]}"#;
+        let err = Config::from_json(number).unwrap_err();
+        assert!(
+            err.to_string().contains("a string or an array of strings"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive_choices_validation() {
+        let config = |arg: &str| {
+            Config::from_json(&format!(
+                r#"{{"schema_version": 2, "name": "test", "args": [{}]}}"#,
+                arg
+            ))
+            .unwrap()
+            .validate()
+        };
+
+        // Choices differing only by case are distinct unless matching ignores case
+        let arg = r#"{"name": "format", "type": "option", "choices": ["json", "JSON"]"#;
+        assert!(config(&format!("{}}}", arg)).is_ok());
+        assert!(matches!(
+            config(&format!(r#"{}, "case_insensitive": true}}"#, arg)),
+            Err(ConfigError::DuplicateChoice(ref name, ref value)) if name == "format" && value == "JSON"
+        ));
+
+        // A default in another case still names a declared choice
+        assert!(config(
+            r#"{"name": "format", "type": "option", "choices": ["json"], "case_insensitive": true, "default": "JSON"}"#
+        )
+        .is_ok());
+
+        assert!(matches!(
+            config(r#"{"name": "format", "type": "option", "case_insensitive": true}"#),
+            Err(ConfigError::CaseInsensitiveRequiresChoices(ref name)) if name == "format"
+        ));
+    }
+
+    #[test]
+    fn test_auto_env_setting_matrix() {
+        let json = |auto_env: &str| {
+            format!(
+                r#"{{"schema_version": 2, "name": "test"{}, "args": [
+                    {{"name": "output", "type": "option"}},
+                    {{"name": "token", "type": "option", "env": "API_TOKEN"}},
+                    {{"name": "mode", "type": "option", "env": false}}
+                ]}}"#,
+                auto_env
+            )
+        };
+        let envs = |config: &Config| -> Vec<Option<String>> {
+            config
+                .args
+                .iter()
+                .map(|arg| arg.effective_env("APP_", 2, config.auto_env_enabled()))
+                .collect()
+        };
+
+        // Global on (explicit or default): auto-env unless the arg overrides it
+        for global in ["", r#", "auto_env": true"#] {
+            let config = Config::from_json(&json(global)).unwrap();
+            config.validate().unwrap();
+            assert_eq!(
+                envs(&config),
+                vec![
+                    Some("APP_OUTPUT".to_string()),
+                    Some("API_TOKEN".to_string()),
+                    None
+                ]
+            );
+        }
+
+        // Global off: only a custom per-arg name reads the environment
+        let config = Config::from_json(&json(r#", "auto_env": false"#)).unwrap();
+        config.validate().unwrap();
+        assert!(!config.auto_env_enabled());
+        assert_eq!(
+            envs(&config),
+            vec![None, Some("API_TOKEN".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn test_auto_env_requires_v2() {
+        let config = Config::from_json(r#"{"name": "test", "auto_env": false}"#).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::AutoEnvRequiresV2)
+        ));
+    }
+
+    #[test]
+    fn test_unique_requires_multiple_values() {
+        let validate = |arg: &str| {
+            Config::from_json(&format!(
+                r#"{{"schema_version": 2, "name": "test", "args": [{}]}}"#,
+                arg
+            ))
+            .unwrap()
+            .validate()
+        };
+
+        assert!(validate(
+            r#"{"name": "file", "type": "option", "multiple": true, "unique": true}"#
+        )
+        .is_ok());
+        for arg in [
+            r#"{"name": "file", "type": "option", "unique": true}"#,
+            r#"{"name": "verbose", "type": "flag", "multiple": true, "unique": true}"#,
+        ] {
+            assert!(matches!(
+                validate(arg),
+                Err(ConfigError::UniqueRequiresMultiple(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_decimal_comma_requires_double() {
+        let validate = |schema_version: u32, arg: &str| {
+            Config::from_json(&format!(
+                r#"{{"schema_version": {}, "name": "test", "args": [{}]}}"#,
+                schema_version, arg
+            ))
+            .unwrap()
+            .validate()
+        };
+
+        assert!(validate(
+            2,
+            r#"{"name": "ratio", "type": "option", "value_type": "double", "decimal_comma": true}"#
+        )
+        .is_ok());
+        for arg in [
+            r#"{"name": "count", "type": "option", "value_type": "int", "decimal_comma": true}"#,
+            r#"{"name": "ratio", "type": "option", "value_type": "double",
+                "choices": ["0.5", "1.5"], "decimal_comma": true}"#,
+        ] {
+            assert!(matches!(
+                validate(2, arg),
+                Err(ConfigError::DecimalCommaRequiresDouble(_))
+            ));
+        }
+        assert!(matches!(
+            validate(1, r#"{"name": "ratio", "type": "option", "decimal_comma": true}"#),
+            Err(ConfigError::FieldRequiresV2(field, _)) if field == "decimal_comma"
+        ));
+    }
+
+    #[test]
+    fn test_decimal_comma_default() {
+        let validate = |arg: &str| {
+            Config::from_json(&format!(
+                r#"{{"schema_version": 2, "name": "test", "args": [{}]}}"#,
+                arg
+            ))
+            .unwrap()
+            .validate()
+        };
+
+        assert!(validate(
+            r#"{"name": "ratio", "type": "option", "value_type": "double",
+                "decimal_comma": true, "default": "0,5", "max": 1.0}"#
+        )
+        .is_ok());
+        assert!(matches!(
+            validate(
+                r#"{"name": "ratio", "type": "option", "value_type": "double",
+                    "decimal_comma": true, "default": "1,5", "max": 1.0}"#
+            ),
+            Err(ConfigError::DefaultOutOfRange(_, default, _)) if default == "1,5"
+        ));
+        // Without decimal_comma the comma is still rejected
+        assert!(matches!(
+            validate(
+                r#"{"name": "ratio", "type": "option", "value_type": "double", "default": "0,5"}"#
+            ),
+            Err(ConfigError::InvalidDefaultForValueType(..))
+        ));
+    }
+
+    #[test]
+    fn test_hidden_requires_v2() {
+        let v1 = r#"{"name": "test", "args": [{"name": "debug", "type": "flag", "hidden": true}]}"#;
+        assert!(matches!(
+            Config::from_json(v1).unwrap().validate(),
+            Err(ConfigError::FieldRequiresV2(field, _)) if field == "hidden"
+        ));
+    }
+
+    #[test]
+    fn test_required_schema_version() {
+        let v1_only = r#"{"schema_version": 2, "name": "test", "args": [
+            {"name": "verbose", "short": "v", "type": "flag"},
+            {"name": "output", "type": "option", "default": "out.txt"}
+        ]}"#;
+        let config = Config::from_json(v1_only).unwrap();
+        assert!(config.is_v2());
+        assert!(!config.uses_subcommands());
+        assert_eq!(config.required_schema_version(), 1);
+
+        let with_choices = r#"{"name": "test", "args": [
+            {"name": "level", "type": "option", "choices": ["low", "high"]}
+        ]}"#;
+        let config = Config::from_json(with_choices).unwrap();
+        assert!(!config.is_v2());
+        assert_eq!(config.required_schema_version(), 2);
+
+        let with_subcommands =
+            r#"{"schema_version": 2, "name": "test", "subcommands": [{"name": "init"}]}"#;
+        let config = Config::from_json(with_subcommands).unwrap();
+        assert!(config.uses_subcommands());
+        assert_eq!(config.required_schema_version(), 2);
+    }
+
+    #[test]
+    fn test_help_inline_or_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let help_file = dir.path().join("remove.txt");
+        std::fs::write(&help_file, "Remove a file.\n\nPaths are relative.\n").unwrap();
+        let help_file = help_file.to_str().unwrap();
+
+        let json = serde_json::json!({
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "verbose", "short": "v", "type": "flag", "help": "Be chatty"},
+                {"name": "quiet", "short": "q", "type": "flag", "help": null}
+            ],
+            "subcommands": [{"name": "remove", "help": {"file": help_file}}]
+        })
+        .to_string();
+        let config = Config::from_json(&json).unwrap();
+        assert_eq!(config.args[0].help.as_deref(), Some("Be chatty"));
+        assert_eq!(config.args[1].help, None);
+        assert_eq!(
+            config.subcommands[0].help.as_deref(),
+            Some("Remove a file.\n\nPaths are relative.")
+        );
+
+        let missing = dir.path().join("missing.txt");
+        let json = serde_json::json!({
+            "name": "test",
+            "args": [{"name": "verbose", "short": "v", "type": "flag",
+                      "help": {"file": missing.to_str().unwrap()}}]
+        })
+        .to_string();
+        let err = Config::from_json(&json).unwrap_err();
+        assert!(matches!(err, ConfigError::ParseError(..)));
+        assert!(err.to_string().contains("cannot read help file"));
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_key_order_and_whitespace() {
+        let a = Config::from_json(
+            r#"{"name":"test","args":[{"name":"verbose","short":"v","type":"flag"}]}"#,
+        )
+        .unwrap();
+        let b = Config::from_json(
+            r#"{
+                "args": [ {"type": "flag", "short": "v", "name": "verbose", "required": false} ],
+                "name": "test"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_eq!(a.fingerprint().len(), 64);
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_content() {
+        let a = Config::from_json(r#"{"name":"test","version":"1.0.0"}"#).unwrap();
+        let b = Config::from_json(r#"{"name":"test","version":"1.0.1"}"#).unwrap();
+        assert_ne!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test]
+    fn test_error_optional_positional_before_required() {
+        let json = r#"{
+            "name": "test",
+            "args": [
+                {"name": "mode", "type": "positional", "default": "fast"},
+                {"name": "input", "type": "positional", "required": true}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::OptionalPositionalBeforeRequired(a, b))
+                if a == "mode" && b == "input"
+        ));
+    }
+
+    #[test]
+    fn test_trailing_positional_with_default_is_valid() {
+        let json = r#"{
+            "name": "test",
+            "args": [
+                {"name": "input", "type": "positional", "required": true},
+                {"name": "mode", "type": "positional", "default": "fast"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_value_type_json() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "metadata", "type": "option", "value_type": "json"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.args[0].value_type, ValueType::Json);
+        assert!(config.validate().is_ok());
+        assert!(ValueType::Json.accepts(r#"{"a": [1, 2]}"#));
+        assert!(!ValueType::Json.accepts("{a: 1}"));
+    }
+
+    #[test]
+    fn test_error_json_value_type_with_choices() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "metadata", "type": "option", "value_type": "json", "choices": ["{}"]}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::ChoicesWithValueType(name, ValueType::Json)) if name == "metadata"
+        ));
+    }
+
+    #[test]
+    fn test_value_type_base64() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "payload", "type": "option", "value_type": "base64"},
+                {"name": "token", "type": "option", "value_type": "base64url"}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.args[0].value_type, ValueType::Base64);
+        assert_eq!(config.args[1].value_type, ValueType::Base64Url);
+        assert!(config.validate().is_ok());
+
+        // Standard alphabet
+        assert!(ValueType::Base64.accepts("aGVsbG8gd29ybGQ="));
+        assert!(ValueType::Base64.accepts("+/8="));
+        assert!(!ValueType::Base64.accepts("-_8="));
+        // URL-safe alphabet
+        assert!(ValueType::Base64Url.accepts("-_8="));
+        assert!(!ValueType::Base64Url.accepts("+/8="));
+        // Padding is required and must be canonical
+        assert!(!ValueType::Base64.accepts("aGVsbG8"));
+        assert!(!ValueType::Base64.accepts("aGVsbG8=="));
+        assert!(!ValueType::Base64Url.accepts("aGVsbG8"));
+    }
+
+    #[test]
+    fn test_value_type_hex() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "hash", "type": "option", "value_type": "hex"}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.args[0].value_type, ValueType::Hex);
+        assert!(config.validate().is_ok());
+
+        assert_eq!(normalize_hex("DEADbeef").unwrap(), "deadbeef");
+        assert_eq!(normalize_hex("0xAB01").unwrap(), "ab01");
+        assert_eq!(normalize_hex("0X00").unwrap(), "00");
+        assert_eq!(
+            normalize_hex("abc").unwrap_err(),
+            "invalid hex: expected an even number of digits, got 3"
+        );
+        assert_eq!(
+            normalize_hex("zz").unwrap_err(),
+            "invalid hex: 'z' is not a hex digit"
+        );
+        assert!(!ValueType::Hex.accepts("0x"));
+        assert!(!ValueType::Hex.accepts(""));
+    }
+
+    #[test]
+    fn test_error_base64_value_type_with_choices() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "payload", "type": "option", "value_type": "base64", "choices": ["AA=="]}]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::ChoicesWithValueType(ref name, ValueType::Base64) if name == "payload"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "'choices' cannot be used with value_type 'base64' on argument 'payload'"
+        );
+    }
 }
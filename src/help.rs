@@ -1,234 +1,289 @@
 //! Help and version text generation for target scripts using Clap.
 
-use crate::config::{ArgConfig, ArgType, Config, SubcommandConfig, ValueType};
-use clap::{Arg, ArgAction, Command};
+use crate::command::build_command;
+use crate::config::{ArgConfig, ArgType, Config, SubcommandConfig};
 
-/// Build a Clap Command from a Config (for help/version generation).
-fn build_command(config: &Config, effective_name: &str) -> Command {
-    let mut cmd = Command::new(effective_name.to_string()).disable_help_subcommand(true);
-
-    // Set version if provided
-    if let Some(ref version) = config.version {
-        cmd = cmd.version(version.clone());
-    }
-
-    // Set description if provided
-    if let Some(ref description) = config.description {
-        cmd = cmd.about(description.clone());
+/// Generate the full help text for a script.
+///
+/// The `effective_name` parameter is the program name to use (from CLI --name or config name).
+pub fn generate_help(config: &Config, effective_name: &str) -> String {
+    let mut cmd = build_command(config, effective_name);
+    // Full help is what `--help` shows; only needed when there is more to show
+    if config.long_description.is_some() {
+        cmd.render_long_help().to_string()
+    } else {
+        cmd.render_help().to_string()
     }
+}
 
-    let prefix = config.effective_prefix();
-    let schema_version = config.schema_version;
-
-    // Track positional index for ordering
-    let mut positional_index = 1usize;
-
-    // Add arguments from config
-    for arg_config in &config.args {
-        let arg = build_arg(arg_config, &mut positional_index, prefix, schema_version);
-        cmd = cmd.arg(arg);
-    }
+/// Describe the Clap command built from the config, for troubleshooting:
+/// the usage line, then each registered arg's id, short, long, action and
+/// `num_args`, with each subcommand's args under its own heading.
+pub fn generate_command_dump(config: &Config, effective_name: &str) -> String {
+    let mut cmd = build_command(config, effective_name);
+    cmd.build();
 
-    // Add subcommands (schema v2)
-    for subcmd_config in &config.subcommands {
-        let subcmd = build_subcommand(subcmd_config, prefix, schema_version);
-        cmd = cmd.subcommand(subcmd);
+    let mut out = format!("{}\n", cmd.render_usage());
+    push_arg_dump(&mut out, &cmd);
+    for subcmd in cmd.get_subcommands() {
+        out.push_str(&format!("\nsubcommand {}:\n", subcmd.get_name()));
+        push_arg_dump(&mut out, subcmd);
     }
+    out
+}
 
-    // Require subcommand if any defined
-    if !config.subcommands.is_empty() {
-        cmd = cmd.subcommand_required(true);
-        cmd = cmd.arg_required_else_help(true);
+/// Append one line per arg registered on `cmd`, including Clap's own.
+fn push_arg_dump(out: &mut String, cmd: &clap::Command) {
+    for arg in cmd.get_arguments() {
+        let short = arg.get_short().map(|c| format!("-{}", c));
+        let long = arg.get_long().map(|l| format!("--{}", l));
+        let num_args = arg.get_num_args().map(|range| range.to_string());
+        out.push_str(&format!(
+            "  {}: short={} long={} action={:?} num_args={}\n",
+            arg.get_id(),
+            short.as_deref().unwrap_or("none"),
+            long.as_deref().unwrap_or("none"),
+            arg.get_action(),
+            num_args.as_deref().unwrap_or("none"),
+        ));
     }
-
-    cmd
 }
 
-/// Build a Clap Command for a subcommand config.
-fn build_subcommand(config: &SubcommandConfig, prefix: &str, schema_version: u32) -> Command {
-    let mut cmd = Command::new(config.name.clone());
-
-    if let Some(ref help) = config.help {
-        cmd = cmd.about(help.clone());
+/// Generate version string.
+///
+/// The `effective_name` parameter is the program name to use (from CLI --name or config name).
+/// When the config has `version_info`, the description and build metadata follow
+/// on separate lines.
+pub fn generate_version(config: &Config, effective_name: &str) -> String {
+    let mut version = effective_name.to_string();
+    if let Some(ref v) = config.version {
+        version.push(' ');
+        version.push_str(v);
     }
+    version.push('\n');
 
-    // Track positional index for ordering
-    let mut positional_index = 1usize;
+    if let Some(ref info) = config.version_info {
+        if let Some(ref description) = config.description {
+            version.push_str(&format!("{}\n", description));
+        }
 
-    // Add arguments
-    for arg_config in &config.args {
-        let arg = build_arg(arg_config, &mut positional_index, prefix, schema_version);
-        cmd = cmd.arg(arg);
+        let build_date = info
+            .build_date
+            .clone()
+            .or_else(|| std::env::var("VERGEN_BUILD_DATE").ok())
+            .unwrap_or_else(|| "unknown".to_string());
+        version.push_str(&format!("build date: {}\n", build_date));
+
+        let git_hash = info
+            .git_hash
+            .clone()
+            .or_else(|| std::env::var("VERGEN_GIT_SHA").ok());
+        if let Some(git_hash) = git_hash {
+            version.push_str(&format!("git hash: {}\n", git_hash));
+        }
+        if let Some(ref arch) = info.arch {
+            version.push_str(&format!("arch: {}\n", arch));
+        }
+        if let Some(ref os) = info.os {
+            version.push_str(&format!("os: {}\n", os));
+        }
     }
 
-    cmd
+    version
 }
 
-/// Build a Clap Arg from an ArgConfig.
-fn build_arg(
-    arg_config: &ArgConfig,
-    positional_index: &mut usize,
-    prefix: &str,
-    schema_version: u32,
-) -> Arg {
-    let mut arg = Arg::new(arg_config.name.clone());
-
-    match arg_config.arg_type {
-        ArgType::Flag => {
-            // For flags, use Count if multiple, SetTrue otherwise
-            if arg_config.multiple {
-                arg = arg.action(ArgAction::Count);
-            } else {
-                arg = arg.action(ArgAction::SetTrue);
-            }
-
-            if let Some(short) = arg_config.short {
-                arg = arg.short(short);
-            }
-
-            // Use effective_long which falls back to name if neither short nor long is specified
-            if let Some(long) = arg_config.effective_long() {
-                arg = arg.long(long.to_string());
-            }
-        }
-        ArgType::Option => {
-            // For options, use Append if multiple, Set otherwise
-            if arg_config.multiple {
-                arg = arg.action(ArgAction::Append);
-            } else {
-                arg = arg.action(ArgAction::Set);
-            }
-
-            if let Some(short) = arg_config.short {
-                arg = arg.short(short);
-            }
+/// Generate a JSON inventory of the config's subcommands.
+///
+/// Each entry has the subcommand's `name`, `help` (or null), and the names of
+/// its `args`, in declaration order, plus a nested `subcommands` list when it
+/// has children.
+pub fn generate_subcommand_list(config: &Config) -> String {
+    let subcommands = subcommand_list(&config.subcommands);
+    let mut json = serde_json::to_string_pretty(&subcommands).unwrap_or_default();
+    json.push('\n');
+    json
+}
 
-            // Use effective_long which falls back to name if neither short nor long is specified
-            if let Some(long) = arg_config.effective_long() {
-                arg = arg.long(long.to_string());
+/// JSON entries for one level of visible subcommands, recursing into children.
+fn subcommand_list(subcommands: &[SubcommandConfig]) -> serde_json::Value {
+    subcommands
+        .iter()
+        .filter(|subcmd| !subcmd.hidden)
+        .map(|subcmd| {
+            let mut entry = serde_json::json!({
+                "name": subcmd.name,
+                "help": subcmd.help,
+                "args": subcmd.args.iter().map(|arg| &arg.name).collect::<Vec<_>>(),
+            });
+            if !subcmd.aliases.is_empty() {
+                entry["aliases"] = serde_json::json!(subcmd.aliases);
             }
-
-            arg = arg.value_name("VALUE");
-        }
-        ArgType::Positional => {
-            arg = arg.index(*positional_index);
-            *positional_index += 1;
-
-            // For multiple positionals
-            if arg_config.multiple {
-                arg = arg.action(ArgAction::Append);
+            if !subcmd.subcommands.is_empty() {
+                entry["subcommands"] = subcommand_list(&subcmd.subcommands);
             }
-        }
-    }
+            entry
+        })
+        .collect()
+}
 
-    if arg_config.required {
-        arg = arg.required(true);
+/// Describe how shclap interprets a config, after defaults and normalization.
+///
+/// Lists each arg with its effective options, output variable, env fallback,
+/// value type, and choices, grouped by scope (top level, then each subcommand).
+pub fn generate_config_explanation(config: &Config, name: &str, prefix: &str) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("name: {}\n", name));
+    out.push_str(&format!("schema version: {}\n", config.schema_version));
+    out.push_str(&format!("prefix: {}\n", prefix));
+    if let Some(ref strip) = config.strip_name_prefix {
+        out.push_str(&format!("strip name prefix: {}\n", strip));
     }
 
-    if let Some(ref default) = arg_config.default {
-        arg = arg.default_value(default.clone());
+    out.push_str("\nargs:\n");
+    explain_args(&mut out, config, &config.args, prefix);
+    for (path, subcmd) in config.all_subcommands() {
+        out.push_str(&format!("\nsubcommand {}:\n", path));
+        explain_args(&mut out, config, &subcmd.args, prefix);
     }
+    out
+}
 
-    if let Some(ref help) = arg_config.help {
-        arg = arg.help(help.clone());
+/// Append the explanation lines for one scope's args.
+fn explain_args(out: &mut String, config: &Config, args: &[ArgConfig], prefix: &str) {
+    if args.is_empty() {
+        out.push_str("  (none)\n");
+        return;
     }
 
-    // Schema v2: Environment variable fallback (auto-env or custom)
-    if let Some(env_var) = arg_config.effective_env(prefix, schema_version) {
-        arg = arg.env(env_var);
-    }
+    let strip = config.strip_name_prefix.as_deref();
+    for arg in args {
+        let arg_type = match arg.arg_type {
+            ArgType::Flag => "flag",
+            ArgType::Option => "option",
+            ArgType::Positional => "positional",
+        };
+        out.push_str(&format!("  {} ({})\n", arg.name, arg_type));
 
-    // Schema v2: num_args range
-    if let Some(ref num_args) = arg_config.num_args {
-        if let Some(range) = parse_num_args_range(num_args) {
-            arg = arg.num_args(range);
+        let options = arg.option_strings();
+        if !options.is_empty() {
+            out.push_str(&format!("    options: {}\n", options.join(", ")));
+        }
+        out.push_str(&format!(
+            "    variable: {}\n",
+            config.arg_var_name(&arg.name, prefix)
+        ));
+        let env = arg
+            .effective_env_with_strip(
+                prefix,
+                config.schema_version,
+                config.auto_env_enabled(),
+                strip,
+            )
+            .unwrap_or_else(|| "(none)".to_string());
+        out.push_str(&format!("    env: {}\n", env));
+        out.push_str(&format!("    value type: {}\n", arg.value_type));
+        if let Some(ref choices) = arg.choices {
+            out.push_str(&format!("    choices: {}\n", choices.join(", ")));
+        }
+        if let Some(ref default) = arg.default {
+            out.push_str(&format!("    default: {}\n", default));
+        }
+        if arg.required {
+            out.push_str("    required: yes\n");
         }
     }
+}
 
-    // Schema v2: Value delimiter
-    if let Some(delim) = arg_config.delimiter {
-        arg = arg.value_delimiter(delim);
-    }
-
-    // Schema v2: Choices (possible values) - takes precedence over value_type
-    if let Some(ref choices) = arg_config.choices {
-        arg = arg.value_parser(clap::builder::PossibleValuesParser::new(choices.clone()));
-    } else {
-        // Schema v2: Apply value_type parser if no choices specified
-        match arg_config.value_type {
-            ValueType::String => {} // Default, no special parser
-            ValueType::Int => {
-                arg = arg.value_parser(clap::value_parser!(i64));
-            }
-            ValueType::Bool => {
-                arg = arg.value_parser(clap::builder::PossibleValuesParser::new(["true", "false"]));
-            }
-            ValueType::Double => {
-                arg = arg.value_parser(clap::value_parser!(f64));
+/// Describe how the interface changed from `old` to `new`, one change per line.
+///
+/// Compares arg names, types, required status, and choices at the top level
+/// and within subcommands present in both, plus added and removed subcommands.
+/// Returns an empty string when the interfaces match.
+pub fn generate_config_diff(old: &Config, new: &Config) -> String {
+    let mut out = String::new();
+    diff_args(&mut out, "", &old.args, &new.args);
+
+    // Nested subcommands are matched by their dotted path, e.g. `remote.add`
+    let old_subcommands = old.all_subcommands();
+    let new_subcommands = new.all_subcommands();
+    for (path, subcmd) in &new_subcommands {
+        match old_subcommands.iter().find(|(p, _)| p == path) {
+            Some((_, old_subcmd)) => {
+                let scope = format!("{}: ", path);
+                diff_args(&mut out, &scope, &old_subcmd.args, &subcmd.args);
             }
+            None => out.push_str(&format!("+ subcommand {}\n", path)),
         }
     }
-
-    arg
-}
-
-/// Parse a num_args string into a Clap ValueRange.
-fn parse_num_args_range(s: &str) -> Option<clap::builder::ValueRange> {
-    let s = s.trim();
-
-    // Single number
-    if let Ok(n) = s.parse::<usize>() {
-        return Some(clap::builder::ValueRange::new(n..=n));
+    for (path, _) in &old_subcommands {
+        if !new_subcommands.iter().any(|(p, _)| p == path) {
+            out.push_str(&format!("- subcommand {}\n", path));
+        }
     }
+    out
+}
 
-    // Range formats
-    if let Some(idx) = s.find("..") {
-        let start: usize = s[..idx].parse().ok()?;
-        let rest = &s[idx + 2..];
-
-        if rest.is_empty() {
-            // Unbounded: "N.."
-            return Some(clap::builder::ValueRange::new(start..));
-        }
-        if let Ok(end) = rest.parse::<usize>() {
-            // Exclusive: "N..M"
-            return Some(clap::builder::ValueRange::new(start..end));
-        }
-        if let Some(stripped) = rest.strip_prefix('=') {
-            if let Ok(end) = stripped.parse::<usize>() {
-                // Inclusive: "N..=M"
-                return Some(clap::builder::ValueRange::new(start..=end));
+/// Append the diff lines for one scope's args, in `new` then `old` order.
+fn diff_args(out: &mut String, scope: &str, old: &[ArgConfig], new: &[ArgConfig]) {
+    let type_name = |arg: &ArgConfig| match arg.arg_type {
+        ArgType::Flag => "flag",
+        ArgType::Option => "option",
+        ArgType::Positional => "positional",
+    };
+    let required = |arg: &ArgConfig| if arg.required { "yes" } else { "no" };
+    let choices = |arg: &ArgConfig| match arg.choices {
+        Some(ref choices) => format!("[{}]", choices.join(", ")),
+        None => "none".to_string(),
+    };
+
+    for arg in new {
+        let Some(old_arg) = old.iter().find(|a| a.name == arg.name) else {
+            out.push_str(&format!(
+                "+ {}arg {} ({})\n",
+                scope,
+                arg.name,
+                type_name(arg)
+            ));
+            continue;
+        };
+        let changes = [
+            (
+                "type",
+                type_name(old_arg).to_string(),
+                type_name(arg).to_string(),
+            ),
+            (
+                "required",
+                required(old_arg).to_string(),
+                required(arg).to_string(),
+            ),
+            ("choices", choices(old_arg), choices(arg)),
+        ];
+        for (field, before, after) in changes {
+            if before != after {
+                out.push_str(&format!(
+                    "~ {}arg {}: {} {} -> {}\n",
+                    scope, arg.name, field, before, after
+                ));
             }
         }
     }
-
-    None
-}
-
-/// Generate the full help text for a script.
-///
-/// The `effective_name` parameter is the program name to use (from CLI --name or config name).
-pub fn generate_help(config: &Config, effective_name: &str) -> String {
-    let mut cmd = build_command(config, effective_name);
-    cmd.render_help().to_string()
-}
-
-/// Generate version string.
-///
-/// The `effective_name` parameter is the program name to use (from CLI --name or config name).
-pub fn generate_version(config: &Config, effective_name: &str) -> String {
-    let mut version = effective_name.to_string();
-    if let Some(ref v) = config.version {
-        version.push(' ');
-        version.push_str(v);
+    for arg in old {
+        if !new.iter().any(|a| a.name == arg.name) {
+            out.push_str(&format!(
+                "- {}arg {} ({})\n",
+                scope,
+                arg.name,
+                type_name(arg)
+            ));
+        }
     }
-    version.push('\n');
-    version
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{DefaultValue, UndefinedEnv, ValueType};
 
     fn make_config(
         name: &str,
@@ -240,10 +295,28 @@ mod tests {
             schema_version: 1,
             name: Some(name.to_string()),
             description: description.map(|s| s.to_string()),
+            long_description: None,
             version: version.map(|s| s.to_string()),
             prefix: None,
             args,
             subcommands: vec![],
+            strip_name_prefix: None,
+            undefined_env: UndefinedEnv::Empty,
+            version_info: None,
+            help_if_no_args: false,
+            help_template: None,
+            author: None,
+            homepage: None,
+            before_help: None,
+            after_help: None,
+            next_line_help: false,
+            allow_abbreviations: false,
+            infer_subcommands: false,
+            min_shclap_version: None,
+            uppercase_prefix: false,
+            strict: false,
+            auto_env: None,
+            group_by_kind: false,
         }
     }
 
@@ -272,6 +345,26 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::String,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         }
     }
 
@@ -289,7 +382,7 @@ mod tests {
             long: long.map(|s| s.to_string()),
             arg_type: ArgType::Option,
             required,
-            default: default.map(|s| s.to_string()),
+            default: default.map(|s| DefaultValue::Single(s.to_string())),
             help: help.map(|s| s.to_string()),
             env: None,
             multiple: false,
@@ -297,6 +390,26 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::String,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         }
     }
 
@@ -315,6 +428,26 @@ mod tests {
             delimiter: None,
             choices: None,
             value_type: ValueType::String,
+            expand_env: false,
+            grouped: false,
+            deprecated_in_favor_of: None,
+            require_equals: false,
+            negative_numbers_only: false,
+            next_line_help: false,
+            emit_index: false,
+            conflicts_with_subcommand: Vec::new(),
+            min: None,
+            max: None,
+            path_must_exist: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            aliases: Vec::new(),
+            max_occurrences: None,
+            case_insensitive: false,
+            unique: false,
+            hidden: false,
+            decimal_comma: false,
+            positional_index: None,
         }
     }
 
@@ -457,6 +590,7 @@ mod tests {
             schema_version: 2,
             name: Some("test".to_string()),
             description: None,
+            long_description: None,
             version: None,
             prefix: None,
             args: vec![ArgConfig {
@@ -477,8 +611,45 @@ mod tests {
                     "toml".to_string(),
                 ]),
                 value_type: ValueType::String,
+                expand_env: false,
+                grouped: false,
+                deprecated_in_favor_of: None,
+                require_equals: false,
+                negative_numbers_only: false,
+                next_line_help: false,
+                emit_index: false,
+                conflicts_with_subcommand: Vec::new(),
+                min: None,
+                max: None,
+                path_must_exist: false,
+                conflicts_with: Vec::new(),
+                requires: Vec::new(),
+                aliases: Vec::new(),
+                max_occurrences: None,
+                case_insensitive: false,
+                unique: false,
+                hidden: false,
+                decimal_comma: false,
+                positional_index: None,
             }],
             subcommands: vec![],
+            strip_name_prefix: None,
+            undefined_env: UndefinedEnv::Empty,
+            version_info: None,
+            help_if_no_args: false,
+            help_template: None,
+            author: None,
+            homepage: None,
+            before_help: None,
+            after_help: None,
+            next_line_help: false,
+            allow_abbreviations: false,
+            infer_subcommands: false,
+            min_shclap_version: None,
+            uppercase_prefix: false,
+            strict: false,
+            auto_env: None,
+            group_by_kind: false,
         };
 
         let help = generate_help(&config, get_name(&config));
@@ -498,6 +669,7 @@ mod tests {
             schema_version: 2,
             name: Some("test".to_string()),
             description: None,
+            long_description: None,
             version: None,
             prefix: None,
             args: vec![ArgConfig {
@@ -514,8 +686,45 @@ mod tests {
                 delimiter: None,
                 choices: None,
                 value_type: ValueType::Bool,
+                expand_env: false,
+                grouped: false,
+                deprecated_in_favor_of: None,
+                require_equals: false,
+                negative_numbers_only: false,
+                next_line_help: false,
+                emit_index: false,
+                conflicts_with_subcommand: Vec::new(),
+                min: None,
+                max: None,
+                path_must_exist: false,
+                conflicts_with: Vec::new(),
+                requires: Vec::new(),
+                aliases: Vec::new(),
+                max_occurrences: None,
+                case_insensitive: false,
+                unique: false,
+                hidden: false,
+                decimal_comma: false,
+                positional_index: None,
             }],
             subcommands: vec![],
+            strip_name_prefix: None,
+            undefined_env: UndefinedEnv::Empty,
+            version_info: None,
+            help_if_no_args: false,
+            help_template: None,
+            author: None,
+            homepage: None,
+            before_help: None,
+            after_help: None,
+            next_line_help: false,
+            allow_abbreviations: false,
+            infer_subcommands: false,
+            min_shclap_version: None,
+            uppercase_prefix: false,
+            strict: false,
+            auto_env: None,
+            group_by_kind: false,
         };
 
         let help = generate_help(&config, get_name(&config));
@@ -535,6 +744,7 @@ mod tests {
             schema_version: 2,
             name: Some("test".to_string()),
             description: None,
+            long_description: None,
             version: None,
             prefix: None,
             args: vec![ArgConfig {
@@ -551,8 +761,45 @@ mod tests {
                 delimiter: None,
                 choices: None,
                 value_type: ValueType::Double,
+                expand_env: false,
+                grouped: false,
+                deprecated_in_favor_of: None,
+                require_equals: false,
+                negative_numbers_only: false,
+                next_line_help: false,
+                emit_index: false,
+                conflicts_with_subcommand: Vec::new(),
+                min: None,
+                max: None,
+                path_must_exist: false,
+                conflicts_with: Vec::new(),
+                requires: Vec::new(),
+                aliases: Vec::new(),
+                max_occurrences: None,
+                case_insensitive: false,
+                unique: false,
+                hidden: false,
+                decimal_comma: false,
+                positional_index: None,
             }],
             subcommands: vec![],
+            strip_name_prefix: None,
+            undefined_env: UndefinedEnv::Empty,
+            version_info: None,
+            help_if_no_args: false,
+            help_template: None,
+            author: None,
+            homepage: None,
+            before_help: None,
+            after_help: None,
+            next_line_help: false,
+            allow_abbreviations: false,
+            infer_subcommands: false,
+            min_shclap_version: None,
+            uppercase_prefix: false,
+            strict: false,
+            auto_env: None,
+            group_by_kind: false,
         };
 
         let double_help = generate_help(&double_config, get_name(&double_config));
@@ -567,4 +814,408 @@ mod tests {
             double_help
         );
     }
+
+    #[test]
+    fn test_generate_subcommand_list() {
+        let config = Config::from_json(
+            r#"{"schema_version":2,"name":"app","subcommands":[
+                {"name":"init","help":"Initialize a project","args":[
+                    {"name":"template","type":"positional"},
+                    {"name":"force","short":"f","type":"flag"}
+                ]},
+                {"name":"status"}
+            ]}"#,
+        )
+        .unwrap();
+        let output = generate_subcommand_list(&config);
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!([
+                {"name": "init", "help": "Initialize a project", "args": ["template", "force"]},
+                {"name": "status", "help": null, "args": []}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_hidden_subcommand_left_out_of_help_and_list() {
+        let config = Config::from_json(
+            r#"{"schema_version":2,"name":"app","subcommands":[
+                {"name":"build","help":"Build it"},
+                {"name":"debug-dump","help":"Internal state dump","hidden":true}
+            ]}"#,
+        )
+        .unwrap();
+
+        let help = generate_help(&config, "app");
+        assert!(help.contains("build"), "{}", help);
+        assert!(!help.contains("debug-dump"), "{}", help);
+
+        let list: serde_json::Value =
+            serde_json::from_str(&generate_subcommand_list(&config)).unwrap();
+        assert_eq!(list.as_array().unwrap().len(), 1);
+        assert_eq!(list[0]["name"], "build");
+    }
+
+    #[test]
+    fn test_long_description_and_before_help() {
+        use crate::parser::{parse_args, ParseOutcome};
+
+        let config = Config::from_json(
+            r#"{"name":"app","version":"1.2.3","description":"An app",
+                "long_description":"An app that does one thing.\n\nIt does it in great detail.",
+                "before_help":"=== APP ===",
+                "args":[{"name":"verbose","short":"v","long":"verbose","type":"flag"}]}"#,
+        )
+        .unwrap();
+
+        let help = generate_help(&config, "app");
+        assert!(help.starts_with("=== APP ===\n"), "{}", help);
+        assert!(
+            help.contains("An app that does one thing.\n\nIt does it in great detail."),
+            "{}",
+            help
+        );
+        assert!(help.find("=== APP ===") < help.find("Usage:"), "{}", help);
+
+        // -h keeps the one-line description
+        match parse_args(&config, &["-h".to_string()], "app") {
+            ParseOutcome::Help(short) => {
+                assert!(short.contains("An app\n"), "{}", short);
+                assert!(!short.contains("great detail"), "{}", short);
+            }
+            other => panic!("Expected Help, got {:?}", other),
+        }
+        assert_eq!(generate_version(&config, "app"), "app 1.2.3\n");
+    }
+
+    #[test]
+    fn test_generate_version_without_info_unchanged() {
+        let config =
+            Config::from_json(r#"{"name":"app","version":"1.2.3","description":"An app"}"#)
+                .unwrap();
+        assert_eq!(generate_version(&config, "app"), "app 1.2.3\n");
+    }
+
+    #[test]
+    fn test_generate_version_with_info() {
+        let config = Config::from_json(
+            r#"{"name":"app","version":"1.2.3","description":"An app","version_info":{
+                "build_date":"2024-05-01","git_hash":"abc1234","arch":"x86_64","os":"linux"
+            }}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            generate_version(&config, "app"),
+            "app 1.2.3\nAn app\nbuild date: 2024-05-01\ngit hash: abc1234\narch: x86_64\nos: linux\n"
+        );
+    }
+
+    #[test]
+    fn test_help_template() {
+        let config = Config::from_json(
+            r#"{"name":"app","description":"Does things","help_template":"{usage-heading} {usage}\n--\n{about}\n{all-args}","args":[
+                {"name":"verbose","short":"v","type":"flag","help":"Be loud"}
+            ]}"#,
+        )
+        .unwrap();
+        let help = generate_help(&config, "app");
+
+        let usage = help.find("Usage: app").unwrap();
+        let about = help.find("Does things").unwrap();
+        assert!(usage < about, "usage should come before about: {}", help);
+        assert!(help.contains("\n--\nDoes things\n"));
+        assert!(help.contains("Be loud"));
+    }
+
+    #[test]
+    fn test_generate_config_explanation() {
+        let json = r#"{
+            "schema_version": 2,
+            "name": "test",
+            "strip_name_prefix": "opt_",
+            "args": [
+                {"name": "opt_verbose", "short": "v", "type": "flag"},
+                {"name": "format", "type": "option", "choices": ["json", "yaml"], "default": "json", "env": false}
+            ],
+            "subcommands": [
+                {"name": "run", "args": [{"name": "target", "type": "positional", "required": true}]}
+            ]
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        let output = generate_config_explanation(&config, "myapp", "APP_");
+
+        assert_eq!(
+            output,
+            "name: myapp\n\
+             schema version: 2\n\
+             prefix: APP_\n\
+             strip name prefix: opt_\n\
+             \n\
+             args:\n\
+             \x20 opt_verbose (flag)\n\
+             \x20   options: -v\n\
+             \x20   variable: APP_VERBOSE\n\
+             \x20   env: APP_VERBOSE\n\
+             \x20   value type: string\n\
+             \x20 format (option)\n\
+             \x20   options: --format\n\
+             \x20   variable: APP_FORMAT\n\
+             \x20   env: (none)\n\
+             \x20   value type: string\n\
+             \x20   choices: json, yaml\n\
+             \x20   default: json\n\
+             \n\
+             subcommand run:\n\
+             \x20 target (positional)\n\
+             \x20   variable: APP_TARGET\n\
+             \x20   env: APP_TARGET\n\
+             \x20   value type: string\n\
+             \x20   required: yes\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_config_explanation_v1_has_no_env() {
+        let config =
+            Config::from_json(r#"{"name":"test","args":[{"name":"out","type":"option"}]}"#)
+                .unwrap();
+        let output = generate_config_explanation(&config, "test", "SHCLAP_");
+        assert!(output.contains("schema version: 1\n"));
+        assert!(output.contains("    env: (none)\n"));
+    }
+
+    #[test]
+    fn test_help_footer_author_homepage() {
+        let config = Config::from_json(
+            r#"{"name":"app","author":"Jane Doe <jane@example.com>","homepage":"https://example.com/app"}"#,
+        )
+        .unwrap();
+        let help = generate_help(&config, "app");
+        assert!(
+            help.contains("Author: Jane Doe <jane@example.com>"),
+            "{}",
+            help
+        );
+        assert!(
+            help.contains("Homepage: https://example.com/app"),
+            "{}",
+            help
+        );
+    }
+
+    #[test]
+    fn test_after_help_follows_options() {
+        let config = Config::from_json(
+            r#"{"name":"app","after_help":"Examples:\n  app -v input.txt",
+                "homepage":"https://example.com/app",
+                "args":[{"name":"verbose","short":"v","type":"flag"}]}"#,
+        )
+        .unwrap();
+        let help = generate_help(&config, "app");
+        let options = help.find("Options:").expect("options section");
+        let footer = help.find("Examples:\n  app -v input.txt").expect("footer");
+        let homepage = help.find("Homepage:").expect("homepage");
+        assert!(options < footer && footer < homepage, "{}", help);
+    }
+
+    #[test]
+    fn test_help_footer_absent_by_default() {
+        let config = Config::from_json(r#"{"name":"app"}"#).unwrap();
+        let help = generate_help(&config, "app");
+        assert!(!help.contains("Author:"));
+        assert!(!help.contains("Homepage:"));
+    }
+
+    #[test]
+    fn test_next_line_help_on_arg() {
+        let config = Config::from_json(
+            r#"{"name":"app","args":[
+                {"name":"output","type":"option","help":"Where to write","next_line_help":true},
+                {"name":"level","type":"option","help":"How loud"}
+            ]}"#,
+        )
+        .unwrap();
+        let help = generate_help(&config, "app");
+        let lines: Vec<&str> = help.lines().collect();
+
+        // The flagged arg's help moves to the following line
+        let output = lines.iter().position(|l| l.contains("--output")).unwrap();
+        assert!(!lines[output].contains("Where to write"), "{}", help);
+        assert_eq!(lines[output + 1].trim(), "Where to write", "{}", help);
+    }
+
+    #[test]
+    fn test_help_beside_arg_by_default() {
+        let config = Config::from_json(
+            r#"{"name":"app","args":[{"name":"level","type":"option","help":"How loud"}]}"#,
+        )
+        .unwrap();
+        let help = generate_help(&config, "app");
+        let level = help.lines().find(|l| l.contains("--level")).unwrap();
+        assert!(level.contains("How loud"), "{}", help);
+    }
+
+    #[test]
+    fn test_hidden_arg_left_out_of_help() {
+        let config = Config::from_json(
+            r#"{"schema_version":2,"name":"app","args":[
+                {"name":"level","type":"option","help":"How loud"},
+                {"name":"debug-dump","type":"option","help":"Internal","hidden":true}
+            ]}"#,
+        )
+        .unwrap();
+        let help = generate_help(&config, "app");
+        assert!(help.contains("--level"), "{}", help);
+        assert!(!help.contains("--debug-dump"), "{}", help);
+    }
+
+    #[test]
+    fn test_generate_command_dump() {
+        let config = Config::from_json(
+            r#"{"schema_version":2,"name":"app","args":[
+                {"name":"verbose","short":"v","type":"flag","multiple":true},
+                {"name":"output","short":"o","long":"output","type":"option"},
+                {"name":"files","type":"positional","multiple":true}
+            ],"subcommands":[
+                {"name":"build","args":[{"name":"release","long":"release","type":"flag"}]}
+            ]}"#,
+        )
+        .unwrap();
+        let dump = generate_command_dump(&config, "app");
+
+        assert!(dump.starts_with("Usage: app"), "{}", dump);
+        assert!(
+            dump.contains("  verbose: short=-v long=none action=Count num_args=0\n"),
+            "{}",
+            dump
+        );
+        assert!(
+            dump.contains("  output: short=-o long=--output action=Set num_args=1\n"),
+            "{}",
+            dump
+        );
+        assert!(
+            dump.contains("  files: short=none long=none action=Append num_args=1\n"),
+            "{}",
+            dump
+        );
+        assert!(
+            dump.contains("subcommand build:\n  release: short=none long=--release action=SetTrue"),
+            "{}",
+            dump
+        );
+    }
+
+    #[test]
+    fn test_group_by_kind_lists_options_before_positionals() {
+        let json = |group_by_kind: bool| {
+            format!(
+                r#"{{"name":"app","group_by_kind":{},"args":[
+                    {{"name":"input","type":"positional","help":"Input file"}},
+                    {{"name":"output","type":"option","help":"Output file"}},
+                    {{"name":"extra","type":"positional","help":"Extra file"}},
+                    {{"name":"verbose","type":"flag","help":"Be loud"}}
+                ]}}"#,
+                group_by_kind
+            )
+        };
+        let position = |help: &str, needle: &str| help.find(needle).unwrap();
+
+        // Default: clap lists positionals first
+        let help = generate_help(&Config::from_json(&json(false)).unwrap(), "app");
+        assert!(position(&help, "Input file") < position(&help, "Output file"));
+
+        let help = generate_help(&Config::from_json(&json(true)).unwrap(), "app");
+        let order: Vec<usize> = ["Output file", "Be loud", "Input file", "Extra file"]
+            .iter()
+            .map(|needle| position(&help, needle))
+            .collect();
+        assert!(order.windows(2).all(|w| w[0] < w[1]), "{}", help);
+        assert!(
+            position(&help, "Options:") < position(&help, "Arguments:"),
+            "{}",
+            help
+        );
+    }
+
+    #[test]
+    fn test_next_line_help_config_wide() {
+        let config = Config::from_json(
+            r#"{"name":"app","next_line_help":true,"args":[
+                {"name":"level","type":"option","help":"How loud"}
+            ]}"#,
+        )
+        .unwrap();
+        let help = generate_help(&config, "app");
+        let lines: Vec<&str> = help.lines().collect();
+
+        let level = lines.iter().position(|l| l.contains("--level")).unwrap();
+        assert!(!lines[level].contains("How loud"), "{}", help);
+        assert_eq!(lines[level + 1].trim(), "How loud", "{}", help);
+    }
+
+    #[test]
+    fn test_config_diff_identical() {
+        let json = r#"{"name":"app","args":[{"name":"verbose","type":"flag"}]}"#;
+        let old = Config::from_json(json).unwrap();
+        let new = Config::from_json(json).unwrap();
+        assert_eq!(generate_config_diff(&old, &new), "");
+    }
+
+    #[test]
+    fn test_config_diff_added_removed_changed_args() {
+        let old = Config::from_json(
+            r#"{"schema_version":2,"name":"app","args":[
+                {"name":"verbose","type":"flag"},
+                {"name":"output","type":"option"},
+                {"name":"level","type":"option","choices":["low","high"]}
+            ]}"#,
+        )
+        .unwrap();
+        let new = Config::from_json(
+            r#"{"schema_version":2,"name":"app","args":[
+                {"name":"output","type":"option","required":true},
+                {"name":"level","type":"positional","choices":["low","med","high"]},
+                {"name":"quiet","type":"flag"}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            generate_config_diff(&old, &new),
+            "~ arg output: required no -> yes\n\
+             ~ arg level: type option -> positional\n\
+             ~ arg level: choices [low, high] -> [low, med, high]\n\
+             + arg quiet (flag)\n\
+             - arg verbose (flag)\n"
+        );
+    }
+
+    #[test]
+    fn test_config_diff_subcommands() {
+        let old = Config::from_json(
+            r#"{"schema_version":2,"name":"app","subcommands":[
+                {"name":"run","args":[{"name":"fast","type":"flag"}]},
+                {"name":"legacy","args":[]}
+            ]}"#,
+        )
+        .unwrap();
+        let new = Config::from_json(
+            r#"{"schema_version":2,"name":"app","subcommands":[
+                {"name":"run","args":[{"name":"fast","type":"flag"},{"name":"jobs","type":"option"}]},
+                {"name":"init","args":[]}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            generate_config_diff(&old, &new),
+            "+ run: arg jobs (option)\n\
+             + subcommand init\n\
+             - subcommand legacy\n"
+        );
+    }
 }
@@ -0,0 +1,475 @@
+//! Import of clap YAML app definitions (the `load_yaml!` format) as shclap configs.
+//!
+//! The conversion is best-effort: everything with a shclap equivalent is
+//! mapped, and anything else is dropped with a warning.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+use serde_yaml::Value as Yaml;
+
+/// A shclap config converted from clap YAML, with warnings for what was dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClapImport {
+    /// The shclap config, ready to serialize as JSON
+    pub config: Value,
+    /// One message per clap feature that has no shclap equivalent
+    pub warnings: Vec<String>,
+}
+
+/// Convert a clap YAML app definition to a shclap config.
+///
+/// Args with `index` become positionals (in index order), args that take a
+/// value become options, and the rest become flags. The config uses schema
+/// version 2 only when a mapped feature needs it; args then get `env: false`,
+/// since clap only reads the environment when asked to.
+pub fn import_clap_yaml(yaml: &str) -> Result<ClapImport> {
+    let app: Yaml = serde_yaml::from_str(yaml).context("invalid YAML")?;
+    let Yaml::Mapping(app) = app else {
+        bail!("expected a YAML mapping describing the app");
+    };
+
+    let mut warnings = Vec::new();
+    let mut config = Map::new();
+    let mut args = Vec::new();
+    let mut subcommands = Vec::new();
+
+    for (key, value) in &app {
+        let key = yaml_key(key);
+        match key.as_str() {
+            "name" | "version" | "author" => {
+                config.insert(key, Value::String(yaml_string(value)));
+            }
+            "about" => {
+                config.insert("description".to_string(), Value::String(yaml_string(value)));
+            }
+            "args" => args = import_args(value, "", &mut warnings),
+            "subcommands" => {
+                for (name, props) in named_entries(value) {
+                    subcommands.push(import_subcommand(&name, props, &mut warnings));
+                }
+            }
+            other => warnings.push(format!("unsupported app key '{}' was ignored", other)),
+        }
+    }
+
+    let needs_v2 = !subcommands.is_empty()
+        || args.iter().any(uses_v2)
+        || subcommands.iter().any(|sub| {
+            sub["args"]
+                .as_array()
+                .is_some_and(|a| a.iter().any(uses_v2))
+        });
+    if needs_v2 {
+        config.insert("schema_version".to_string(), json!(2));
+        for arg in args.iter_mut().chain(
+            subcommands
+                .iter_mut()
+                .filter_map(|sub| sub["args"].as_array_mut())
+                .flatten(),
+        ) {
+            arg.as_object_mut()
+                .unwrap()
+                .entry("env")
+                .or_insert(Value::Bool(false));
+        }
+    }
+    config.insert("args".to_string(), Value::Array(args));
+    if !subcommands.is_empty() {
+        config.insert("subcommands".to_string(), Value::Array(subcommands));
+    }
+
+    Ok(ClapImport {
+        config: Value::Object(config),
+        warnings,
+    })
+}
+
+/// Convert one entry of `subcommands`.
+fn import_subcommand(name: &str, props: &Yaml, warnings: &mut Vec<String>) -> Value {
+    let mut sub = Map::new();
+    sub.insert("name".to_string(), Value::String(name.to_string()));
+    let mut args = Vec::new();
+
+    if let Yaml::Mapping(props) = props {
+        for (key, value) in props {
+            let key = yaml_key(key);
+            match key.as_str() {
+                "about" => {
+                    sub.insert("help".to_string(), Value::String(yaml_string(value)));
+                }
+                "args" => args = import_args(value, &format!("{} ", name), warnings),
+                other => warnings.push(format!(
+                    "unsupported key '{}' on subcommand '{}' was ignored",
+                    other, name
+                )),
+            }
+        }
+    }
+
+    sub.insert("args".to_string(), Value::Array(args));
+    Value::Object(sub)
+}
+
+/// Convert an `args` list, moving positionals after the other args in index order.
+/// `scope` prefixes the arg name in warnings (e.g. `"deploy "`).
+fn import_args(value: &Yaml, scope: &str, warnings: &mut Vec<String>) -> Vec<Value> {
+    let mut named = Vec::new();
+    let mut positionals = Vec::new();
+
+    for (name, props) in named_entries(value) {
+        let (arg, index) = import_arg(&name, props, scope, warnings);
+        match index {
+            Some(index) => positionals.push((index, arg)),
+            None => named.push(arg),
+        }
+    }
+
+    positionals.sort_by_key(|(index, _)| *index);
+    named.extend(positionals.into_iter().map(|(_, arg)| arg));
+    named
+}
+
+/// Convert one arg, returning it with its positional `index`, if any.
+fn import_arg(
+    name: &str,
+    props: &Yaml,
+    scope: &str,
+    warnings: &mut Vec<String>,
+) -> (Value, Option<u64>) {
+    let mut arg = Map::new();
+    arg.insert("name".to_string(), Value::String(name.to_string()));
+    let mut index = None;
+    let mut takes_value = false;
+    let (mut min_values, mut max_values) = (None, None);
+    let mut aliases = Vec::new();
+
+    let Yaml::Mapping(props) = props else {
+        arg.insert("type".to_string(), json!("flag"));
+        return (Value::Object(arg), None);
+    };
+
+    for (key, value) in props {
+        let key = yaml_key(key);
+        match key.as_str() {
+            "short" => {
+                if let Some(c) = yaml_string(value).trim_start_matches('-').chars().next() {
+                    arg.insert("short".to_string(), Value::String(c.to_string()));
+                }
+            }
+            "long" => {
+                let long = yaml_string(value);
+                arg.insert(
+                    "long".to_string(),
+                    Value::String(long.trim_start_matches('-').to_string()),
+                );
+            }
+            "help" => {
+                arg.insert("help".to_string(), Value::String(yaml_string(value)));
+            }
+            "long_help" => {
+                arg.entry("help")
+                    .or_insert_with(|| Value::String(yaml_string(value)));
+            }
+            "required" => {
+                arg.insert("required".to_string(), Value::Bool(yaml_bool(value)));
+            }
+            "takes_value" => takes_value |= yaml_bool(value),
+            "multiple" | "multiple_occurrences" | "multiple_values" => {
+                if yaml_bool(value) {
+                    arg.insert("multiple".to_string(), Value::Bool(true));
+                }
+            }
+            "index" => index = value.as_u64(),
+            "default_value" => {
+                takes_value = true;
+                arg.insert("default".to_string(), Value::String(yaml_string(value)));
+            }
+            "possible_values" => {
+                takes_value = true;
+                arg.insert("choices".to_string(), json!(yaml_strings(value)));
+            }
+            "env" => {
+                takes_value = true;
+                arg.insert("env".to_string(), Value::String(yaml_string(value)));
+            }
+            "conflicts_with" | "conflicts_with_all" => {
+                extend_array(&mut arg, "conflicts_with", yaml_strings(value));
+            }
+            "requires" | "requires_all" => {
+                extend_array(&mut arg, "requires", yaml_strings(value));
+            }
+            "alias" | "aliases" | "visible_alias" | "visible_aliases" => {
+                aliases.extend(yaml_strings(value));
+            }
+            "value_delimiter" => {
+                takes_value = true;
+                if let Some(c) = yaml_string(value).chars().next() {
+                    arg.insert("delimiter".to_string(), Value::String(c.to_string()));
+                }
+            }
+            "use_delimiter" | "use_value_delimiter" => {
+                if yaml_bool(value) {
+                    takes_value = true;
+                    arg.entry("delimiter").or_insert(json!(","));
+                }
+            }
+            "number_of_values" => {
+                takes_value = true;
+                arg.insert("num_args".to_string(), Value::String(yaml_string(value)));
+            }
+            "min_values" => {
+                takes_value = true;
+                min_values = value.as_u64();
+            }
+            "max_values" => {
+                takes_value = true;
+                max_values = value.as_u64();
+            }
+            "value_name" | "value_names" => takes_value = true,
+            "require_equals" => {
+                if yaml_bool(value) {
+                    arg.insert("require_equals".to_string(), Value::Bool(true));
+                }
+            }
+            other => warnings.push(format!(
+                "unsupported key '{}' on argument '{}{}' was ignored",
+                other, scope, name
+            )),
+        }
+    }
+
+    let arg_type = if index.is_some() {
+        "positional"
+    } else if takes_value {
+        "option"
+    } else {
+        "flag"
+    };
+    arg.insert("type".to_string(), json!(arg_type));
+
+    if min_values.is_some() || max_values.is_some() {
+        let range = match max_values {
+            Some(max) => format!("{}..={}", min_values.unwrap_or(0), max),
+            None => format!("{}..", min_values.unwrap_or(0)),
+        };
+        arg.entry("num_args").or_insert(Value::String(range));
+    }
+
+    if !aliases.is_empty() {
+        if arg_type == "positional" {
+            warnings.push(format!(
+                "aliases on positional argument '{}{}' were ignored",
+                scope, name
+            ));
+        } else {
+            arg.insert("aliases".to_string(), json!(aliases));
+        }
+    }
+
+    (Value::Object(arg), index)
+}
+
+/// Whether an imported arg needs schema version 2.
+fn uses_v2(arg: &Value) -> bool {
+    const V2_KEYS: &[&str] = &[
+        "multiple",
+        "choices",
+        "env",
+        "delimiter",
+        "num_args",
+        "conflicts_with",
+        "requires",
+        "aliases",
+        "require_equals",
+    ];
+    V2_KEYS.iter().any(|key| arg.get(key).is_some())
+}
+
+/// The `- name: {props}` entries of a clap YAML list.
+fn named_entries(value: &Yaml) -> Vec<(String, &Yaml)> {
+    let Yaml::Sequence(items) = value else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Yaml::Mapping(map) => map.iter().next().map(|(k, v)| (yaml_key(k), v)),
+            Yaml::String(name) => Some((name.clone(), &Yaml::Null)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn extend_array(arg: &mut Map<String, Value>, key: &str, values: Vec<String>) {
+    if let Value::Array(existing) = arg.entry(key).or_insert_with(|| json!([])) {
+        existing.extend(values.into_iter().map(Value::String));
+    }
+}
+
+fn yaml_key(key: &Yaml) -> String {
+    yaml_string(key)
+}
+
+fn yaml_string(value: &Yaml) -> String {
+    match value {
+        Yaml::String(s) => s.clone(),
+        Yaml::Bool(b) => b.to_string(),
+        Yaml::Number(n) => n.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn yaml_bool(value: &Yaml) -> bool {
+    value.as_bool().unwrap_or(false)
+}
+
+/// A string, or a list of strings, as a list.
+fn yaml_strings(value: &Yaml) -> Vec<String> {
+    match value {
+        Yaml::Sequence(items) => items.iter().map(yaml_string).collect(),
+        other => vec![yaml_string(other)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const CLAP_YAML: &str = r#"
+name: myapp
+version: "1.0"
+author: Jane Doe <jane@example.com>
+about: Does awesome things
+settings:
+    - ArgRequiredElseHelp
+args:
+    - INPUT:
+        help: Sets the input file to use
+        required: true
+        index: 1
+    - config:
+        short: c
+        long: config
+        value_name: FILE
+        help: Sets a custom config file
+        takes_value: true
+    - verbose:
+        short: v
+        multiple: true
+        help: Sets the level of verbosity
+subcommands:
+    - test:
+        about: controls testing features
+        args:
+            - debug:
+                short: d
+                help: print debug information
+                hidden: true
+"#;
+
+    #[test]
+    fn test_import_clap_yaml_validates() {
+        let import = import_clap_yaml(CLAP_YAML).unwrap();
+        let json = serde_json::to_string(&import.config).unwrap();
+        let config = Config::from_json(&json).unwrap();
+        config.validate().unwrap();
+
+        assert_eq!(config.schema_version, 2);
+        assert_eq!(config.name.as_deref(), Some("myapp"));
+        assert_eq!(config.description.as_deref(), Some("Does awesome things"));
+
+        let names: Vec<_> = config.args.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["config", "verbose", "INPUT"]);
+        assert_eq!(config.args[0].arg_type, crate::config::ArgType::Option);
+        assert_eq!(config.args[1].arg_type, crate::config::ArgType::Flag);
+        assert!(config.args[1].multiple);
+        assert_eq!(config.args[2].arg_type, crate::config::ArgType::Positional);
+        assert!(config.args[2].required);
+
+        assert_eq!(config.subcommands.len(), 1);
+        assert_eq!(config.subcommands[0].name, "test");
+        assert_eq!(config.subcommands[0].args[0].short, Some('d'));
+    }
+
+    #[test]
+    fn test_import_clap_yaml_warns_on_unmapped_keys() {
+        let import = import_clap_yaml(CLAP_YAML).unwrap();
+        assert_eq!(
+            import.warnings,
+            [
+                "unsupported app key 'settings' was ignored",
+                "unsupported key 'hidden' on argument 'test debug' was ignored",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_clap_yaml_stays_v1_when_possible() {
+        let yaml = r#"
+name: tiny
+args:
+    - output:
+        short: o
+        takes_value: true
+        default_value: out.txt
+    - INPUT:
+        index: 1
+"#;
+        let import = import_clap_yaml(yaml).unwrap();
+        assert!(import.warnings.is_empty());
+        assert_eq!(
+            import.config,
+            json!({
+                "name": "tiny",
+                "args": [
+                    {"name": "output", "short": "o", "default": "out.txt", "type": "option"},
+                    {"name": "INPUT", "type": "positional"}
+                ]
+            })
+        );
+        let config = Config::from_json(&import.config.to_string()).unwrap();
+        assert_eq!(config.schema_version, 1);
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_import_clap_yaml_maps_values_and_relations() {
+        let yaml = r#"
+name: fmt
+args:
+    - format:
+        long: format
+        possible_values: [json, yaml]
+        conflicts_with: raw
+        visible_aliases: [fmt]
+    - raw:
+        long: raw
+    - tags:
+        long: tags
+        use_delimiter: true
+        min_values: 1
+"#;
+        let import = import_clap_yaml(yaml).unwrap();
+        let config = Config::from_json(&import.config.to_string()).unwrap();
+        config.validate().unwrap();
+
+        let format = &config.args[0];
+        assert_eq!(format.choices, Some(vec!["json".into(), "yaml".into()]));
+        assert_eq!(format.conflicts_with, ["raw"]);
+        assert_eq!(format.aliases, ["fmt"]);
+        assert_eq!(format.env, Some(crate::config::EnvSetting::Disabled));
+        assert_eq!(config.args[2].delimiter, Some(','));
+        assert_eq!(config.args[2].num_args.as_deref(), Some("1.."));
+    }
+
+    #[test]
+    fn test_import_clap_yaml_rejects_non_mapping() {
+        let err = import_clap_yaml("- just\n- a list\n").unwrap_err();
+        assert!(
+            err.to_string().contains("expected a YAML mapping"),
+            "{}",
+            err
+        );
+    }
+}
@@ -4,20 +4,38 @@
 //! arguments according to a JSON configuration, generating help text,
 //! and outputting parsed values as shell export statements.
 
+mod command;
 pub mod config;
 pub mod help;
+pub mod import;
 pub mod output;
 pub mod parser;
 
 pub use config::{
-    ArgConfig, ArgType, Config, ConfigError, SubcommandConfig, MAX_SCHEMA_VERSION,
-    MIN_SCHEMA_VERSION,
+    ArgConfig, ArgType, Config, ConfigError, DefaultValue, SubcommandConfig, ValueType,
+    MAX_SCHEMA_VERSION, MIN_SCHEMA_VERSION,
 };
-pub use help::{generate_help, generate_version};
+pub use help::{
+    generate_command_dump, generate_config_diff, generate_config_explanation, generate_help,
+    generate_subcommand_list, generate_version,
+};
+pub use import::{import_clap_yaml, ClapImport};
 pub use output::{
-    generate_error_output, generate_error_string, generate_help_output,
-    generate_help_output_string, generate_output, generate_output_string,
-    generate_output_string_legacy, generate_print, generate_version_output,
-    generate_version_output_string,
+    generate_dispatch, generate_error_output, generate_error_output_in, generate_error_string,
+    generate_error_string_for, generate_eval_string, generate_help_output, generate_help_output_in,
+    generate_help_output_string, generate_help_output_string_for, generate_json_message,
+    generate_json_output_string, generate_output, generate_output_string,
+    generate_output_string_legacy, generate_output_string_with_options,
+    generate_output_with_options, generate_print, generate_print_from_values,
+    generate_toml_message, generate_toml_string, generate_version_output,
+    generate_version_output_in, generate_version_output_string, generate_version_output_string_for,
+    validate_output_dir, write_output_file, write_temp_file, LineEnding, OutputOptions,
+    ShellDialect,
+};
+pub use parser::{
+    missing_required_args, parse_args, parse_args_validating, ParseError, ParseOutcome,
+    ParseResult, ParseSuccess, ParsedValue,
 };
-pub use parser::{parse_args, ParseError, ParseOutcome, ParseResult, ParseSuccess, ParsedValue};
+
+#[cfg(feature = "timeout")]
+pub use parser::parse_args_with_timeout;
@@ -1,11 +1,21 @@
 //! shclap - Clap-style argument parsing for shell scripts.
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use shclap::{
-    generate_error_output, generate_help, generate_help_output, generate_output, generate_print,
-    generate_version, generate_version_output, parse_args, Config, ParseOutcome,
+    generate_command_dump, generate_config_diff, generate_config_explanation, generate_dispatch,
+    generate_error_string_for, generate_eval_string, generate_help,
+    generate_help_output_string_for, generate_json_message, generate_json_output_string,
+    generate_output_string_with_options, generate_output_with_options, generate_print,
+    generate_print_from_values, generate_subcommand_list, generate_toml_message,
+    generate_toml_string, generate_version, generate_version_output_string_for, import_clap_yaml,
+    missing_required_args, parse_args, parse_args_validating, validate_output_dir,
+    write_output_file, write_temp_file, ArgConfig, ArgType, Config, OutputOptions, ParseOutcome,
+    ParsedValue, ShellDialect, ValueType, MAX_SCHEMA_VERSION, MIN_SCHEMA_VERSION,
 };
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
 /// Clap-style argument parsing for shell scripts.
 #[derive(Parser, Debug)]
@@ -27,10 +37,113 @@ enum Commands {
         #[arg(long)]
         name: Option<String>,
 
+        /// Environment variable holding the application name, used when --name is absent
+        #[arg(long, value_name = "VAR")]
+        name_env: Option<String>,
+
         /// Environment variable prefix (overrides config)
         #[arg(long)]
         prefix: Option<String>,
 
+        /// Common prefix to strip from arg names in variable names (overrides config)
+        #[arg(long)]
+        prefix_strip: Option<String>,
+
+        /// Prompt on the terminal for missing required values
+        #[arg(long)]
+        interactive: bool,
+
+        /// Print eval-safe statements to stdout instead of a file path
+        #[arg(long)]
+        eval: bool,
+
+        /// Print the output file's contents to stdout instead of writing a temp file
+        #[arg(long, conflicts_with_all = ["eval", "output_dir", "output_file"])]
+        stdout: bool,
+
+        /// End the output file with a line that deletes it, so sourcing cleans up
+        #[arg(long, conflicts_with_all = ["eval", "stdout", "output_file"])]
+        self_cleanup: bool,
+
+        /// Report every invalid value at once instead of stopping at the first
+        #[arg(long)]
+        validate_values: bool,
+
+        /// Enable every strict check, as if the config set "strict": true
+        #[arg(long)]
+        strict: bool,
+
+        /// Wrap the output in `set -x` / `set +x` to trace each assignment
+        #[arg(long, conflicts_with = "eval")]
+        xtrace: bool,
+
+        /// Also emit <ARG>_OCCURRENCES for each multiple option
+        #[arg(long, conflicts_with = "eval")]
+        count_occurrences: bool,
+
+        /// Unset every declared variable before the exports, for a clean slate when re-sourcing
+        #[arg(long)]
+        emit_unset_prefix: bool,
+
+        /// Shell syntax for the output file; `posix` avoids bash arrays, `zsh` declares arrays
+        /// with `typeset`, `fish` uses `set -gx`, `powershell` assigns `$env:` variables
+        #[arg(long, value_enum, default_value_t = ShellDialect::Bash, conflicts_with = "eval")]
+        shell: ShellDialect,
+
+        /// Emit int-typed values with `declare -gix` so bash enforces integer arithmetic
+        #[arg(long, conflicts_with = "eval")]
+        declare_int: bool,
+
+        /// Only emit the variable for this arg (repeatable)
+        #[arg(long, value_name = "NAME", conflicts_with = "exclude")]
+        only: Vec<String>,
+
+        /// Parse and validate this arg but leave it out of the output (repeatable)
+        #[arg(long, value_name = "NAME")]
+        exclude: Vec<String>,
+
+        /// Print a readable summary of the parsed values to stderr
+        #[arg(long)]
+        pretty: bool,
+
+        /// Start the output with a `# invoked as: ...` comment reconstructing the
+        /// parsed command line, for traceability
+        #[arg(long, conflicts_with = "eval")]
+        parse_and_print: bool,
+
+        /// Append a `case` on the subcommand variable that calls `cmd_<name>` per subcommand
+        #[arg(long)]
+        emit_dispatch: bool,
+
+        /// Output format for parsed values; `toml` and `json` print to stdout
+        #[arg(long, value_enum, default_value_t = OutputFormat::Shell, conflicts_with = "eval")]
+        format: OutputFormat,
+
+        /// Directory to create the output file in (default: $TMPDIR)
+        #[arg(long, value_name = "PATH")]
+        output_dir: Option<PathBuf>,
+
+        /// Write the output to this file or FIFO instead of a new temp file
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["output_dir", "eval"])]
+        output_file: Option<PathBuf>,
+
+        /// Reject more than this many script arguments before parsing (default: unlimited)
+        #[arg(long)]
+        max_args: Option<usize>,
+
+        /// Read script arguments from this environment variable (shell-word split),
+        /// placed before any arguments after `--`
+        #[arg(long, value_name = "VAR")]
+        args_from_env: Option<String>,
+
+        /// Interpret the config as this schema version (overrides config)
+        #[arg(
+            long,
+            value_parser = clap::value_parser!(u32)
+                .range(MIN_SCHEMA_VERSION as i64..=MAX_SCHEMA_VERSION as i64)
+        )]
+        schema_version: Option<u32>,
+
         /// Arguments to parse for the target script
         #[arg(last = true)]
         args: Vec<String>,
@@ -45,6 +158,10 @@ enum Commands {
         /// Application name (overrides config 'name' field)
         #[arg(long)]
         name: Option<String>,
+
+        /// Environment variable holding the application name, used when --name is absent
+        #[arg(long, value_name = "VAR")]
+        name_env: Option<String>,
     },
 
     /// Print version of the target script
@@ -56,6 +173,10 @@ enum Commands {
         /// Application name (overrides config 'name' field)
         #[arg(long)]
         name: Option<String>,
+
+        /// Environment variable holding the application name, used when --name is absent
+        #[arg(long, value_name = "VAR")]
+        name_env: Option<String>,
     },
 
     /// Print how the script was called (reconstructs command line from env vars)
@@ -68,10 +189,120 @@ enum Commands {
         #[arg(long)]
         name: Option<String>,
 
+        /// Environment variable holding the application name, used when --name is absent
+        #[arg(long, value_name = "VAR")]
+        name_env: Option<String>,
+
+        /// Environment variable prefix (overrides config)
+        #[arg(long)]
+        prefix: Option<String>,
+
+        /// Common prefix to strip from arg names in variable names (overrides config)
+        #[arg(long)]
+        prefix_strip: Option<String>,
+    },
+
+    /// Print the variable names a successful parse could set, one per line
+    ListVars {
+        /// JSON configuration for the target script
+        #[arg(long)]
+        config: String,
+
+        /// Environment variable prefix (overrides config)
+        #[arg(long)]
+        prefix: Option<String>,
+    },
+
+    /// Print the target script's subcommands as JSON
+    ListSubcommands {
+        /// JSON configuration for the target script
+        #[arg(long)]
+        config: String,
+    },
+
+    /// Compare two configs' interfaces; exits 1 if they differ
+    Diff {
+        /// JSON configuration before the change
+        #[arg(long)]
+        old: String,
+
+        /// JSON configuration after the change
+        #[arg(long)]
+        new: String,
+    },
+
+    /// Print how shclap interprets the config: effective options, variables, and env fallbacks
+    ExplainConfig {
+        /// JSON configuration for the target script
+        #[arg(long)]
+        config: String,
+
+        /// Application name (overrides config 'name' field)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Environment variable holding the application name, used when --name is absent
+        #[arg(long, value_name = "VAR")]
+        name_env: Option<String>,
+
+        /// Environment variable prefix (overrides config)
+        #[arg(long)]
+        prefix: Option<String>,
+    },
+
+    /// Print the Clap command built from the config (usage and each arg's id,
+    /// short, long, action and num_args) to stderr, for troubleshooting
+    DumpCommand {
+        /// JSON configuration for the target script
+        #[arg(long)]
+        config: String,
+
+        /// Application name (overrides config 'name' field)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Environment variable holding the application name, used when --name is absent
+        #[arg(long, value_name = "VAR")]
+        name_env: Option<String>,
+    },
+
+    /// Check that every required arg can be filled from the current environment;
+    /// exits 1 and lists the unsatisfied args if not
+    ValidateEnv {
+        /// JSON configuration for the target script
+        #[arg(long)]
+        config: String,
+
+        /// Application name (overrides config 'name' field)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Environment variable holding the application name, used when --name is absent
+        #[arg(long, value_name = "VAR")]
+        name_env: Option<String>,
+
         /// Environment variable prefix (overrides config)
         #[arg(long)]
         prefix: Option<String>,
     },
+
+    /// Convert a clap YAML app definition to a shclap JSON config (best-effort)
+    Import {
+        /// Path to the clap YAML file
+        #[arg(long, value_name = "PATH")]
+        clap_yaml: PathBuf,
+    },
+}
+
+/// Output format for successfully parsed values.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Sourceable shell export statements
+    Shell,
+    /// A TOML table keyed by arg name
+    Toml,
+    /// A JSON object keyed by arg name, with typed values
+    Json,
 }
 
 fn main() -> Result<()> {
@@ -81,438 +312,2000 @@ fn main() -> Result<()> {
         Commands::Parse {
             config,
             name,
+            name_env,
             prefix,
+            prefix_strip,
+            interactive,
+            eval,
+            stdout,
+            self_cleanup,
+            validate_values,
+            strict,
+            xtrace,
+            count_occurrences,
+            emit_unset_prefix,
+            shell,
+            declare_int,
+            only,
+            exclude,
+            pretty,
+            parse_and_print,
+            emit_dispatch,
+            format,
+            output_dir,
+            output_file,
+            max_args,
+            args_from_env,
+            schema_version,
             args,
         } => {
+            // clap can't make a conflict depend on a value, so reject flags
+            // that only shape shell output here
+            if let Some(message) = format_conflict(
+                format,
+                &[
+                    ("--shell", shell != ShellDialect::Bash),
+                    ("--output-dir", output_dir.is_some()),
+                    ("--output-file", output_file.is_some()),
+                    ("--self-cleanup", self_cleanup),
+                    ("--declare-int", declare_int),
+                    ("--xtrace", xtrace),
+                    ("--count-occurrences", count_occurrences),
+                    ("--emit-unset-prefix", emit_unset_prefix),
+                    ("--emit-dispatch", emit_dispatch),
+                    ("--parse-and-print", parse_and_print),
+                ],
+            ) {
+                Cli::command()
+                    .error(clap::error::ErrorKind::ArgumentConflict, message)
+                    .exit();
+            }
+
+            // Errors, help and version print the same content either way
+            let mut report = Report {
+                print_output: eval || stdout,
+                output_dir: None,
+                output_file: None,
+                dialect: shell,
+                format,
+            };
+
+            // A bad output dir can't hold the error file either, so report it
+            // from the default location
+            if let Some(ref dir) = output_dir {
+                if let Err(e) = validate_output_dir(dir) {
+                    return output_error(&e.to_string(), &report);
+                }
+            }
+            let output_dir = output_dir.as_deref();
+            let output_file = output_file.as_deref();
+            report.output_dir = output_dir;
+            report.output_file = output_file;
+
+            let args = match args_from_env {
+                Some(ref var) => match env_args(var, args) {
+                    Ok(args) => args,
+                    Err(message) => return output_error(&message, &report),
+                },
+                None => args,
+            };
+
+            // Guard against argument bombs before doing any work
+            if let Err(message) = check_max_args(&args, max_args) {
+                return output_error(&message, &report);
+            }
+
             // Handle config parsing errors
-            let cfg = match Config::from_json(&config) {
+            let mut cfg = match Config::from_json(&config) {
                 Ok(c) => c,
                 Err(e) => {
-                    return output_error(&format!("failed to parse JSON config: {}", e));
+                    return output_error(&format!("failed to parse JSON config: {}", e), &report);
                 }
             };
 
+            // CLI --prefix-strip takes priority over config strip_name_prefix
+            if prefix_strip.is_some() {
+                cfg.strip_name_prefix = prefix_strip;
+            }
+
+            // CLI --prefix takes priority over config prefix, for env fallback too
+            if prefix.is_some() {
+                cfg.prefix = prefix;
+            }
+
+            // CLI --strict turns on strict mode for a lenient config
+            if strict && !cfg.strict {
+                cfg.strict = true;
+                if let Err(e) = cfg.check_unknown_fields(&config) {
+                    return output_error(&e.to_string(), &report);
+                }
+            }
+
+            // CLI --schema-version replaces the declared version before validation
+            if let Some(version) = schema_version {
+                cfg.schema_version = version;
+            }
+
             // Handle validation errors
             if let Err(e) = cfg.validate() {
-                return output_error(&e.to_string());
+                return output_error(&e.to_string(), &report);
+            }
+
+            if format != OutputFormat::Shell && subcommand_key_clash(&cfg) {
+                let message = "an arg named 'subcommand' would overwrite the matched \
+                               subcommand in --format toml or json output";
+                return output_error(message, &report);
+            }
+
+            // _GROUPS, _COUNT and the like must not overwrite another arg's variable
+            if format == OutputFormat::Shell {
+                let posix = shell == ShellDialect::Posix;
+                if let Err(e) = cfg.validate_derived_var_names(posix, count_occurrences) {
+                    return output_error(&e.to_string(), &report);
+                }
             }
 
-            // Determine effective name: CLI --name takes priority over config name
-            let effective_name = match (name.as_deref(), cfg.name.as_deref()) {
-                (Some(cli_name), _) => cli_name,
-                (None, Some(config_name)) => config_name,
-                (None, None) => {
-                    return output_error(
-                        "no application name provided: use --name or set 'name' in config",
+            if let Err(message) = check_filter_names(&cfg, "--only", &only)
+                .and_then(|()| check_filter_names(&cfg, "--exclude", &exclude))
+            {
+                return output_error(&message, &report);
+            }
+
+            // Determine effective name: CLI --name, then --name-env, then config name
+            let Some(effective_name) =
+                resolve_name(name.as_deref(), name_env.as_deref(), cfg.name.as_deref())
+            else {
+                return output_error(NO_NAME_ERROR, &report);
+            };
+            let effective_name = effective_name.as_str();
+
+            let effective_prefix = &cfg.resolve_prefix(None);
+
+            // Prompt for missing required values when running interactively
+            let args = if interactive {
+                let is_tty = io::stdin().is_terminal();
+                prompt_missing_values(
+                    &cfg,
+                    args,
+                    effective_name,
+                    is_tty,
+                    &mut io::stdin().lock(),
+                    &mut io::stderr(),
+                )
+                .context("failed to read interactive input")?
+            } else {
+                args
+            };
+
+            let outcome = if validate_values {
+                parse_args_validating(&cfg, &args, effective_name)
+            } else {
+                parse_args(&cfg, &args, effective_name)
+            };
+
+            if let ParseOutcome::Success(ref result) = outcome {
+                for warning in &result.warnings {
+                    eprintln!("shclap: warning: {}", warning);
+                }
+                if pretty {
+                    eprintln!(
+                        "shclap: {}",
+                        format_summary(&result.values, result.subcommand.as_deref())
                     );
                 }
+            }
+
+            let unset_vars = if emit_unset_prefix {
+                cfg.expected_env_vars_with(
+                    effective_prefix,
+                    shell == ShellDialect::Posix,
+                    count_occurrences,
+                )
+            } else {
+                Vec::new()
             };
 
-            let effective_prefix = prefix.as_deref().unwrap_or_else(|| cfg.effective_prefix());
+            let dispatch_subcommands: Vec<String> = if emit_dispatch {
+                // Only leaves can be matched: a subcommand with children requires one
+                cfg.all_subcommands()
+                    .into_iter()
+                    .filter(|(_, subcmd)| subcmd.subcommands.is_empty())
+                    .map(|(path, _)| path)
+                    .collect()
+            } else {
+                Vec::new()
+            };
 
             // Handle parse result
-            match parse_args(&cfg, &args, effective_name) {
+            match outcome {
+                ParseOutcome::Success(result) if format == OutputFormat::Toml => {
+                    let values = drop_excluded(retain_only(result.values, &only), &exclude);
+                    let toml = generate_toml_string(&values, result.subcommand.as_deref())
+                        .context("failed to generate TOML output")?;
+                    print!("{}", toml);
+                }
+                ParseOutcome::Success(result) if format == OutputFormat::Json => {
+                    let values = drop_excluded(retain_only(result.values, &only), &exclude);
+                    println!(
+                        "{}",
+                        generate_json_output_string(&cfg, &values, result.subcommand.as_deref())
+                    );
+                }
+                ParseOutcome::Success(result) if eval => {
+                    let values = retain_only(result.values, &only);
+                    let values = strip_value_names(&cfg, drop_excluded(values, &exclude));
+                    for var in &unset_vars {
+                        println!("unset {}", var);
+                    }
+                    print!(
+                        "{}",
+                        generate_eval_string(
+                            &values,
+                            effective_prefix,
+                            result.subcommand.as_deref()
+                        )
+                    );
+                    if !dispatch_subcommands.is_empty() {
+                        print!(
+                            "\n{}",
+                            generate_dispatch(&dispatch_subcommands, effective_prefix)
+                        );
+                    }
+                }
                 ParseOutcome::Success(result) => {
-                    let path = generate_output(
-                        &result.values,
+                    // Reconstruct from every parsed value, before --only/--exclude
+                    let invoked_as = parse_and_print.then(|| {
+                        generate_print_from_values(
+                            &cfg,
+                            effective_name,
+                            &result.values,
+                            result.subcommand.as_deref(),
+                        )
+                    });
+                    let values = retain_only(result.values, &only);
+                    let values = strip_value_names(&cfg, drop_excluded(values, &exclude));
+                    let occurrences = if count_occurrences {
+                        strip_value_names(&cfg, result.occurrences)
+                    } else {
+                        HashMap::new()
+                    };
+                    let int_args = if declare_int {
+                        int_arg_names(&cfg, result.subcommand.as_deref())
+                    } else {
+                        HashSet::new()
+                    };
+                    let options = OutputOptions {
+                        with_xtrace: xtrace,
+                        occurrences,
+                        unset_vars,
+                        int_args,
+                        unique_args: scoped_arg_names(&cfg, result.subcommand.as_deref(), |arg| {
+                            arg.unique
+                        }),
+                        output_dir: output_dir.map(Path::to_path_buf),
+                        output_file: output_file.map(Path::to_path_buf),
+                        self_cleanup,
+                        dispatch_subcommands,
+                        dialect: shell,
+                        invoked_as,
+                        ..Default::default()
+                    };
+                    if stdout {
+                        print!(
+                            "{}",
+                            generate_output_string_with_options(
+                                &values,
+                                effective_prefix,
+                                result.subcommand.as_deref(),
+                                &options,
+                            )
+                        );
+                        return Ok(());
+                    }
+                    let path = generate_output_with_options(
+                        &values,
                         effective_prefix,
                         result.subcommand.as_deref(),
+                        &options,
                     )
                     .context("failed to generate output file")?;
                     println!("{}", path.display());
                 }
+                ParseOutcome::Help(help_text) if format == OutputFormat::Toml => {
+                    print!("{}", generate_toml_message("help", &help_text));
+                }
+                ParseOutcome::Version(version_text) if format == OutputFormat::Toml => {
+                    print!("{}", generate_toml_message("version", &version_text));
+                }
+                ParseOutcome::Help(help_text) if format == OutputFormat::Json => {
+                    println!("{}", generate_json_message("help", &help_text));
+                }
+                ParseOutcome::Version(version_text) if format == OutputFormat::Json => {
+                    println!("{}", generate_json_message("version", &version_text));
+                }
                 ParseOutcome::Help(help_text) => {
-                    let path = generate_help_output(&help_text)
+                    report
+                        .emit(&generate_help_output_string_for(&help_text, shell))
                         .context("failed to generate help output file")?;
-                    println!("{}", path.display());
                 }
                 ParseOutcome::Version(version_text) => {
-                    let path = generate_version_output(&version_text)
+                    report
+                        .emit(&generate_version_output_string_for(&version_text, shell))
                         .context("failed to generate version output file")?;
-                    println!("{}", path.display());
                 }
                 ParseOutcome::Error(error_msg) => {
-                    return output_error(&error_msg);
+                    return output_error(&error_msg, &report);
                 }
             }
         }
-        Commands::Help { config, name } => {
+        Commands::Help {
+            config,
+            name,
+            name_env,
+        } => {
             let cfg = Config::from_json(&config).context("failed to parse config JSON")?;
 
-            // Determine effective name: CLI --name takes priority over config name
-            let effective_name = match (name.as_deref(), cfg.name.as_deref()) {
-                (Some(cli_name), _) => cli_name.to_string(),
-                (None, Some(config_name)) => config_name.to_string(),
-                (None, None) => {
-                    anyhow::bail!(
-                        "no application name provided: use --name or set 'name' in config"
-                    );
-                }
-            };
+            // Determine effective name: CLI --name, then --name-env, then config name
+            let effective_name =
+                resolve_name(name.as_deref(), name_env.as_deref(), cfg.name.as_deref())
+                    .context(NO_NAME_ERROR)?;
 
             print!("{}", generate_help(&cfg, &effective_name));
         }
-        Commands::Version { config, name } => {
+        Commands::Version {
+            config,
+            name,
+            name_env,
+        } => {
             let cfg = Config::from_json(&config).context("failed to parse config JSON")?;
 
-            // Determine effective name: CLI --name takes priority over config name
-            let effective_name = match (name.as_deref(), cfg.name.as_deref()) {
-                (Some(cli_name), _) => cli_name.to_string(),
-                (None, Some(config_name)) => config_name.to_string(),
-                (None, None) => {
-                    anyhow::bail!(
-                        "no application name provided: use --name or set 'name' in config"
-                    );
-                }
-            };
+            // Determine effective name: CLI --name, then --name-env, then config name
+            let effective_name =
+                resolve_name(name.as_deref(), name_env.as_deref(), cfg.name.as_deref())
+                    .context(NO_NAME_ERROR)?;
 
             print!("{}", generate_version(&cfg, &effective_name));
         }
         Commands::Print {
             config,
             name,
+            name_env,
             prefix,
+            prefix_strip,
         } => {
+            let mut cfg = Config::from_json(&config).context("failed to parse config JSON")?;
+
+            // CLI --prefix-strip takes priority over config strip_name_prefix
+            if prefix_strip.is_some() {
+                cfg.strip_name_prefix = prefix_strip;
+            }
+
+            // Determine effective name: CLI --name, then --name-env, then config name
+            let effective_name =
+                resolve_name(name.as_deref(), name_env.as_deref(), cfg.name.as_deref())
+                    .context(NO_NAME_ERROR)?;
+
+            let effective_prefix = &cfg.resolve_prefix(prefix.as_deref());
+
+            println!(
+                "{}",
+                generate_print(&cfg, &effective_name, effective_prefix)
+            );
+        }
+        Commands::ListVars { config, prefix } => {
+            let cfg = Config::from_json(&config).context("failed to parse config JSON")?;
+            let effective_prefix = &cfg.resolve_prefix(prefix.as_deref());
+            for var in cfg.expected_env_vars(effective_prefix) {
+                println!("{}", var);
+            }
+        }
+        Commands::ListSubcommands { config } => {
             let cfg = Config::from_json(&config).context("failed to parse config JSON")?;
+            print!("{}", generate_subcommand_list(&cfg));
+        }
+        Commands::Diff { old, new } => {
+            let old_cfg = Config::from_json(&old).context("failed to parse --old config JSON")?;
+            let new_cfg = Config::from_json(&new).context("failed to parse --new config JSON")?;
 
-            // Determine effective name: CLI --name takes priority over config name
-            let effective_name = match (name.as_deref(), cfg.name.as_deref()) {
-                (Some(cli_name), _) => cli_name.to_string(),
-                (None, Some(config_name)) => config_name.to_string(),
-                (None, None) => {
-                    anyhow::bail!(
-                        "no application name provided: use --name or set 'name' in config"
-                    );
+            let diff = generate_config_diff(&old_cfg, &new_cfg);
+            if !diff.is_empty() {
+                print!("{}", diff);
+                std::process::exit(1);
+            }
+        }
+        Commands::ExplainConfig {
+            config,
+            name,
+            name_env,
+            prefix,
+        } => {
+            let mut cfg = Config::from_json(&config).context("failed to parse config JSON")?;
+
+            // CLI --prefix replaces the config prefix, as it does for parse
+            if prefix.is_some() {
+                cfg.prefix = prefix;
+            }
+
+            // Determine effective name: CLI --name, then --name-env, then config name
+            let effective_name =
+                resolve_name(name.as_deref(), name_env.as_deref(), cfg.name.as_deref())
+                    .context(NO_NAME_ERROR)?;
+
+            let effective_prefix = &cfg.resolve_prefix(None);
+
+            print!(
+                "{}",
+                generate_config_explanation(&cfg, &effective_name, effective_prefix)
+            );
+        }
+        Commands::DumpCommand {
+            config,
+            name,
+            name_env,
+        } => {
+            let cfg = Config::from_json(&config).context("failed to parse config JSON")?;
+
+            // Determine effective name: CLI --name, then --name-env, then config name
+            let effective_name =
+                resolve_name(name.as_deref(), name_env.as_deref(), cfg.name.as_deref())
+                    .context(NO_NAME_ERROR)?;
+
+            eprint!("{}", generate_command_dump(&cfg, &effective_name));
+        }
+        Commands::ValidateEnv {
+            config,
+            name,
+            name_env,
+            prefix,
+        } => {
+            let mut cfg = Config::from_json(&config).context("failed to parse config JSON")?;
+
+            // CLI --prefix replaces the config prefix, as it does for parse
+            if prefix.is_some() {
+                cfg.prefix = prefix;
+            }
+
+            // Determine effective name: CLI --name, then --name-env, then config name
+            let effective_name =
+                resolve_name(name.as_deref(), name_env.as_deref(), cfg.name.as_deref())
+                    .context(NO_NAME_ERROR)?;
+            let effective_prefix = &cfg.resolve_prefix(None);
+
+            let missing = cfg.unsatisfied_env_args(effective_prefix, |var| std::env::var(var).ok());
+            if !missing.is_empty() {
+                for (arg, var) in missing {
+                    match var {
+                        Some(var) => {
+                            eprintln!(
+                                "{}: required arg '{}': ${} is not set",
+                                effective_name, arg.name, var
+                            )
+                        }
+                        None => eprintln!(
+                            "{}: required arg '{}' has no env fallback",
+                            effective_name, arg.name
+                        ),
+                    }
                 }
-            };
+                std::process::exit(1);
+            }
+        }
+        Commands::Import { clap_yaml } => {
+            let yaml = std::fs::read_to_string(&clap_yaml)
+                .with_context(|| format!("failed to read {}", clap_yaml.display()))?;
+            let import = import_clap_yaml(&yaml).context("failed to import clap YAML")?;
+            for warning in &import.warnings {
+                eprintln!("shclap: warning: {}", warning);
+            }
+            println!("{}", serde_json::to_string_pretty(&import.config)?);
+        }
+    }
+
+    Ok(())
+}
+
+const NO_NAME_ERROR: &str =
+    "no application name provided: use --name, --name-env, or set 'name' in config";
+
+/// Resolve the application name: CLI `--name`, then the variable named by
+/// `--name-env` (if set and non-empty), then the config `name`.
+fn resolve_name(
+    name: Option<&str>,
+    name_env: Option<&str>,
+    config_name: Option<&str>,
+) -> Option<String> {
+    name.map(str::to_string)
+        .or_else(|| {
+            name_env
+                .and_then(|var| std::env::var(var).ok())
+                .filter(|value| !value.is_empty())
+        })
+        .or_else(|| config_name.map(str::to_string))
+}
+
+/// Prompt for each missing required top-level value and inject it into the args.
+///
+/// Returns the args unchanged when stdin is not a TTY, so the normal
+/// missing-argument error is reported. Options are injected as `--long=value`
+/// (or `-s value`) ahead of the existing args; positionals are appended.
+fn prompt_missing_values(
+    cfg: &Config,
+    mut args: Vec<String>,
+    effective_name: &str,
+    is_tty: bool,
+    input: &mut impl BufRead,
+    prompt: &mut impl Write,
+) -> Result<Vec<String>> {
+    if !is_tty {
+        return Ok(args);
+    }
+
+    let mut injected_options = Vec::new();
+    for arg in missing_required_args(cfg, &args, effective_name) {
+        match &arg.help {
+            Some(help) => write!(prompt, "{} ({}): ", arg.name, help)?,
+            None => write!(prompt, "{}: ", arg.name)?,
+        }
+        prompt.flush()?;
+
+        let mut line = String::new();
+        input.read_line(&mut line)?;
+        let value = line.trim_end_matches(['\n', '\r']).to_string();
+
+        match arg.arg_type {
+            ArgType::Positional => args.push(value),
+            _ => {
+                if let Some(long) = arg.long_with_prefix() {
+                    injected_options.push(format!("{}={}", long, value));
+                } else if let Some(short) = arg.short_with_prefix() {
+                    injected_options.push(short);
+                    injected_options.push(value);
+                }
+            }
+        }
+    }
+
+    injected_options.extend(args);
+    Ok(injected_options)
+}
+
+/// Split the environment variable `var` into arguments with shell-word rules
+/// (quotes and backslashes as in `sh`) and put them before `args`.
+/// An unset or empty variable contributes no arguments.
+fn env_args(var: &str, args: Vec<String>) -> std::result::Result<Vec<String>, String> {
+    let value = std::env::var(var).unwrap_or_default();
+    let mut split = shell_words::split(&value)
+        .map_err(|e| format!("failed to split arguments from ${}: {}", var, e))?;
+    split.extend(args);
+    Ok(split)
+}
+
+/// Check the number of script arguments against the `--max-args` limit.
+fn check_max_args(args: &[String], max_args: Option<usize>) -> std::result::Result<(), String> {
+    match max_args {
+        Some(max) if args.len() > max => Err(format!(
+            "too many arguments: got {}, limit is {}",
+            args.len(),
+            max
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Check that every name given to a filter flag (`--only`, `--exclude`) is a
+/// declared arg, at the top level or in a subcommand.
+fn check_filter_names(
+    cfg: &Config,
+    flag: &str,
+    names: &[String],
+) -> std::result::Result<(), String> {
+    let subcommands = cfg.all_subcommands();
+    let is_declared = |name: &String| {
+        cfg.args
+            .iter()
+            .chain(subcommands.iter().flat_map(|(_, s)| &s.args))
+            .any(|arg| &arg.name == name)
+    };
+    match names.iter().find(|name| !is_declared(name)) {
+        Some(unknown) => Err(format!("unknown argument in {}: {}", flag, unknown)),
+        None => Ok(()),
+    }
+}
+
+/// Keep only the values named in `only`, or all of them when `only` is empty.
+fn retain_only<V>(mut values: HashMap<String, V>, only: &[String]) -> HashMap<String, V> {
+    if !only.is_empty() {
+        values.retain(|name, _| only.contains(name));
+    }
+    values
+}
+
+/// Drop the values named in `exclude`.
+fn drop_excluded<V>(mut values: HashMap<String, V>, exclude: &[String]) -> HashMap<String, V> {
+    values.retain(|name, _| !exclude.contains(name));
+    values
+}
+
+/// Re-key parsed values by their stripped arg names so the emitted
+/// variable names honor `strip_name_prefix`.
+fn strip_value_names<V>(cfg: &Config, values: HashMap<String, V>) -> HashMap<String, V> {
+    if cfg.strip_name_prefix.is_none() {
+        return values;
+    }
+    values
+        .into_iter()
+        .map(|(name, value)| (cfg.strip_arg_name(&name).to_string(), value))
+        .collect()
+}
+
+/// Format parsed values as a one-line summary, e.g. `verbose=true, files=[a,b]`.
+///
+/// The subcommand comes first when present; args follow in name order.
+fn format_summary(values: &HashMap<String, ParsedValue>, subcommand: Option<&str>) -> String {
+    let list = |items: &[String]| format!("[{}]", items.join(","));
+
+    let mut names: Vec<_> = values.keys().collect();
+    names.sort();
+
+    let mut parts: Vec<String> = subcommand
+        .map(|name| format!("subcommand={}", name))
+        .into_iter()
+        .collect();
+    for name in names {
+        let rendered = match &values[name] {
+            ParsedValue::Single(value) => value.clone(),
+            ParsedValue::Multiple(items) => list(items),
+            ParsedValue::Grouped(groups) => {
+                let groups: Vec<String> = groups.iter().map(|g| list(g)).collect();
+                list(&groups)
+            }
+        };
+        parts.push(format!("{}={}", name, rendered));
+    }
+    parts.join(", ")
+}
+
+/// Names (after prefix stripping) of the int-typed args in scope for `subcommand`.
+///
+/// Args with `choices` are skipped, since choices take precedence over `value_type`.
+fn int_arg_names(cfg: &Config, subcommand: Option<&str>) -> HashSet<String> {
+    scoped_arg_names(cfg, subcommand, |arg| {
+        arg.value_type == ValueType::Int && arg.choices.is_none()
+    })
+}
+
+/// Names (after prefix stripping) of the args in scope for `subcommand` that
+/// match `filter`: the top-level args plus those of each subcommand on its path.
+fn scoped_arg_names(
+    cfg: &Config,
+    subcommand: Option<&str>,
+    filter: impl Fn(&ArgConfig) -> bool,
+) -> HashSet<String> {
+    let chain = subcommand
+        .and_then(|path| cfg.subcommand_chain(path))
+        .unwrap_or_default();
+    cfg.args
+        .iter()
+        .chain(chain.iter().flat_map(|subcmd| &subcmd.args))
+        .filter(|arg| filter(arg))
+        .map(|arg| cfg.strip_arg_name(&arg.name).to_string())
+        .collect()
+}
+
+/// The error for the first of `flags` that is set while `format` is not
+/// `shell`; `flags` pairs each flag name with whether it was given.
+fn format_conflict(format: OutputFormat, flags: &[(&str, bool)]) -> Option<String> {
+    if format == OutputFormat::Shell {
+        return None;
+    }
+    let (flag, _) = flags.iter().find(|(_, set)| *set)?;
+    let name = format.to_possible_value()?.get_name().to_string();
+    Some(format!(
+        "the argument '--format {}' cannot be used with '{}'",
+        name, flag
+    ))
+}
+
+/// Whether an arg named `subcommand` would clash with the key `--format toml`
+/// and `json` store the matched subcommand under.
+fn subcommand_key_clash(cfg: &Config) -> bool {
+    !cfg.subcommands.is_empty()
+        && cfg
+            .args
+            .iter()
+            .chain(
+                cfg.all_subcommands()
+                    .into_iter()
+                    .flat_map(|(_, subcmd)| &subcmd.args),
+            )
+            .any(|arg| arg.name == "subcommand")
+}
+
+/// Where `parse` reports errors, help and version, and in which dialect.
+struct Report<'a> {
+    /// Print the script itself (`--stdout`/`--eval`) instead of a file path
+    print_output: bool,
+    output_dir: Option<&'a Path>,
+    output_file: Option<&'a Path>,
+    dialect: ShellDialect,
+    /// Non-shell formats print help, version and errors as documents on stdout
+    format: OutputFormat,
+}
+
+impl Report<'_> {
+    /// Print `content`, or write it to the output file (default: a temp file)
+    /// and print the path.
+    fn emit(&self, content: &str) -> Result<()> {
+        if self.print_output {
+            print!("{}", content);
+            return Ok(());
+        }
+        let path = match self.output_file {
+            Some(file) => write_output_file(content, file),
+            None => write_temp_file(content, self.output_dir),
+        }?;
+        println!("{}", path.display());
+        Ok(())
+    }
+}
+
+/// Output an error file path (or, with `print_output`, its contents) and return Ok.
+/// Falls back to stderr + exit 1 if file creation fails. With `--format toml`
+/// or `json`, prints an `error` document and exits 1.
+fn output_error(message: &str, report: &Report) -> Result<()> {
+    match report.format {
+        OutputFormat::Toml => print!("{}", generate_toml_message("error", message)),
+        OutputFormat::Json => println!("{}", generate_json_message("error", message)),
+        OutputFormat::Shell => {}
+    }
+    if report.format != OutputFormat::Shell {
+        std::process::exit(1);
+    }
+
+    match report.emit(&generate_error_string_for(message, report.dialect)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            eprintln!("shclap: {}", message);
+            eprintln!("shclap: also failed to create error output file: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_parse_subcommand_parses_config() {
+        let cli = Cli::try_parse_from(["shclap", "parse", "--config", r#"{"name":"test"}"#, "--"])
+            .unwrap();
+
+        match cli.command {
+            Commands::Parse {
+                config,
+                name,
+                prefix,
+                args,
+                ..
+            } => {
+                assert_eq!(config, r#"{"name":"test"}"#);
+                assert!(name.is_none());
+                assert!(prefix.is_none());
+                assert!(args.is_empty());
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_prefix() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--prefix",
+            "MYAPP_",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { name, prefix, .. } => {
+                assert!(name.is_none());
+                assert_eq!(prefix, Some("MYAPP_".to_string()));
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_name() {
+        let cli = Cli::try_parse_from([
+            "shclap", "parse", "--config", r#"{}"#, "--name", "myapp", "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { name, .. } => {
+                assert_eq!(name, Some("myapp".to_string()));
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_args() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--",
+            "-v",
+            "--output",
+            "file.txt",
+            "input.txt",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { args, .. } => {
+                assert_eq!(args, vec!["-v", "--output", "file.txt", "input.txt"]);
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_requires_config() {
+        let result = Cli::try_parse_from(["shclap", "parse", "--"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_help_subcommand() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "help",
+            "--config",
+            r#"{"name":"test","description":"A test"}"#,
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Help { config, name, .. } => {
+                assert_eq!(config, r#"{"name":"test","description":"A test"}"#);
+                assert!(name.is_none());
+            }
+            _ => panic!("Expected Help command"),
+        }
+    }
+
+    #[test]
+    fn test_help_subcommand_with_name() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "help",
+            "--config",
+            r#"{"description":"A test"}"#,
+            "--name",
+            "myapp",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Help { config, name, .. } => {
+                assert_eq!(config, r#"{"description":"A test"}"#);
+                assert_eq!(name, Some("myapp".to_string()));
+            }
+            _ => panic!("Expected Help command"),
+        }
+    }
+
+    #[test]
+    fn test_version_subcommand() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "version",
+            "--config",
+            r#"{"name":"test","version":"1.0.0"}"#,
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Version { config, name, .. } => {
+                assert_eq!(config, r#"{"name":"test","version":"1.0.0"}"#);
+                assert!(name.is_none());
+            }
+            _ => panic!("Expected Version command"),
+        }
+    }
+
+    #[test]
+    fn test_version_subcommand_with_name() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "version",
+            "--config",
+            r#"{"version":"1.0.0"}"#,
+            "--name",
+            "myapp",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Version { config, name, .. } => {
+                assert_eq!(config, r#"{"version":"1.0.0"}"#);
+                assert_eq!(name, Some("myapp".to_string()));
+            }
+            _ => panic!("Expected Version command"),
+        }
+    }
+
+    #[test]
+    fn test_list_vars_subcommand() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "list-vars",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--prefix",
+            "APP_",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::ListVars { config, prefix } => {
+                assert_eq!(config, r#"{"name":"test"}"#);
+                assert_eq!(prefix, Some("APP_".to_string()));
+            }
+            _ => panic!("Expected ListVars command"),
+        }
+    }
+
+    #[test]
+    fn test_list_subcommands_subcommand() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "list-subcommands",
+            "--config",
+            r#"{"schema_version":2}"#,
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::ListSubcommands { config } => {
+                assert_eq!(config, r#"{"schema_version":2}"#);
+            }
+            _ => panic!("Expected ListSubcommands command"),
+        }
+    }
+
+    #[test]
+    fn test_explain_config_subcommand() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "explain-config",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--prefix",
+            "APP_",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::ExplainConfig {
+                config,
+                name,
+                prefix,
+                ..
+            } => {
+                assert_eq!(config, r#"{"name":"test"}"#);
+                assert_eq!(name, None);
+                assert_eq!(prefix.as_deref(), Some("APP_"));
+            }
+            _ => panic!("Expected ExplainConfig command"),
+        }
+    }
+
+    #[test]
+    fn test_diff_subcommand() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "diff",
+            "--old",
+            r#"{"name":"a"}"#,
+            "--new",
+            r#"{"name":"b"}"#,
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Diff { old, new } => {
+                assert_eq!(old, r#"{"name":"a"}"#);
+                assert_eq!(new, r#"{"name":"b"}"#);
+            }
+            _ => panic!("Expected Diff command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_requires_subcommand() {
+        let result = Cli::try_parse_from(["shclap"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_help() {
+        // Verify the command can generate help without panicking
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_prefix_priority_cli_overrides_config() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test","prefix":"CONFIG_"}"#,
+            "--prefix",
+            "CLI_",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { config, prefix, .. } => {
+                let cfg = Config::from_json(&config).unwrap();
+                let effective = prefix.as_deref().unwrap_or_else(|| cfg.effective_prefix());
+                assert_eq!(effective, "CLI_");
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_prefix_priority_config_when_no_cli() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test","prefix":"CONFIG_"}"#,
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { config, prefix, .. } => {
+                let cfg = Config::from_json(&config).unwrap();
+                let effective = prefix.as_deref().unwrap_or_else(|| cfg.effective_prefix());
+                assert_eq!(effective, "CONFIG_");
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_prefix_priority_default_when_neither_set() {
+        let cli = Cli::try_parse_from(["shclap", "parse", "--config", r#"{"name":"test"}"#, "--"])
+            .unwrap();
+
+        match cli.command {
+            Commands::Parse { config, prefix, .. } => {
+                let cfg = Config::from_json(&config).unwrap();
+                let effective = prefix.as_deref().unwrap_or_else(|| cfg.effective_prefix());
+                assert_eq!(effective, "SHCLAP_");
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_name_priority_cli_overrides_config() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"config_name"}"#,
+            "--name",
+            "cli_name",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { config, name, .. } => {
+                let cfg = Config::from_json(&config).unwrap();
+                let effective = name.as_deref().or(cfg.name.as_deref()).unwrap();
+                assert_eq!(effective, "cli_name");
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_name_priority_config_when_no_cli() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"config_name"}"#,
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { config, name, .. } => {
+                let cfg = Config::from_json(&config).unwrap();
+                let effective = name.as_deref().or(cfg.name.as_deref()).unwrap();
+                assert_eq!(effective, "config_name");
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_prefix_strip() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--prefix-strip",
+            "opt_",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { prefix_strip, .. } => {
+                assert_eq!(prefix_strip, Some("opt_".to_string()));
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_eval() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--eval",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { eval, .. } => assert!(eval),
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_validate_values() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--validate-values",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse {
+                validate_values, ..
+            } => assert!(validate_values),
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_schema_version() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--schema-version",
+            "1",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { schema_version, .. } => assert_eq!(schema_version, Some(1)),
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_rejects_unsupported_schema_version() {
+        for version in ["0", "3"] {
+            let result = Cli::try_parse_from([
+                "shclap",
+                "parse",
+                "--config",
+                r#"{"name":"test"}"#,
+                "--schema-version",
+                version,
+                "--",
+            ]);
+            assert!(result.is_err(), "version {} should be rejected", version);
+        }
+    }
+
+    #[test]
+    fn test_schema_version_override_applies_v1_rules() {
+        let mut cfg = Config::from_json(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"files","type":"option","multiple":true}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(cfg.validate().is_ok());
+
+        cfg.schema_version = 1;
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_xtrace() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--xtrace",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { xtrace, .. } => assert!(xtrace),
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_count_occurrences() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--count-occurrences",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse {
+                count_occurrences, ..
+            } => assert!(count_occurrences),
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_emit_unset_prefix() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--emit-unset-prefix",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse {
+                emit_unset_prefix, ..
+            } => assert!(emit_unset_prefix),
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_check_max_args() {
+        let args: Vec<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+
+        assert!(check_max_args(&args, None).is_ok());
+        assert!(check_max_args(&args, Some(3)).is_ok());
+        assert_eq!(
+            check_max_args(&args, Some(2)),
+            Err("too many arguments: got 3, limit is 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_env_args_splits_quoted_arguments() {
+        std::env::set_var(
+            "SHCLAP_TEST_ENV_ARGS_QUOTED",
+            r#"--output "my file.txt" --name='a b' plain\ word"#,
+        );
+        let args = env_args("SHCLAP_TEST_ENV_ARGS_QUOTED", vec!["extra".to_string()]).unwrap();
+        std::env::remove_var("SHCLAP_TEST_ENV_ARGS_QUOTED");
+
+        assert_eq!(
+            args,
+            [
+                "--output",
+                "my file.txt",
+                "--name=a b",
+                "plain word",
+                "extra"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_env_args_unset_and_unbalanced() {
+        let args = env_args("SHCLAP_TEST_ENV_ARGS_UNSET", vec!["x".to_string()]).unwrap();
+        assert_eq!(args, ["x"]);
+
+        std::env::set_var("SHCLAP_TEST_ENV_ARGS_BAD", r#"--output "unterminated"#);
+        let err = env_args("SHCLAP_TEST_ENV_ARGS_BAD", Vec::new()).unwrap_err();
+        std::env::remove_var("SHCLAP_TEST_ENV_ARGS_BAD");
+        assert!(
+            err.starts_with("failed to split arguments from $SHCLAP_TEST_ENV_ARGS_BAD"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_max_args() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--max-args",
+            "100",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { max_args, .. } => assert_eq!(max_args, Some(100)),
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_strip_value_names() {
+        let cfg = Config::from_json(r#"{"name":"test","strip_name_prefix":"opt_"}"#).unwrap();
+        let values: HashMap<String, ParsedValue> = [
+            ("opt_output", ParsedValue::Single("out.txt".to_string())),
+            ("verbose", ParsedValue::Single("true".to_string())),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        let stripped = strip_value_names(&cfg, values);
+        assert_eq!(
+            stripped.get("output"),
+            Some(&ParsedValue::Single("out.txt".to_string()))
+        );
+        assert!(stripped.contains_key("verbose"));
+        assert!(!stripped.contains_key("opt_output"));
+    }
+
+    #[test]
+    fn test_prompt_missing_values_non_tty_falls_back() {
+        let cfg = Config::from_json(
+            r#"{"name":"test","args":[
+                {"name":"output","long":"output","type":"option","required":true}
+            ]}"#,
+        )
+        .unwrap();
+        let mut input = io::Cursor::new("ignored\n");
+        let mut prompt = Vec::new();
+
+        let args =
+            prompt_missing_values(&cfg, vec![], "test", false, &mut input, &mut prompt).unwrap();
+
+        // Args are untouched and nothing is prompted, so parsing reports the usual error
+        assert!(args.is_empty());
+        assert!(prompt.is_empty());
+        assert!(matches!(
+            parse_args(&cfg, &args, "test"),
+            ParseOutcome::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_prompt_missing_values_injects_answers() {
+        let cfg = Config::from_json(
+            r#"{"name":"test","args":[
+                {"name":"output","long":"output","type":"option","required":true,"help":"Output file"},
+                {"name":"input","type":"positional","required":true}
+            ]}"#,
+        )
+        .unwrap();
+        let mut input = io::Cursor::new("out.txt\nin.txt\n");
+        let mut prompt = Vec::new();
+
+        let args =
+            prompt_missing_values(&cfg, vec![], "test", true, &mut input, &mut prompt).unwrap();
+
+        assert_eq!(args, vec!["--output=out.txt", "in.txt"]);
+        assert_eq!(
+            String::from_utf8(prompt).unwrap(),
+            "output (Output file): input: "
+        );
+        assert!(matches!(
+            parse_args(&cfg, &args, "test"),
+            ParseOutcome::Success(_)
+        ));
+    }
+
+    #[test]
+    fn test_subcommands_parse_name_env() {
+        for subcommand in [
+            "parse",
+            "help",
+            "version",
+            "print",
+            "explain-config",
+            "dump-command",
+            "validate-env",
+        ] {
+            let cli = Cli::try_parse_from([
+                "shclap",
+                subcommand,
+                "--config",
+                r#"{}"#,
+                "--name-env",
+                "PROG_NAME",
+            ])
+            .unwrap();
+
+            let name_env = match cli.command {
+                Commands::Parse { name_env, .. }
+                | Commands::Help { name_env, .. }
+                | Commands::Version { name_env, .. }
+                | Commands::Print { name_env, .. }
+                | Commands::ExplainConfig { name_env, .. }
+                | Commands::DumpCommand { name_env, .. }
+                | Commands::ValidateEnv { name_env, .. } => name_env,
+                _ => panic!("Expected a command with --name-env"),
+            };
+            assert_eq!(name_env.as_deref(), Some("PROG_NAME"));
+        }
+    }
+
+    #[test]
+    fn test_resolve_name_precedence() {
+        let var = "SHCLAP_TEST_RESOLVE_NAME";
+        std::env::set_var(var, "from-env");
+
+        // CLI --name beats the env var, which beats the config name
+        assert_eq!(
+            resolve_name(Some("cli"), Some(var), Some("config")).as_deref(),
+            Some("cli")
+        );
+        assert_eq!(
+            resolve_name(None, Some(var), Some("config")).as_deref(),
+            Some("from-env")
+        );
+
+        // Empty or unset variables fall through to the config name
+        std::env::set_var(var, "");
+        assert_eq!(
+            resolve_name(None, Some(var), Some("config")).as_deref(),
+            Some("config")
+        );
+        std::env::remove_var(var);
+        assert_eq!(
+            resolve_name(None, Some(var), Some("config")).as_deref(),
+            Some("config")
+        );
+        assert_eq!(resolve_name(None, Some(var), None), None);
+    }
+
+    #[test]
+    fn test_int_arg_names() {
+        let cfg = Config::from_json(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"port","type":"option","value_type":"int"},
+                {"name":"host","type":"option"},
+                {"name":"level","type":"option","value_type":"int","choices":["1","2"]}
+            ],"subcommands":[
+                {"name":"run","args":[{"name":"workers","type":"option","value_type":"int"}]},
+                {"name":"stop","args":[{"name":"timeout","type":"option","value_type":"int"}]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let names = int_arg_names(&cfg, Some("run"));
+        assert_eq!(
+            names,
+            HashSet::from(["port".to_string(), "workers".to_string()])
+        );
+        assert_eq!(
+            int_arg_names(&cfg, None),
+            HashSet::from(["port".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_subcommand_parses_output_dir() {
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--output-dir",
+            "/var/tmp/shclap",
+            "--",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::Parse { output_dir, .. } => {
+                assert_eq!(output_dir, Some(PathBuf::from("/var/tmp/shclap")));
+            }
+            _ => panic!("Expected Parse command"),
+        }
+    }
+
+    #[test]
+    fn test_format_summary_mixed_values() {
+        let values = HashMap::from([
+            (
+                "verbose".to_string(),
+                ParsedValue::Single("true".to_string()),
+            ),
+            (
+                "output".to_string(),
+                ParsedValue::Single("file.txt".to_string()),
+            ),
+            (
+                "files".to_string(),
+                ParsedValue::Multiple(vec!["a".to_string(), "b".to_string()]),
+            ),
+            (
+                "pairs".to_string(),
+                ParsedValue::Grouped(vec![
+                    vec!["1".to_string(), "2".to_string()],
+                    vec!["3".to_string(), "4".to_string()],
+                ]),
+            ),
+        ]);
+
+        assert_eq!(
+            format_summary(&values, None),
+            "files=[a,b], output=file.txt, pairs=[[1,2],[3,4]], verbose=true"
+        );
+        assert_eq!(
+            format_summary(&values, Some("run")),
+            "subcommand=run, files=[a,b], output=file.txt, pairs=[[1,2],[3,4]], verbose=true"
+        );
+    }
+
+    #[test]
+    fn test_retain_only() {
+        let values = HashMap::from([
+            ("verbose".to_string(), "true"),
+            ("output".to_string(), "file.txt"),
+            ("level".to_string(), "3"),
+        ]);
 
-            let effective_prefix = prefix.as_deref().unwrap_or_else(|| cfg.effective_prefix());
+        let single = retain_only(values.clone(), &["output".to_string()]);
+        assert_eq!(single, HashMap::from([("output".to_string(), "file.txt")]));
 
-            println!(
-                "{}",
-                generate_print(&cfg, &effective_name, effective_prefix)
-            );
-        }
-    }
+        let multiple = retain_only(values.clone(), &["output".to_string(), "level".to_string()]);
+        assert_eq!(multiple.len(), 2);
+        assert!(multiple.contains_key("output") && multiple.contains_key("level"));
 
-    Ok(())
-}
+        assert_eq!(retain_only(values.clone(), &[]), values);
+    }
 
-/// Output an error file path and return Ok.
-/// Falls back to stderr + exit 1 if file creation fails.
-fn output_error(message: &str) -> Result<()> {
-    match generate_error_output(message) {
-        Ok(path) => {
-            println!("{}", path.display());
-            Ok(())
-        }
-        Err(e) => {
-            eprintln!("shclap: {}", message);
-            eprintln!("shclap: also failed to create error output file: {}", e);
-            std::process::exit(1);
-        }
+    #[test]
+    fn test_drop_excluded() {
+        let values = HashMap::from([
+            ("token".to_string(), "secret"),
+            ("output".to_string(), "file.txt"),
+        ]);
+        let kept = drop_excluded(values.clone(), &["token".to_string()]);
+        assert_eq!(kept, HashMap::from([("output".to_string(), "file.txt")]));
+        assert_eq!(drop_excluded(values.clone(), &[]), values);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use clap::CommandFactory;
+    #[test]
+    fn test_only_and_exclude_conflict() {
+        let result = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--only",
+            "output",
+            "--exclude",
+            "token",
+            "--",
+        ]);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::ArgumentConflict
+        );
+    }
 
     #[test]
-    fn test_parse_subcommand_parses_config() {
-        let cli = Cli::try_parse_from(["shclap", "parse", "--config", r#"{"name":"test"}"#, "--"])
-            .unwrap();
+    fn test_check_filter_names() {
+        let cfg = Config::from_json(
+            r#"{"schema_version":2,"name":"test","args":[{"name":"output","type":"option"}],
+                "subcommands":[{"name":"run","args":[{"name":"jobs","type":"option"}]}]}"#,
+        )
+        .unwrap();
 
-        match cli.command {
-            Commands::Parse {
-                config,
-                name,
-                prefix,
-                args,
-            } => {
-                assert_eq!(config, r#"{"name":"test"}"#);
-                assert!(name.is_none());
-                assert!(prefix.is_none());
-                assert!(args.is_empty());
-            }
-            _ => panic!("Expected Parse command"),
-        }
+        assert!(check_filter_names(&cfg, "--only", &[]).is_ok());
+        assert!(
+            check_filter_names(&cfg, "--only", &["output".to_string(), "jobs".to_string()]).is_ok()
+        );
+        assert_eq!(
+            check_filter_names(&cfg, "--only", &["output".to_string(), "nope".to_string()]),
+            Err("unknown argument in --only: nope".to_string())
+        );
+        assert_eq!(
+            check_filter_names(&cfg, "--exclude", &["nope".to_string()]),
+            Err("unknown argument in --exclude: nope".to_string())
+        );
     }
 
     #[test]
-    fn test_parse_subcommand_parses_prefix() {
+    fn test_parse_subcommand_parses_repeated_only() {
         let cli = Cli::try_parse_from([
             "shclap",
             "parse",
             "--config",
             r#"{"name":"test"}"#,
-            "--prefix",
-            "MYAPP_",
+            "--only",
+            "output",
+            "--only",
+            "level",
             "--",
         ])
         .unwrap();
 
         match cli.command {
-            Commands::Parse { name, prefix, .. } => {
-                assert!(name.is_none());
-                assert_eq!(prefix, Some("MYAPP_".to_string()));
-            }
+            Commands::Parse { only, .. } => assert_eq!(only, vec!["output", "level"]),
             _ => panic!("Expected Parse command"),
         }
     }
 
     #[test]
-    fn test_parse_subcommand_parses_name() {
+    fn test_parse_format_toml() {
         let cli = Cli::try_parse_from([
-            "shclap", "parse", "--config", r#"{}"#, "--name", "myapp", "--",
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--format",
+            "toml",
+            "--",
         ])
         .unwrap();
-
         match cli.command {
-            Commands::Parse { name, .. } => {
-                assert_eq!(name, Some("myapp".to_string()));
-            }
+            Commands::Parse { format, .. } => assert_eq!(format, OutputFormat::Toml),
             _ => panic!("Expected Parse command"),
         }
     }
 
     #[test]
-    fn test_parse_subcommand_parses_args() {
+    fn test_parse_format_defaults_to_shell_and_conflicts_with_eval() {
         let cli = Cli::try_parse_from([
             "shclap",
             "parse",
             "--config",
             r#"{"name":"test"}"#,
-            "--",
-            "-v",
-            "--output",
-            "file.txt",
-            "input.txt",
+            "--eval",
         ])
         .unwrap();
-
         match cli.command {
-            Commands::Parse { args, .. } => {
-                assert_eq!(args, vec!["-v", "--output", "file.txt", "input.txt"]);
-            }
+            Commands::Parse { format, .. } => assert_eq!(format, OutputFormat::Shell),
             _ => panic!("Expected Parse command"),
         }
+
+        let result = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--format",
+            "toml",
+            "--eval",
+        ]);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn test_parse_eval_conflicts_with_output_only_flags() {
+        // --eval prints plain bash exports, so these would be silently ignored
+        for flags in [
+            &["--shell", "fish"][..],
+            &["--xtrace"],
+            &["--declare-int"],
+            &["--count-occurrences"],
+            &["--self-cleanup"],
+        ] {
+            let mut argv = vec![
+                "shclap",
+                "parse",
+                "--config",
+                r#"{"name":"test"}"#,
+                "--eval",
+            ];
+            argv.extend_from_slice(flags);
+            let result = Cli::try_parse_from(argv);
+            assert_eq!(
+                result.unwrap_err().kind(),
+                clap::error::ErrorKind::ArgumentConflict,
+                "{:?}",
+                flags
+            );
+        }
     }
 
     #[test]
-    fn test_parse_subcommand_requires_config() {
-        let result = Cli::try_parse_from(["shclap", "parse", "--"]);
-        assert!(result.is_err());
+    fn test_format_conflict_only_for_non_shell_formats() {
+        let flags = [
+            ("--shell", false),
+            ("--xtrace", true),
+            ("--declare-int", true),
+        ];
+        assert_eq!(format_conflict(OutputFormat::Shell, &flags), None);
+        assert_eq!(
+            format_conflict(OutputFormat::Toml, &flags).as_deref(),
+            Some("the argument '--format toml' cannot be used with '--xtrace'")
+        );
+        assert_eq!(
+            format_conflict(OutputFormat::Toml, &[("--shell", false)]),
+            None
+        );
     }
 
     #[test]
-    fn test_help_subcommand() {
+    fn test_subcommand_key_clash() {
+        let clash = |json: &str| subcommand_key_clash(&Config::from_json(json).unwrap());
+        assert!(!clash(
+            r#"{"schema_version":2,"args":[{"name":"subcommand","long":"subcommand","type":"option"}]}"#
+        ));
+        assert!(clash(
+            r#"{"schema_version":2,"subcommands":[{"name":"run","args":[{"name":"subcommand","long":"subcommand","type":"option"}]}]}"#
+        ));
+        assert!(!clash(
+            r#"{"schema_version":2,"subcommands":[{"name":"run","args":[{"name":"target","long":"target","type":"option"}]}]}"#
+        ));
+    }
+
+    #[test]
+    fn test_parse_strict_flag() {
         let cli = Cli::try_parse_from([
             "shclap",
-            "help",
+            "parse",
             "--config",
-            r#"{"name":"test","description":"A test"}"#,
+            r#"{"name":"test"}"#,
+            "--strict",
+            "--",
         ])
         .unwrap();
-
         match cli.command {
-            Commands::Help { config, name } => {
-                assert_eq!(config, r#"{"name":"test","description":"A test"}"#);
-                assert!(name.is_none());
-            }
-            _ => panic!("Expected Help command"),
+            Commands::Parse { strict, .. } => assert!(strict),
+            _ => panic!("Expected Parse command"),
         }
     }
 
     #[test]
-    fn test_help_subcommand_with_name() {
+    fn test_parse_shell_dialect() {
+        let cli =
+            Cli::try_parse_from(["shclap", "parse", "--config", r#"{"name":"test"}"#]).unwrap();
+        match cli.command {
+            Commands::Parse { shell, .. } => assert_eq!(shell, ShellDialect::Bash),
+            _ => panic!("Expected Parse command"),
+        }
+
         let cli = Cli::try_parse_from([
             "shclap",
-            "help",
+            "parse",
             "--config",
-            r#"{"description":"A test"}"#,
-            "--name",
-            "myapp",
+            r#"{"name":"test"}"#,
+            "--shell",
+            "posix",
         ])
         .unwrap();
+        match cli.command {
+            Commands::Parse { shell, .. } => assert_eq!(shell, ShellDialect::Posix),
+            _ => panic!("Expected Parse command"),
+        }
 
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--shell",
+            "fish",
+        ])
+        .unwrap();
         match cli.command {
-            Commands::Help { config, name } => {
-                assert_eq!(config, r#"{"description":"A test"}"#);
-                assert_eq!(name, Some("myapp".to_string()));
-            }
-            _ => panic!("Expected Help command"),
+            Commands::Parse { shell, .. } => assert_eq!(shell, ShellDialect::Fish),
+            _ => panic!("Expected Parse command"),
         }
-    }
 
-    #[test]
-    fn test_version_subcommand() {
         let cli = Cli::try_parse_from([
             "shclap",
-            "version",
+            "parse",
             "--config",
-            r#"{"name":"test","version":"1.0.0"}"#,
+            r#"{"name":"test"}"#,
+            "--shell",
+            "zsh",
         ])
         .unwrap();
+        match cli.command {
+            Commands::Parse { shell, .. } => assert_eq!(shell, ShellDialect::Zsh),
+            _ => panic!("Expected Parse command"),
+        }
 
+        let cli = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--shell",
+            "powershell",
+        ])
+        .unwrap();
         match cli.command {
-            Commands::Version { config, name } => {
-                assert_eq!(config, r#"{"name":"test","version":"1.0.0"}"#);
-                assert!(name.is_none());
-            }
-            _ => panic!("Expected Version command"),
+            Commands::Parse { shell, .. } => assert_eq!(shell, ShellDialect::PowerShell),
+            _ => panic!("Expected Parse command"),
         }
     }
 
     #[test]
-    fn test_version_subcommand_with_name() {
+    fn test_parse_subcommand_parses_output_file() {
         let cli = Cli::try_parse_from([
             "shclap",
-            "version",
+            "parse",
             "--config",
-            r#"{"version":"1.0.0"}"#,
-            "--name",
-            "myapp",
+            r#"{"name":"test"}"#,
+            "--output-file",
+            "/run/myapp/args.fifo",
         ])
         .unwrap();
-
         match cli.command {
-            Commands::Version { config, name } => {
-                assert_eq!(config, r#"{"version":"1.0.0"}"#);
-                assert_eq!(name, Some("myapp".to_string()));
+            Commands::Parse { output_file, .. } => {
+                assert_eq!(output_file, Some(PathBuf::from("/run/myapp/args.fifo")));
             }
-            _ => panic!("Expected Version command"),
+            _ => panic!("Expected Parse command"),
         }
-    }
 
-    #[test]
-    fn test_cli_requires_subcommand() {
-        let result = Cli::try_parse_from(["shclap"]);
+        let result = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--output-file",
+            "out.sh",
+            "--output-dir",
+            "/tmp",
+        ]);
         assert!(result.is_err());
     }
 
     #[test]
-    fn test_cli_help() {
-        // Verify the command can generate help without panicking
-        Cli::command().debug_assert();
+    fn test_import_subcommand() {
+        let cli = Cli::try_parse_from(["shclap", "import", "--clap-yaml", "cli.yml"]).unwrap();
+        match cli.command {
+            Commands::Import { clap_yaml } => assert_eq!(clap_yaml, PathBuf::from("cli.yml")),
+            _ => panic!("Expected Import command"),
+        }
     }
 
     #[test]
-    fn test_prefix_priority_cli_overrides_config() {
+    fn test_dump_command_subcommand() {
         let cli = Cli::try_parse_from([
             "shclap",
-            "parse",
+            "dump-command",
             "--config",
-            r#"{"name":"test","prefix":"CONFIG_"}"#,
-            "--prefix",
-            "CLI_",
-            "--",
+            r#"{"name":"test"}"#,
+            "--name",
+            "app",
         ])
         .unwrap();
-
         match cli.command {
-            Commands::Parse {
-                config,
-                name: _,
-                prefix,
-                args: _,
-            } => {
-                let cfg = Config::from_json(&config).unwrap();
-                let effective = prefix.as_deref().unwrap_or_else(|| cfg.effective_prefix());
-                assert_eq!(effective, "CLI_");
-            }
-            _ => panic!("Expected Parse command"),
+            Commands::DumpCommand { name, .. } => assert_eq!(name.as_deref(), Some("app")),
+            _ => panic!("Expected DumpCommand command"),
         }
     }
 
     #[test]
-    fn test_prefix_priority_config_when_no_cli() {
+    fn test_validate_env_subcommand() {
         let cli = Cli::try_parse_from([
             "shclap",
-            "parse",
+            "validate-env",
             "--config",
-            r#"{"name":"test","prefix":"CONFIG_"}"#,
-            "--",
+            r#"{"name":"test"}"#,
+            "--prefix",
+            "APP_",
         ])
         .unwrap();
-
-        match cli.command {
-            Commands::Parse {
-                config,
-                name: _,
-                prefix,
-                args: _,
-            } => {
-                let cfg = Config::from_json(&config).unwrap();
-                let effective = prefix.as_deref().unwrap_or_else(|| cfg.effective_prefix());
-                assert_eq!(effective, "CONFIG_");
-            }
-            _ => panic!("Expected Parse command"),
-        }
-    }
-
-    #[test]
-    fn test_prefix_priority_default_when_neither_set() {
-        let cli = Cli::try_parse_from(["shclap", "parse", "--config", r#"{"name":"test"}"#, "--"])
-            .unwrap();
-
         match cli.command {
-            Commands::Parse {
-                config,
-                name: _,
-                prefix,
-                args: _,
-            } => {
-                let cfg = Config::from_json(&config).unwrap();
-                let effective = prefix.as_deref().unwrap_or_else(|| cfg.effective_prefix());
-                assert_eq!(effective, "SHCLAP_");
+            Commands::ValidateEnv { name, prefix, .. } => {
+                assert_eq!(name, None);
+                assert_eq!(prefix.as_deref(), Some("APP_"));
             }
-            _ => panic!("Expected Parse command"),
+            _ => panic!("Expected ValidateEnv command"),
         }
     }
 
     #[test]
-    fn test_name_priority_cli_overrides_config() {
+    fn test_parse_stdout_flag() {
         let cli = Cli::try_parse_from([
             "shclap",
             "parse",
             "--config",
-            r#"{"name":"config_name"}"#,
-            "--name",
-            "cli_name",
-            "--",
+            r#"{"name":"test"}"#,
+            "--stdout",
         ])
         .unwrap();
-
         match cli.command {
-            Commands::Parse { config, name, .. } => {
-                let cfg = Config::from_json(&config).unwrap();
-                let effective = name.as_deref().or(cfg.name.as_deref()).unwrap();
-                assert_eq!(effective, "cli_name");
+            Commands::Parse { stdout, eval, .. } => {
+                assert!(stdout);
+                assert!(!eval);
             }
             _ => panic!("Expected Parse command"),
         }
+
+        for other in ["--eval", "--output-file=out.sh", "--output-dir=/tmp"] {
+            let result = Cli::try_parse_from([
+                "shclap",
+                "parse",
+                "--config",
+                r#"{"name":"test"}"#,
+                "--stdout",
+                other,
+            ]);
+            assert!(result.is_err(), "--stdout should conflict with {}", other);
+        }
     }
 
     #[test]
-    fn test_name_priority_config_when_no_cli() {
+    fn test_parse_self_cleanup_flag() {
         let cli = Cli::try_parse_from([
             "shclap",
             "parse",
             "--config",
-            r#"{"name":"config_name"}"#,
-            "--",
+            r#"{"name":"test"}"#,
+            "--self-cleanup",
         ])
         .unwrap();
-
         match cli.command {
-            Commands::Parse { config, name, .. } => {
-                let cfg = Config::from_json(&config).unwrap();
-                let effective = name.as_deref().or(cfg.name.as_deref()).unwrap();
-                assert_eq!(effective, "config_name");
-            }
+            Commands::Parse { self_cleanup, .. } => assert!(self_cleanup),
             _ => panic!("Expected Parse command"),
         }
+
+        let result = Cli::try_parse_from([
+            "shclap",
+            "parse",
+            "--config",
+            r#"{"name":"test"}"#,
+            "--self-cleanup",
+            "--eval",
+        ]);
+        assert!(result.is_err());
     }
 }
@@ -1,12 +1,12 @@
 //! Temporary file generation with shell export statements and special outputs.
 
-use crate::config::{ArgType, Config};
+use crate::config::{ArgConfig, ArgType, Config, ValueType};
 use crate::parser::ParsedValue;
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
 
 /// Heredoc delimiter for help output.
@@ -14,6 +14,85 @@ const HELP_DELIMITER: &str = "SHCLAP_HELP";
 /// Heredoc delimiter for version output.
 const VERSION_DELIMITER: &str = "SHCLAP_VERSION";
 
+/// Line ending used between statements in generated output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// Unix line feed (`\n`)
+    #[default]
+    Lf,
+    /// Windows carriage return + line feed (`\r\n`)
+    CrLf,
+    /// The platform's preferred ending for sourced shell files, which is LF
+    /// everywhere (see [`LineEnding::platform_str`])
+    Platform,
+}
+
+impl LineEnding {
+    /// Get the characters for this line ending.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Platform => Self::platform_str(),
+        }
+    }
+
+    /// The ending `Platform` resolves to: always LF. Even on Windows the
+    /// output is sourced by bash (WSL, Git Bash), where a `\r` would end up
+    /// in the variable values.
+    fn platform_str() -> &'static str {
+        "\n"
+    }
+}
+
+/// Shell syntax the generated output targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ShellDialect {
+    /// Bash: multiple values become arrays, int values may use `declare -gix`
+    #[default]
+    Bash,
+    /// POSIX `sh`: no arrays; multiple values become `VAR_1`..`VAR_N` plus `VAR_COUNT`
+    Posix,
+    /// zsh: like bash, but arrays are declared with `typeset -g -a` since zsh
+    /// can't export them, and `unique` args add `-U`
+    Zsh,
+    /// fish: `set -gx VAR "x"`, with multiple values as a fish list
+    Fish,
+    /// PowerShell: `$env:VAR = "x"`, with multiple values joined by newlines
+    #[value(name = "powershell")]
+    PowerShell,
+}
+
+/// Options controlling how export statements are generated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputOptions {
+    /// Line ending used between statements
+    pub line_ending: LineEnding,
+    /// Wrap the statements in `set -x` / `set +x` so the shell traces each assignment
+    pub with_xtrace: bool,
+    /// Occurrence counts to emit as `<ARG>_OCCURRENCES`, keyed by arg name
+    pub occurrences: HashMap<String, usize>,
+    /// Variable names to `unset` before any export, for a clean slate when re-sourcing
+    pub unset_vars: Vec<String>,
+    /// Arg names to emit with `declare -gix` so bash enforces integer arithmetic
+    pub int_args: HashSet<String>,
+    /// Arg names whose arrays zsh output declares with `typeset -U`
+    pub unique_args: HashSet<String>,
+    /// Directory to create the output file in (default: the system temp dir)
+    pub output_dir: Option<PathBuf>,
+    /// Write to this path instead of a new temp file (see [`write_output_file`])
+    pub output_file: Option<PathBuf>,
+    /// End the temp file with a line that removes it, so sourcing cleans up;
+    /// ignored with `output_file`
+    pub self_cleanup: bool,
+    /// Subcommands to dispatch on with a trailing `case` that calls `cmd_<name>`
+    pub dispatch_subcommands: Vec<String>,
+    /// Shell syntax to emit
+    pub dialect: ShellDialect,
+    /// Reconstructed command line to record in a leading `# invoked as:` comment
+    pub invoked_as: Option<String>,
+}
+
 /// Escape a string for safe use in a shell double-quoted context.
 ///
 /// Escapes: $, `, \, ", and !
@@ -35,6 +114,66 @@ fn escape_shell_value(value: &str) -> String {
     escaped
 }
 
+/// Escape a string for safe use in a fish double-quoted context.
+///
+/// Escapes: $, \, and ". Control characters are emitted between closing and
+/// reopening quotes as fish escapes (e.g. `"a"\n"b"`), so each statement
+/// stays on one line.
+fn escape_fish_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '$' => escaped.push_str("\\$"),
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\"\\n\""),
+            '\r' => escaped.push_str("\"\\r\""),
+            '\t' => escaped.push_str("\"\\t\""),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape a string for safe use in a PowerShell double-quoted context.
+///
+/// Escapes $ and backtick with a backtick, doubles " along with the
+/// typographic quotes PowerShell also ends strings at (U+201C, U+201D,
+/// U+201E), and writes control characters as backtick escapes (`` `n ``,
+/// `` `r ``, `` `t ``).
+fn escape_powershell_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '$' => escaped.push_str("`$"),
+            '`' => escaped.push_str("``"),
+            '"' | '\u{201C}' | '\u{201D}' | '\u{201E}' => {
+                escaped.push(c);
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("`n"),
+            '\r' => escaped.push_str("`r"),
+            '\t' => escaped.push_str("`t"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The `# invoked as:` comment line for `options.invoked_as`, or an empty
+/// string. `#` starts a comment in every supported dialect, so sourcing the
+/// file is unaffected; line breaks are escaped to keep it one line.
+fn invocation_comment(options: &OutputOptions) -> String {
+    match options.invoked_as {
+        Some(ref command) => format!(
+            "# invoked as: {}{}",
+            command.replace('\n', "\\n").replace('\r', "\\r"),
+            options.line_ending.as_str()
+        ),
+        None => String::new(),
+    }
+}
+
 /// Convert an argument name to a valid shell variable name.
 ///
 /// Converts to uppercase and replaces hyphens with underscores.
@@ -51,8 +190,22 @@ pub fn generate_output(
     prefix: &str,
     subcommand: Option<&str>,
 ) -> Result<PathBuf> {
-    let content = generate_output_string(parsed, prefix, subcommand);
-    write_temp_file(&content)
+    generate_output_with_options(parsed, prefix, subcommand, &OutputOptions::default())
+}
+
+/// Generate a temporary file with shell export statements, using `options`.
+pub fn generate_output_with_options(
+    parsed: &HashMap<String, ParsedValue>,
+    prefix: &str,
+    subcommand: Option<&str>,
+    options: &OutputOptions,
+) -> Result<PathBuf> {
+    let content = generate_output_string_with_options(parsed, prefix, subcommand, options);
+    match options.output_file.as_deref() {
+        Some(path) => write_output_file(&content, path),
+        None if options.self_cleanup => write_self_cleaning_temp_file(&content, options),
+        None => write_temp_file(&content, options.output_dir.as_deref()),
+    }
 }
 
 /// Generate the output content as a string (for testing).
@@ -61,14 +214,304 @@ pub fn generate_output_string(
     prefix: &str,
     subcommand: Option<&str>,
 ) -> String {
-    let mut output = String::new();
+    generate_output_string_with_options(parsed, prefix, subcommand, &OutputOptions::default())
+}
+
+/// Generate the output content as a string, using `options`.
+///
+/// Every dialect goes through the same steps; only the statements differ
+/// (see `push_scalar`, `push_list` and the other per-dialect helpers).
+pub fn generate_output_string_with_options(
+    parsed: &HashMap<String, ParsedValue>,
+    prefix: &str,
+    subcommand: Option<&str>,
+    options: &OutputOptions,
+) -> String {
+    let eol = options.line_ending.as_str();
+    let mut output = invocation_comment(options);
+
+    for var in &options.unset_vars {
+        output.push_str(&unset_statement(options.dialect, var));
+        output.push_str(eol);
+    }
+
+    if options.with_xtrace {
+        output.push_str(xtrace_statements(options.dialect).0);
+        output.push_str(eol);
+    }
 
     // Output subcommand first if present
     if let Some(subcmd) = subcommand {
+        let var_name = format!("{}SUBCOMMAND", prefix);
+        push_scalar(&mut output, options, &var_name, subcmd, false);
+    }
+
+    // Sort keys for deterministic output
+    let mut keys: Vec<_> = parsed.keys().collect();
+    keys.sort();
+
+    for name in keys {
+        let var_name = format!("{}{}", prefix, to_shell_var_name(name));
+
+        match &parsed[name] {
+            ParsedValue::Single(s) => {
+                let int = options.int_args.contains(name);
+                push_scalar(&mut output, options, &var_name, s, int);
+            }
+            ParsedValue::Multiple(values) => {
+                push_list(&mut output, options, name, &var_name, values);
+            }
+            ParsedValue::Grouped(groups) => {
+                // Shells have no nested lists: emit the flattened values, the
+                // group count, and one list per group (VAR_0, VAR_1, ...)
+                push_list(&mut output, options, name, &var_name, &groups.concat());
+                let count = groups.len().to_string();
+                push_scalar(
+                    &mut output,
+                    options,
+                    &format!("{}_GROUPS", var_name),
+                    &count,
+                    false,
+                );
+                for (i, group) in groups.iter().enumerate() {
+                    let group_var = format!("{}_{}", var_name, i);
+                    push_list(&mut output, options, name, &group_var, group);
+                }
+            }
+        }
+
+        if let Some(count) = options.occurrences.get(name) {
+            let count_var = format!("{}_OCCURRENCES", var_name);
+            push_scalar(&mut output, options, &count_var, &count.to_string(), false);
+        }
+    }
+
+    if options.with_xtrace {
+        output.push_str(xtrace_statements(options.dialect).1);
+        output.push_str(eol);
+    }
+
+    for line in dialect_dispatch_lines(options.dialect, &options.dispatch_subcommands, prefix) {
+        output.push_str(&line);
+        output.push_str(eol);
+    }
+
+    output
+}
+
+/// Append a statement setting `var_name` to one value. With `int`, bash
+/// uses `declare -gix`: -g keeps it global when sourced inside a function,
+/// and -x keeps it exported, as with the plain form.
+fn push_scalar(
+    output: &mut String,
+    options: &OutputOptions,
+    var_name: &str,
+    value: &str,
+    int: bool,
+) {
+    let statement = match options.dialect {
+        ShellDialect::Bash if int => {
+            format!(
+                "declare -gix {}=\"{}\"",
+                var_name,
+                escape_shell_value(value)
+            )
+        }
+        ShellDialect::Bash | ShellDialect::Posix | ShellDialect::Zsh => {
+            format!("export {}=\"{}\"", var_name, escape_shell_value(value))
+        }
+        ShellDialect::Fish => format!("set -gx {} \"{}\"", var_name, escape_fish_value(value)),
+        ShellDialect::PowerShell => {
+            format!("$env:{} = \"{}\"", var_name, escape_powershell_value(value))
+        }
+    };
+    output.push_str(&statement);
+    output.push_str(options.line_ending.as_str());
+}
+
+/// Append the statements setting `var_name` to the values of arg `name`: a
+/// bash or zsh array, `VAR_COUNT` plus `VAR_1`..`VAR_N` for POSIX `sh`, a
+/// fish list, or for PowerShell, whose environment variables only hold
+/// strings, the values joined with newlines (split them with `-split "`n"`).
+fn push_list(
+    output: &mut String,
+    options: &OutputOptions,
+    name: &str,
+    var_name: &str,
+    values: &[String],
+) {
+    let eol = options.line_ending.as_str();
+    match options.dialect {
+        ShellDialect::Posix => push_posix_list(output, var_name, values, eol),
+        ShellDialect::Fish => {
+            let quoted: String = values
+                .iter()
+                .map(|v| format!(" \"{}\"", escape_fish_value(v)))
+                .collect();
+            output.push_str(&format!("set -gx {}{}{}", var_name, quoted, eol));
+        }
+        ShellDialect::PowerShell => {
+            let escaped: Vec<String> = values.iter().map(|v| escape_powershell_value(v)).collect();
+            output.push_str(&format!(
+                "$env:{} = \"{}\"{}",
+                var_name,
+                escaped.join("`n"),
+                eol
+            ));
+        }
+        ShellDialect::Bash | ShellDialect::Zsh => {
+            let quoted: Vec<String> = values
+                .iter()
+                .map(|v| format!("\"{}\"", escape_shell_value(v)))
+                .collect();
+            output.push_str(&format!(
+                "{} {}=({}){}",
+                array_keyword(options, name),
+                var_name,
+                quoted.join(" "),
+                eol
+            ));
+        }
+    }
+}
+
+/// The statement that removes `var` in `dialect`.
+fn unset_statement(dialect: ShellDialect, var: &str) -> String {
+    match dialect {
+        ShellDialect::Bash | ShellDialect::Posix | ShellDialect::Zsh => format!("unset {}", var),
+        ShellDialect::Fish => format!("set -e {}", var),
+        ShellDialect::PowerShell => {
+            format!("Remove-Item Env:{} -ErrorAction SilentlyContinue", var)
+        }
+    }
+}
+
+/// The statements that turn command tracing on and off in `dialect`.
+fn xtrace_statements(dialect: ShellDialect) -> (&'static str, &'static str) {
+    match dialect {
+        ShellDialect::Bash | ShellDialect::Posix | ShellDialect::Zsh => ("set -x", "set +x"),
+        ShellDialect::Fish => ("set fish_trace 1", "set -e fish_trace"),
+        ShellDialect::PowerShell => ("Set-PSDebug -Trace 1", "Set-PSDebug -Off"),
+    }
+}
+
+/// Lines that call `cmd_<name>` for the matched subcommand in `dialect`: a
+/// `case` for POSIX-style shells, a `switch` for fish and PowerShell.
+fn dialect_dispatch_lines(
+    dialect: ShellDialect,
+    subcommands: &[String],
+    prefix: &str,
+) -> Vec<String> {
+    match dialect {
+        ShellDialect::Bash | ShellDialect::Posix | ShellDialect::Zsh => {
+            dispatch_lines(subcommands, prefix)
+        }
+        _ if subcommands.is_empty() => Vec::new(),
+        ShellDialect::Fish => {
+            let mut lines = vec![format!("switch \"${}SUBCOMMAND\"", prefix)];
+            for name in subcommands {
+                lines.push(format!("    case \"{}\"", escape_fish_value(name)));
+                lines.push(format!("        cmd_{}", dispatch_function(name)));
+            }
+            lines.push("end".to_string());
+            lines
+        }
+        ShellDialect::PowerShell => {
+            let mut lines = vec![format!("switch ($env:{}SUBCOMMAND) {{", prefix)];
+            for name in subcommands {
+                lines.push(format!(
+                    "    \"{}\" {{ cmd_{} }}",
+                    escape_powershell_value(name),
+                    dispatch_function(name)
+                ));
+            }
+            lines.push("}".to_string());
+            lines
+        }
+    }
+}
+
+/// Keyword that declares the array for `name`: `export` for bash, and for zsh,
+/// which can't export arrays, a global `typeset -g -a` (`-U` for unique args).
+fn array_keyword(options: &OutputOptions, name: &str) -> &'static str {
+    match options.dialect {
+        ShellDialect::Zsh if options.unique_args.contains(name) => "typeset -g -U -a",
+        ShellDialect::Zsh => "typeset -g -a",
+        _ => "export",
+    }
+}
+
+/// Append a list without arrays, for POSIX `sh`: `VAR_COUNT` followed by
+/// `VAR_1` through `VAR_N`.
+fn push_posix_list(output: &mut String, var_name: &str, values: &[String], eol: &str) {
+    output.push_str(&format!(
+        "export {}_COUNT=\"{}\"{}",
+        var_name,
+        values.len(),
+        eol
+    ));
+    for (i, value) in values.iter().enumerate() {
         output.push_str(&format!(
-            "export {}SUBCOMMAND=\"{}\"\n",
+            "export {}_{}=\"{}\"{}",
+            var_name,
+            i + 1,
+            escape_shell_value(value),
+            eol
+        ));
+    }
+}
+
+/// Generate a `case` statement on `{prefix}SUBCOMMAND` that calls
+/// `cmd_<name>` for each subcommand (hyphens in names become underscores).
+///
+/// Returns an empty string when there are no subcommands.
+pub fn generate_dispatch(subcommands: &[String], prefix: &str) -> String {
+    dispatch_lines(subcommands, prefix).join("\n")
+}
+
+/// Lines of the dispatch `case` statement; see `generate_dispatch`.
+fn dispatch_lines(subcommands: &[String], prefix: &str) -> Vec<String> {
+    if subcommands.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lines = vec![format!("case \"${{{}SUBCOMMAND:-}}\" in", prefix)];
+    for name in subcommands {
+        lines.push(format!(
+            "    \"{}\") cmd_{} ;;",
+            escape_shell_value(name),
+            dispatch_function(name)
+        ));
+    }
+    lines.push("esac".to_string());
+    lines
+}
+
+/// Suffix of the `cmd_<name>` function a subcommand dispatches to.
+fn dispatch_function(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Generate the output as a string that is safe to pass to `eval`.
+///
+/// Unlike `generate_output_string`, values never span multiple lines: control
+/// characters are emitted as ANSI-C quoted segments (e.g. `"a"$'\n'"b"`),
+/// arrays use plain `VAR=(...)` assignment, and there is no trailing newline.
+pub fn generate_eval_string(
+    parsed: &HashMap<String, ParsedValue>,
+    prefix: &str,
+    subcommand: Option<&str>,
+) -> String {
+    let mut statements = Vec::new();
+
+    // Output subcommand first if present
+    if let Some(subcmd) = subcommand {
+        statements.push(format!(
+            "export {}SUBCOMMAND={}",
             prefix,
-            escape_shell_value(subcmd)
+            quote_eval_value(subcmd)
         ));
     }
 
@@ -77,33 +520,182 @@ pub fn generate_output_string(
     keys.sort();
 
     for name in keys {
-        let value = &parsed[name];
         let var_name = format!("{}{}", prefix, to_shell_var_name(name));
 
-        match value {
+        match &parsed[name] {
             ParsedValue::Single(s) => {
-                let escaped_value = escape_shell_value(s);
-                output.push_str(&format!("export {}=\"{}\"\n", var_name, escaped_value));
+                statements.push(format!("export {}={}", var_name, quote_eval_value(s)));
             }
             ParsedValue::Multiple(values) => {
-                // Output as bash array: export VAR=("val1" "val2" "val3")
-                let escaped: Vec<String> = values
-                    .iter()
-                    .map(|v| format!("\"{}\"", escape_shell_value(v)))
-                    .collect();
-                output.push_str(&format!("export {}=({})\n", var_name, escaped.join(" ")));
+                let quoted: Vec<String> = values.iter().map(|v| quote_eval_value(v)).collect();
+                statements.push(format!("{}=({})", var_name, quoted.join(" ")));
+            }
+            ParsedValue::Grouped(groups) => {
+                let quote = |values: &[String]| -> String {
+                    values
+                        .iter()
+                        .map(|v| quote_eval_value(v))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+                statements.push(format!("{}=({})", var_name, quote(&groups.concat())));
+                statements.push(format!("export {}_GROUPS=\"{}\"", var_name, groups.len()));
+                for (i, group) in groups.iter().enumerate() {
+                    statements.push(format!("{}_{}=({})", var_name, i, quote(group)));
+                }
             }
         }
     }
 
-    output
+    statements.join("\n")
+}
+
+/// Generate the parsed values as a TOML table keyed by arg name.
+///
+/// Single values become strings, multiple values become arrays (grouped
+/// values become arrays of arrays), and a matched subcommand is stored under
+/// the top-level `subcommand` key.
+pub fn generate_toml_string(
+    parsed: &HashMap<String, ParsedValue>,
+    subcommand: Option<&str>,
+) -> Result<String> {
+    let strings = |values: &[String]| -> toml::Value {
+        toml::Value::Array(values.iter().cloned().map(toml::Value::String).collect())
+    };
+
+    let mut table = toml::Table::new();
+    if let Some(subcmd) = subcommand {
+        table.insert(
+            "subcommand".to_string(),
+            toml::Value::String(subcmd.to_string()),
+        );
+    }
+    for (name, value) in parsed {
+        let value = match value {
+            ParsedValue::Single(s) => toml::Value::String(s.clone()),
+            ParsedValue::Multiple(values) => strings(values),
+            ParsedValue::Grouped(groups) => {
+                toml::Value::Array(groups.iter().map(|group| strings(group)).collect())
+            }
+        };
+        table.insert(name.clone(), value);
+    }
+
+    Ok(toml::to_string(&table)?)
+}
+
+/// Generate a one-key TOML document holding help, version or error text,
+/// which `--format toml` prints in place of a shell script.
+pub fn generate_toml_message(key: &str, text: &str) -> String {
+    format!("{} = {}\n", key, toml::Value::String(text.to_string()))
+}
+
+/// Generate the parsed values as a JSON object keyed by arg name.
+///
+/// Values keep the type the config declares: `int` and `double` values
+/// become JSON numbers, `bool` values and plain flags become booleans, and
+/// counting flags become numbers; anything that doesn't parse stays a string.
+/// Args are looked up in the matched subcommands first (innermost first), then
+/// at the top level.
+/// Multiple values become arrays (grouped values arrays of arrays), and a
+/// matched subcommand is stored under the `subcommand` key.
+pub fn generate_json_output_string(
+    config: &Config,
+    parsed: &HashMap<String, ParsedValue>,
+    subcommand: Option<&str>,
+) -> String {
+    let chain = subcommand
+        .and_then(|path| config.subcommand_chain(path))
+        .unwrap_or_default();
+
+    let mut object = serde_json::Map::new();
+    if let Some(subcmd) = subcommand {
+        object.insert(
+            "subcommand".to_string(),
+            serde_json::Value::String(subcmd.to_string()),
+        );
+    }
+    for (name, value) in parsed {
+        let arg = chain
+            .iter()
+            .rev()
+            .flat_map(|subcmd| &subcmd.args)
+            .chain(&config.args)
+            .find(|arg| &arg.name == name);
+        let coerce = |values: &[String]| -> serde_json::Value {
+            values.iter().map(|v| json_typed_value(arg, v)).collect()
+        };
+        let value = match value {
+            ParsedValue::Single(s) => json_typed_value(arg, s),
+            ParsedValue::Multiple(values) => coerce(values),
+            ParsedValue::Grouped(groups) => groups.iter().map(|group| coerce(group)).collect(),
+        };
+        object.insert(name.clone(), value);
+    }
+
+    serde_json::to_string_pretty(&object).expect("a JSON map always serializes")
+}
+
+/// Generate a one-key JSON object holding help, version or error text,
+/// which `--format json` prints in place of a shell script.
+pub fn generate_json_message(key: &str, text: &str) -> String {
+    serde_json::to_string_pretty(&serde_json::json!({ key: text }))
+        .expect("a JSON map always serializes")
+}
+
+/// Convert one value to JSON according to its arg's type, falling back to a string.
+fn json_typed_value(arg: Option<&ArgConfig>, value: &str) -> serde_json::Value {
+    let string = || serde_json::Value::String(value.to_string());
+    let Some(arg) = arg else {
+        return string();
+    };
+    let as_bool = || value.parse::<bool>().ok().map(serde_json::Value::Bool);
+    let as_int = || value.parse::<i64>().ok().map(serde_json::Value::from);
+
+    let typed = match (&arg.arg_type, &arg.value_type) {
+        (ArgType::Flag, _) if arg.multiple => as_int(),
+        (ArgType::Flag, _) => as_bool(),
+        // With emit_index the value is the position of the choice
+        _ if arg.emit_index => as_int(),
+        (_, ValueType::Int) => as_int(),
+        (_, ValueType::Double) => value
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(serde_json::Value::Number),
+        (_, ValueType::Bool) => as_bool(),
+        _ => None,
+    };
+    typed.unwrap_or_else(string)
+}
+
+/// Quote a value as a single shell word for `eval` output.
+///
+/// Printable text is double-quoted with `$`, `` ` ``, `\` and `"` escaped;
+/// newlines, carriage returns and tabs become `$'\n'`, `$'\r'` and `$'\t'`.
+fn quote_eval_value(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '$' | '`' | '\\' | '"' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\"$'\\n'\""),
+            '\r' => quoted.push_str("\"$'\\r'\""),
+            '\t' => quoted.push_str("\"$'\\t'\""),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
 }
 
 /// Generate output using legacy HashMap<String, String> format.
 /// For backward compatibility with existing code.
 pub fn generate_output_legacy(parsed: &HashMap<String, String>, prefix: &str) -> Result<PathBuf> {
     let content = generate_output_string_legacy(parsed, prefix);
-    write_temp_file(&content)
+    write_temp_file(&content, None)
 }
 
 /// Generate the output content as a string using legacy format (for testing).
@@ -128,49 +720,100 @@ pub fn generate_output_string_legacy(parsed: &HashMap<String, String>, prefix: &
 ///
 /// When sourced, the file will print the error message to stderr and exit 1.
 pub fn generate_error_output(message: &str) -> Result<PathBuf> {
+    generate_error_output_in(message, None)
+}
+
+/// Generate an error output file in `dir` (default: the system temp dir).
+pub fn generate_error_output_in(message: &str, dir: Option<&Path>) -> Result<PathBuf> {
     let content = generate_error_string(message);
-    write_temp_file(&content)
+    write_temp_file(&content, dir)
 }
 
 /// Generate an error output as a string (for testing).
 pub fn generate_error_string(message: &str) -> String {
-    // Escape the message for safe use in double quotes
-    let escaped = escape_shell_value(message);
-    format!("echo \"shclap: {}\" >&2\nexit 1\n", escaped)
+    generate_error_string_for(message, ShellDialect::Bash)
+}
+
+/// Generate an error output as a string in `dialect`'s syntax.
+pub fn generate_error_string_for(message: &str, dialect: ShellDialect) -> String {
+    match dialect {
+        ShellDialect::Fish => format!(
+            "echo \"shclap: {}\" >&2\nexit 1\n",
+            escape_fish_value(message)
+        ),
+        ShellDialect::PowerShell => format!(
+            "[Console]::Error.WriteLine(\"shclap: {}\")\nexit 1\n",
+            escape_powershell_value(message)
+        ),
+        _ => format!(
+            "echo \"shclap: {}\" >&2\nexit 1\n",
+            escape_shell_value(message)
+        ),
+    }
 }
 
 /// Generate a help output file.
 ///
 /// When sourced, the file will print the help text and exit 0.
 pub fn generate_help_output(help_text: &str) -> Result<PathBuf> {
+    generate_help_output_in(help_text, None)
+}
+
+/// Generate a help output file in `dir` (default: the system temp dir).
+pub fn generate_help_output_in(help_text: &str, dir: Option<&Path>) -> Result<PathBuf> {
     let content = generate_help_output_string(help_text);
-    write_temp_file(&content)
+    write_temp_file(&content, dir)
 }
 
 /// Generate a help output as a string (for testing).
 pub fn generate_help_output_string(help_text: &str) -> String {
-    format!(
-        "cat <<'{delimiter}'\n{text}{delimiter}\nexit 0\n",
-        delimiter = HELP_DELIMITER,
-        text = help_text
-    )
+    generate_help_output_string_for(help_text, ShellDialect::Bash)
+}
+
+/// Generate a help output as a string in `dialect`'s syntax.
+pub fn generate_help_output_string_for(help_text: &str, dialect: ShellDialect) -> String {
+    print_text_string(help_text, HELP_DELIMITER, dialect)
 }
 
 /// Generate a version output file.
 ///
 /// When sourced, the file will print the version and exit 0.
 pub fn generate_version_output(version_text: &str) -> Result<PathBuf> {
+    generate_version_output_in(version_text, None)
+}
+
+/// Generate a version output file in `dir` (default: the system temp dir).
+pub fn generate_version_output_in(version_text: &str, dir: Option<&Path>) -> Result<PathBuf> {
     let content = generate_version_output_string(version_text);
-    write_temp_file(&content)
+    write_temp_file(&content, dir)
 }
 
 /// Generate a version output as a string (for testing).
 pub fn generate_version_output_string(version_text: &str) -> String {
-    format!(
-        "cat <<'{delimiter}'\n{text}{delimiter}\nexit 0\n",
-        delimiter = VERSION_DELIMITER,
-        text = version_text
-    )
+    generate_version_output_string_for(version_text, ShellDialect::Bash)
+}
+
+/// Generate a version output as a string in `dialect`'s syntax.
+pub fn generate_version_output_string_for(version_text: &str, dialect: ShellDialect) -> String {
+    print_text_string(version_text, VERSION_DELIMITER, dialect)
+}
+
+/// Script that prints `text` and exits 0: a quoted heredoc ending at
+/// `delimiter` for POSIX-style shells; fish and PowerShell have no heredocs,
+/// so they print one escaped string.
+fn print_text_string(text: &str, delimiter: &str, dialect: ShellDialect) -> String {
+    match dialect {
+        ShellDialect::Fish => format!("printf '%s' \"{}\"\nexit 0\n", escape_fish_value(text)),
+        ShellDialect::PowerShell => format!(
+            "[Console]::Out.Write(\"{}\")\nexit 0\n",
+            escape_powershell_value(text)
+        ),
+        _ => format!(
+            "cat <<'{delimiter}'\n{text}{delimiter}\nexit 0\n",
+            delimiter = delimiter,
+            text = text
+        ),
+    }
 }
 
 /// Generate a reconstructed command line from environment variables.
@@ -192,69 +835,131 @@ pub fn generate_print(config: &Config, name: &str, prefix: &str) -> String {
 
     // Process all args from config
     for arg in &config.args {
-        let var_name = format!("{}{}", prefix, to_shell_var_name(&arg.name));
+        let var_name = format!(
+            "{}{}",
+            prefix,
+            to_shell_var_name(config.strip_arg_name(&arg.name))
+        );
 
         if let Ok(value) = env::var(&var_name) {
-            match arg.arg_type {
-                ArgType::Flag => {
-                    // For flags, only add if value is "true" or a count > 0
-                    if value == "true" {
-                        // Use long form if available, otherwise short
-                        if let Some(ref long) = arg.long {
-                            parts.push(format!("--{}", long));
-                        } else if let Some(ref long) = arg.effective_long() {
-                            parts.push(format!("--{}", long));
-                        } else if let Some(short) = arg.short {
-                            parts.push(format!("-{}", short));
-                        }
-                    } else if let Ok(count) = value.parse::<u32>() {
-                        // Multiple flag (count)
-                        if count > 0 {
-                            if let Some(short) = arg.short {
-                                // Output as -vvv for count=3
-                                parts
-                                    .push(format!("-{}", short.to_string().repeat(count as usize)));
-                            } else if let Some(ref long) = arg.long {
-                                // Repeat the flag
-                                for _ in 0..count {
-                                    parts.push(format!("--{}", long));
-                                }
-                            } else if let Some(ref long) = arg.effective_long() {
-                                for _ in 0..count {
-                                    parts.push(format!("--{}", long));
-                                }
-                            }
-                        }
+            push_print_arg(arg, &value, &mut parts, &mut positionals);
+        }
+    }
+
+    // Add positionals at the end
+    parts.extend(positionals);
+
+    parts.join(" ")
+}
+
+/// Generate a reconstructed command line from parsed values.
+///
+/// Like [`generate_print`], but works from a parse result (keyed by arg name)
+/// instead of the environment, so it can run before the output is sourced.
+/// The subcommand path, if any, follows the top-level args, and each
+/// subcommand's args follow its name.
+pub fn generate_print_from_values(
+    config: &Config,
+    name: &str,
+    parsed: &HashMap<String, ParsedValue>,
+    subcommand: Option<&str>,
+) -> String {
+    let mut parts: Vec<String> = vec![name.to_string()];
+
+    let mut scopes: Vec<(Option<&str>, &[ArgConfig])> = vec![(None, &config.args)];
+    if let Some(chain) = subcommand.and_then(|path| config.subcommand_chain(path)) {
+        scopes.extend(
+            chain
+                .into_iter()
+                .map(|subcmd| (Some(subcmd.name.as_str()), subcmd.args.as_slice())),
+        );
+    }
+
+    for (subcmd_name, args) in scopes {
+        parts.extend(subcmd_name.map(str::to_string));
+        let mut positionals: Vec<String> = Vec::new();
+        for arg in args {
+            match parsed.get(&arg.name) {
+                Some(ParsedValue::Single(value)) => {
+                    push_print_arg(arg, value, &mut parts, &mut positionals);
+                }
+                Some(ParsedValue::Multiple(values)) => {
+                    for value in values {
+                        push_print_arg(arg, value, &mut parts, &mut positionals);
                     }
                 }
-                ArgType::Option => {
-                    if !value.is_empty() {
-                        // Use long form with = syntax
-                        if let Some(ref long) = arg.long {
-                            parts.push(format!("--{}={}", long, shell_quote(&value)));
-                        } else if let Some(ref long) = arg.effective_long() {
-                            parts.push(format!("--{}={}", long, shell_quote(&value)));
-                        } else if let Some(short) = arg.short {
-                            parts.push(format!("-{}", short));
-                            parts.push(shell_quote(&value));
-                        }
+                // Each group is one occurrence taking several values
+                Some(ParsedValue::Grouped(groups)) if arg.arg_type == ArgType::Option => {
+                    let opt = arg.long_with_prefix().or_else(|| arg.short_with_prefix());
+                    for group in groups {
+                        parts.extend(opt.clone());
+                        parts.extend(group.iter().map(|value| shell_quote(value)));
                     }
                 }
-                ArgType::Positional => {
-                    if !value.is_empty() {
-                        positionals.push(shell_quote(&value));
+                Some(ParsedValue::Grouped(groups)) => {
+                    for value in groups.concat() {
+                        push_print_arg(arg, &value, &mut parts, &mut positionals);
                     }
                 }
+                None => {}
             }
         }
+        parts.extend(positionals);
     }
 
-    // Add positionals at the end
-    parts.extend(positionals);
-
     parts.join(" ")
 }
 
+/// Add the command-line form of one `value` of `arg` to `parts`, or to
+/// `positionals` for positional args.
+fn push_print_arg(
+    arg: &ArgConfig,
+    value: &str,
+    parts: &mut Vec<String>,
+    positionals: &mut Vec<String>,
+) {
+    match arg.arg_type {
+        ArgType::Flag => {
+            // For flags, only add if value is "true" or a count > 0
+            if value == "true" {
+                // Use long form if available, otherwise short
+                if let Some(opt) = arg.long_with_prefix().or_else(|| arg.short_with_prefix()) {
+                    parts.push(opt);
+                }
+            } else if let Ok(count) = value.parse::<u32>() {
+                // Multiple flag (count)
+                if count > 0 {
+                    if let Some(short) = arg.effective_short() {
+                        // Output as -vvv for count=3
+                        parts.push(format!("-{}", short.to_string().repeat(count as usize)));
+                    } else if let Some(long) = arg.long_with_prefix() {
+                        // Repeat the flag
+                        for _ in 0..count {
+                            parts.push(long.clone());
+                        }
+                    }
+                }
+            }
+        }
+        ArgType::Option => {
+            if !value.is_empty() {
+                // Use long form with = syntax
+                if let Some(long) = arg.long_with_prefix() {
+                    parts.push(format!("{}={}", long, shell_quote(value)));
+                } else if let Some(short) = arg.short_with_prefix() {
+                    parts.push(short);
+                    parts.push(shell_quote(value));
+                }
+            }
+        }
+        ArgType::Positional => {
+            if !value.is_empty() {
+                positionals.push(shell_quote(value));
+            }
+        }
+    }
+}
+
 /// Quote a value for shell if it contains special characters.
 fn shell_quote(value: &str) -> String {
     if value.is_empty() || value.contains(|c: char| c.is_whitespace() || "\"'$`\\!".contains(c)) {
@@ -265,24 +970,127 @@ fn shell_quote(value: &str) -> String {
     }
 }
 
-/// Write content to a temporary file and return its path.
-fn write_temp_file(content: &str) -> Result<PathBuf> {
-    let mut file = NamedTempFile::new()?;
+/// Check that `dir` exists, is a directory, and accepts new files.
+pub fn validate_output_dir(dir: &Path) -> Result<()> {
+    if !dir.is_dir() {
+        anyhow::bail!("output directory '{}' does not exist", dir.display());
+    }
+    // Probe with an anonymous file, which is removed as soon as it is dropped
+    tempfile::tempfile_in(dir).map_err(|e| {
+        anyhow::anyhow!(
+            "output directory '{}' is not writable: {}",
+            dir.display(),
+            e
+        )
+    })?;
+    Ok(())
+}
+
+/// Write content to a temporary file in `dir` (default: the system temp dir)
+/// and return its path.
+pub fn write_temp_file(content: &str, dir: Option<&Path>) -> Result<PathBuf> {
+    let mut file = create_temp_file(dir)?;
     file.write_all(content.as_bytes())?;
     let path = file.into_temp_path().keep()?;
     Ok(path)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Like `write_temp_file`, but end the content with a line that removes the
+/// file, so it is gone once sourced.
+fn write_self_cleaning_temp_file(content: &str, options: &OutputOptions) -> Result<PathBuf> {
+    let mut file = create_temp_file(options.output_dir.as_deref())?;
+    let cleanup = cleanup_line(file.path(), options.dialect);
+    file.write_all(content.as_bytes())?;
+    file.write_all(cleanup.as_bytes())?;
+    file.write_all(options.line_ending.as_str().as_bytes())?;
+    let path = file.into_temp_path().keep()?;
+    Ok(path)
+}
 
-    fn make_map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
-        pairs
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect()
-    }
+fn create_temp_file(dir: Option<&Path>) -> Result<NamedTempFile> {
+    Ok(match dir {
+        Some(dir) => NamedTempFile::new_in(dir)?,
+        None => NamedTempFile::new()?,
+    })
+}
+
+/// The statement that removes the file at `path` in `dialect`.
+///
+/// The path comes from the output dir or `$TMPDIR`, so it is always single
+/// quoted: `;`, `|` or a glob in it must never reach the shell unquoted.
+fn cleanup_line(path: &Path, dialect: ShellDialect) -> String {
+    let path = path.to_string_lossy();
+    match dialect {
+        // PowerShell also ends single-quoted strings at U+2018..U+201B
+        ShellDialect::PowerShell => {
+            let mut quoted = String::with_capacity(path.len());
+            for c in path.chars() {
+                if matches!(c, '\'' | '\u{2018}'..='\u{201B}') {
+                    quoted.push(c);
+                }
+                quoted.push(c);
+            }
+            format!(
+                "Remove-Item -LiteralPath '{}' -ErrorAction SilentlyContinue",
+                quoted
+            )
+        }
+        // In fish single quotes, \\ and \' are the only escapes
+        ShellDialect::Fish => format!(
+            "rm -f -- '{}'",
+            path.replace('\\', "\\\\").replace('\'', "\\'")
+        ),
+        ShellDialect::Bash | ShellDialect::Posix | ShellDialect::Zsh => {
+            format!("rm -f -- '{}'", path.replace('\'', "'\\''"))
+        }
+    }
+}
+
+/// Write content to `path` and return it.
+///
+/// A FIFO is opened and written directly, so the open blocks until a reader
+/// opens the other end, and the write returns once the reader has taken all
+/// of it. Anything else is written to a temp file in the same directory and
+/// renamed over `path`, so a reader never sees a partial file.
+pub fn write_output_file(content: &str, path: &Path) -> Result<PathBuf> {
+    if is_fifo(path) {
+        let mut fifo = std::fs::OpenOptions::new().write(true).open(path)?;
+        fifo.write_all(content.as_bytes())?;
+        fifo.flush()?;
+        return Ok(path.to_path_buf());
+    }
+
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(content.as_bytes())?;
+    file.persist(path)?;
+    Ok(path.to_path_buf())
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
 
     fn make_parsed_map(pairs: &[(&str, ParsedValue)]) -> HashMap<String, ParsedValue> {
         pairs
@@ -356,6 +1164,30 @@ mod tests {
         assert!(output.contains("export MYAPP_NAME=\"test\""));
     }
 
+    #[test]
+    fn test_custom_prefix_with_subcommand() {
+        let parsed = make_parsed_map(&[("force", ParsedValue::Single("true".to_string()))]);
+        let output = generate_output_string(&parsed, "MYAPP_", Some("init"));
+
+        assert_eq!(
+            output,
+            "export MYAPP_SUBCOMMAND=\"init\"\nexport MYAPP_FORCE=\"true\"\n"
+        );
+        assert!(generate_eval_string(&parsed, "MYAPP_", Some("init"))
+            .contains("export MYAPP_SUBCOMMAND=\"init\""));
+    }
+
+    #[test]
+    fn test_empty_prefix_with_subcommand() {
+        let parsed = make_parsed_map(&[("force", ParsedValue::Single("true".to_string()))]);
+        let output = generate_output_string(&parsed, "", Some("init"));
+
+        assert_eq!(
+            output,
+            "export SUBCOMMAND=\"init\"\nexport FORCE=\"true\"\n"
+        );
+    }
+
     #[test]
     fn test_empty_value() {
         let parsed = make_map(&[("empty", "")]);
@@ -433,6 +1265,26 @@ mod tests {
         assert!(output.contains("export SHCLAP_FILES=(\"a.txt\" \"b.txt\" \"c.txt\")"));
     }
 
+    #[test]
+    fn test_grouped_values_output() {
+        let parsed = make_parsed_map(&[(
+            "coord",
+            ParsedValue::Grouped(vec![
+                vec!["1".to_string(), "2".to_string()],
+                vec!["3".to_string(), "4".to_string()],
+            ]),
+        )]);
+        let output = generate_output_string(&parsed, "SHCLAP_", None);
+
+        assert_eq!(
+            output,
+            "export SHCLAP_COORD=(\"1\" \"2\" \"3\" \"4\")\n\
+             export SHCLAP_COORD_GROUPS=\"2\"\n\
+             export SHCLAP_COORD_0=(\"1\" \"2\")\n\
+             export SHCLAP_COORD_1=(\"3\" \"4\")\n"
+        );
+    }
+
     #[test]
     fn test_multiple_values_with_escaping() {
         let parsed = make_parsed_map(&[(
@@ -482,6 +1334,207 @@ mod tests {
         assert!(output.contains("export SHCLAP_FILES=(\"a.txt\" \"b.txt\")"));
     }
 
+    #[test]
+    fn test_xtrace_wraps_output() {
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            with_xtrace: true,
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", Some("run"), &options);
+
+        assert_eq!(
+            output,
+            "set -x\nexport SHCLAP_SUBCOMMAND=\"run\"\nexport SHCLAP_VERBOSE=\"true\"\nset +x\n"
+        );
+    }
+
+    #[test]
+    fn test_occurrences_output() {
+        let parsed = make_parsed_map(&[(
+            "point",
+            ParsedValue::Multiple(vec![
+                "1".to_string(),
+                "2".to_string(),
+                "3".to_string(),
+                "4".to_string(),
+            ]),
+        )]);
+        let options = OutputOptions {
+            occurrences: HashMap::from([("point".to_string(), 2)]),
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+
+        assert_eq!(
+            output,
+            "export SHCLAP_POINT=(\"1\" \"2\" \"3\" \"4\")\nexport SHCLAP_POINT_OCCURRENCES=\"2\"\n"
+        );
+    }
+
+    #[test]
+    fn test_unset_vars_precede_exports() {
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            unset_vars: vec![
+                "SHCLAP_SUBCOMMAND".to_string(),
+                "SHCLAP_VERBOSE".to_string(),
+                "SHCLAP_OUTPUT".to_string(),
+            ],
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", Some("run"), &options);
+
+        assert_eq!(
+            output,
+            "unset SHCLAP_SUBCOMMAND\nunset SHCLAP_VERBOSE\nunset SHCLAP_OUTPUT\n\
+             export SHCLAP_SUBCOMMAND=\"run\"\nexport SHCLAP_VERBOSE=\"true\"\n"
+        );
+    }
+
+    #[test]
+    fn test_int_args_use_declare() {
+        let parsed = make_parsed_map(&[
+            ("port", ParsedValue::Single("8080".to_string())),
+            ("host", ParsedValue::Single("localhost".to_string())),
+        ]);
+        let options = OutputOptions {
+            int_args: HashSet::from(["port".to_string()]),
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+
+        assert_eq!(
+            output,
+            "export SHCLAP_HOST=\"localhost\"\ndeclare -gix SHCLAP_PORT=\"8080\"\n"
+        );
+    }
+
+    #[test]
+    fn test_xtrace_off_by_default() {
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let output = generate_output_string(&parsed, "SHCLAP_", None);
+
+        assert!(!output.contains("set -x"));
+        assert!(!output.contains("set +x"));
+    }
+
+    #[test]
+    fn test_line_ending_lf() {
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            line_ending: LineEnding::Lf,
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", Some("run"), &options);
+
+        assert_eq!(
+            output,
+            "export SHCLAP_SUBCOMMAND=\"run\"\nexport SHCLAP_VERBOSE=\"true\"\n"
+        );
+        assert_eq!(
+            output,
+            generate_output_string(&parsed, "SHCLAP_", Some("run"))
+        );
+    }
+
+    #[test]
+    fn test_line_ending_crlf() {
+        let parsed = make_parsed_map(&[
+            ("verbose", ParsedValue::Single("true".to_string())),
+            (
+                "files",
+                ParsedValue::Multiple(vec!["a.txt".to_string(), "b.txt".to_string()]),
+            ),
+        ]);
+        let options = OutputOptions {
+            line_ending: LineEnding::CrLf,
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+
+        assert_eq!(
+            output,
+            "export SHCLAP_FILES=(\"a.txt\" \"b.txt\")\r\nexport SHCLAP_VERBOSE=\"true\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_line_ending_platform_is_lf() {
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            line_ending: LineEnding::Platform,
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+
+        assert_eq!(output, "export SHCLAP_VERBOSE=\"true\"\n");
+        assert!(!output.contains('\r'));
+    }
+
+    #[test]
+    fn test_line_ending_escaped_newlines_unaffected() {
+        let parsed = make_parsed_map(&[("text", ParsedValue::Single("a\nb".to_string()))]);
+        let options = OutputOptions {
+            line_ending: LineEnding::CrLf,
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+
+        assert_eq!(output, "export SHCLAP_TEXT=\"a\\nb\"\r\n");
+    }
+
+    #[test]
+    fn test_eval_string_basic() {
+        let parsed = make_parsed_map(&[
+            ("verbose", ParsedValue::Single("true".to_string())),
+            (
+                "files",
+                ParsedValue::Multiple(vec!["a.txt".to_string(), "b c.txt".to_string()]),
+            ),
+        ]);
+        let output = generate_eval_string(&parsed, "SHCLAP_", Some("run"));
+
+        assert_eq!(
+            output,
+            "export SHCLAP_SUBCOMMAND=\"run\"\n\
+             SHCLAP_FILES=(\"a.txt\" \"b c.txt\")\n\
+             export SHCLAP_VERBOSE=\"true\""
+        );
+    }
+
+    #[test]
+    fn test_eval_string_no_trailing_newline() {
+        let parsed = make_parsed_map(&[("name", ParsedValue::Single("x".to_string()))]);
+        let output = generate_eval_string(&parsed, "SHCLAP_", None);
+
+        assert!(!output.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_eval_string_escapes_newlines() {
+        let parsed = make_parsed_map(&[("text", ParsedValue::Single("a\nb\tc".to_string()))]);
+        let output = generate_eval_string(&parsed, "SHCLAP_", None);
+
+        assert_eq!(output, "export SHCLAP_TEXT=\"a\"$'\\n'\"b\"$'\\t'\"c\"");
+        // Each statement stays on one line
+        assert_eq!(output.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_eval_string_escapes_special_chars() {
+        let parsed = make_parsed_map(&[(
+            "cmd",
+            ParsedValue::Single("$(rm -rf /) `id` \"q\" \\".to_string()),
+        )]);
+        let output = generate_eval_string(&parsed, "SHCLAP_", None);
+
+        assert_eq!(
+            output,
+            "export SHCLAP_CMD=\"\\$(rm -rf /) \\`id\\` \\\"q\\\" \\\\\""
+        );
+    }
+
     #[test]
     fn test_generate_error_string() {
         let output = generate_error_string("unknown option: --foo");
@@ -518,6 +1571,17 @@ mod tests {
         assert!(output.ends_with("SHCLAP_VERSION\nexit 0\n"));
     }
 
+    #[test]
+    fn test_generate_version_output_string_extended() {
+        let version = "myapp 1.0.0\nbuild date: 2024-05-01\ngit hash: abc1234\n";
+        let output = generate_version_output_string(version);
+
+        assert_eq!(
+            output,
+            "cat <<'SHCLAP_VERSION'\nmyapp 1.0.0\nbuild date: 2024-05-01\ngit hash: abc1234\nSHCLAP_VERSION\nexit 0\n"
+        );
+    }
+
     #[test]
     fn test_generate_error_output_creates_file() {
         let path = generate_error_output("test error").unwrap();
@@ -633,4 +1697,679 @@ mod tests {
 
         assert!(result.contains("'path with spaces'"));
     }
+
+    #[test]
+    fn test_generate_print_from_values() {
+        use crate::config::Config;
+
+        let config = Config::from_json(
+            r#"{
+            "schema_version": 2,
+            "name": "myapp",
+            "args": [
+                {"name": "verbose", "short": "v", "type": "flag", "multiple": true},
+                {"name": "tag", "long": "tag", "type": "option", "multiple": true}
+            ],
+            "subcommands": [{"name": "deploy", "args": [
+                {"name": "target", "type": "positional"},
+                {"name": "dry-run", "long": "dry-run", "type": "flag"}
+            ]}]
+        }"#,
+        )
+        .unwrap();
+        let parsed = make_parsed_map(&[
+            ("verbose", ParsedValue::Single("2".to_string())),
+            (
+                "tag",
+                ParsedValue::Multiple(vec!["a".to_string(), "b c".to_string()]),
+            ),
+            ("target", ParsedValue::Single("prod".to_string())),
+            ("dry-run", ParsedValue::Single("false".to_string())),
+        ]);
+
+        assert_eq!(
+            generate_print_from_values(&config, "myapp", &parsed, Some("deploy")),
+            "myapp -vv --tag=a --tag='b c' deploy prod"
+        );
+    }
+
+    #[test]
+    fn test_invoked_as_comment_precedes_exports() {
+        let parsed = make_parsed_map(&[
+            ("verbose", ParsedValue::Single("true".to_string())),
+            ("output", ParsedValue::Single("out.txt".to_string())),
+        ]);
+        let options = OutputOptions {
+            invoked_as: Some("myapp --verbose --output=out.txt".to_string()),
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+        assert_eq!(
+            output,
+            "# invoked as: myapp --verbose --output=out.txt\n\
+             export SHCLAP_OUTPUT=\"out.txt\"\n\
+             export SHCLAP_VERBOSE=\"true\"\n"
+        );
+
+        // A quoted newline can't end the comment early
+        let options = OutputOptions {
+            invoked_as: Some("myapp --msg='a\nb'".to_string()),
+            dialect: ShellDialect::Fish,
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+        assert!(output.starts_with("# invoked as: myapp --msg='a\\nb'\nset -gx"));
+    }
+
+    #[test]
+    fn test_output_in_custom_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            output_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let path = generate_output_with_options(&parsed, "SHCLAP_", None, &options).unwrap();
+
+        assert_eq!(path.parent(), Some(dir.path()));
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "export SHCLAP_VERBOSE=\"true\"\n");
+    }
+
+    #[test]
+    fn test_error_output_in_custom_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = generate_error_output_in("bad", Some(dir.path())).unwrap();
+        assert_eq!(path.parent(), Some(dir.path()));
+    }
+
+    #[test]
+    fn test_validate_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(validate_output_dir(dir.path()).is_ok());
+
+        let missing = dir.path().join("missing");
+        let err = validate_output_dir(&missing).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+
+        // A regular file is not a directory
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        assert!(validate_output_dir(&file).is_err());
+    }
+
+    #[test]
+    fn test_toml_output_escapes_and_arrays() {
+        let parsed = make_parsed_map(&[
+            (
+                "message",
+                ParsedValue::Single("say \"hi\"\n\\ done".to_string()),
+            ),
+            (
+                "files",
+                ParsedValue::Multiple(vec!["a b.txt".to_string(), "c'd.txt".to_string()]),
+            ),
+            (
+                "coord",
+                ParsedValue::Grouped(vec![
+                    vec!["1".to_string(), "2".to_string()],
+                    vec!["3".to_string(), "4".to_string()],
+                ]),
+            ),
+        ]);
+        let output = generate_toml_string(&parsed, Some("build")).unwrap();
+
+        let table: toml::Table = toml::from_str(&output).unwrap();
+        assert_eq!(table["subcommand"].as_str(), Some("build"));
+        assert_eq!(table["message"].as_str(), Some("say \"hi\"\n\\ done"));
+        let files: Vec<_> = table["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_str().unwrap())
+            .collect();
+        assert_eq!(files, vec!["a b.txt", "c'd.txt"]);
+        assert_eq!(table["coord"].as_array().unwrap().len(), 2);
+        assert_eq!(table["coord"][1][0].as_str(), Some("3"));
+    }
+
+    #[test]
+    fn test_toml_and_json_messages() {
+        assert_eq!(
+            generate_toml_message("error", "bad \"x\""),
+            "error = \"bad \\\"x\\\"\"\n"
+        );
+        assert_eq!(
+            generate_json_message("version", "app 1.0\n"),
+            "{\n  \"version\": \"app 1.0\\n\"\n}"
+        );
+    }
+
+    #[test]
+    fn test_toml_output_without_subcommand() {
+        let parsed = make_parsed_map(&[("name", ParsedValue::Single("alice".to_string()))]);
+        let output = generate_toml_string(&parsed, None).unwrap();
+        assert_eq!(output, "name = \"alice\"\n");
+    }
+
+    #[test]
+    fn test_dispatch_branch_per_subcommand() {
+        let subcommands = vec!["init".to_string(), "run-all".to_string()];
+        let dispatch = generate_dispatch(&subcommands, "SHCLAP_");
+        assert_eq!(
+            dispatch,
+            "case \"${SHCLAP_SUBCOMMAND:-}\" in\n    \"init\") cmd_init ;;\n    \"run-all\") cmd_run_all ;;\nesac"
+        );
+    }
+
+    #[test]
+    fn test_dispatch_empty_without_subcommands() {
+        assert_eq!(generate_dispatch(&[], "SHCLAP_"), "");
+    }
+
+    #[test]
+    fn test_output_appends_dispatch_after_exports() {
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            dispatch_subcommands: vec!["init".to_string()],
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "APP_", Some("init"), &options);
+        assert!(output.ends_with(
+            "export APP_VERBOSE=\"true\"\ncase \"${APP_SUBCOMMAND:-}\" in\n    \"init\") cmd_init ;;\nesac\n"
+        ));
+    }
+
+    #[test]
+    fn test_bash_dialect_multiple_values_as_array() {
+        let parsed = make_parsed_map(&[(
+            "file",
+            ParsedValue::Multiple(vec!["a.txt".to_string(), "b c.txt".to_string()]),
+        )]);
+        let options = OutputOptions {
+            dialect: ShellDialect::Bash,
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+        assert_eq!(output, "export SHCLAP_FILE=(\"a.txt\" \"b c.txt\")\n");
+    }
+
+    #[test]
+    fn test_zsh_dialect_declares_arrays_with_typeset() {
+        let parsed = make_parsed_map(&[
+            (
+                "file",
+                ParsedValue::Multiple(vec!["a.txt".to_string(), "b.txt".to_string()]),
+            ),
+            ("tag", ParsedValue::Multiple(vec!["x".to_string()])),
+            ("name", ParsedValue::Single("demo".to_string())),
+        ]);
+        let options = OutputOptions {
+            dialect: ShellDialect::Zsh,
+            unique_args: HashSet::from(["file".to_string()]),
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+        assert_eq!(
+            output,
+            "typeset -g -U -a SHCLAP_FILE=(\"a.txt\" \"b.txt\")\n\
+             export SHCLAP_NAME=\"demo\"\n\
+             typeset -g -a SHCLAP_TAG=(\"x\")\n"
+        );
+
+        // Bash ignores unique_args: the values are already deduplicated
+        let options = OutputOptions {
+            dialect: ShellDialect::Bash,
+            ..options
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+        assert!(output.contains("export SHCLAP_FILE=(\"a.txt\" \"b.txt\")\n"));
+        assert!(!output.contains("typeset"));
+    }
+
+    #[test]
+    fn test_posix_dialect_multiple_values_as_indexed_vars() {
+        let parsed = make_parsed_map(&[
+            (
+                "file",
+                ParsedValue::Multiple(vec!["a.txt".to_string(), "b c.txt".to_string()]),
+            ),
+            ("count", ParsedValue::Single("3".to_string())),
+        ]);
+        let options = OutputOptions {
+            dialect: ShellDialect::Posix,
+            int_args: HashSet::from(["count".to_string()]),
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+        assert_eq!(
+            output,
+            "export SHCLAP_COUNT=\"3\"\n\
+             export SHCLAP_FILE_COUNT=\"2\"\n\
+             export SHCLAP_FILE_1=\"a.txt\"\n\
+             export SHCLAP_FILE_2=\"b c.txt\"\n"
+        );
+        assert!(!output.contains('('));
+    }
+
+    #[test]
+    fn test_posix_dialect_grouped_values() {
+        let parsed = make_parsed_map(&[(
+            "coord",
+            ParsedValue::Grouped(vec![
+                vec!["1".to_string(), "2".to_string()],
+                vec!["3".to_string()],
+            ]),
+        )]);
+        let options = OutputOptions {
+            dialect: ShellDialect::Posix,
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+        assert!(output.contains("export SHCLAP_COORD_COUNT=\"3\"\n"));
+        assert!(output.contains("export SHCLAP_COORD_3=\"3\"\n"));
+        assert!(output.contains("export SHCLAP_COORD_GROUPS=\"2\"\n"));
+        assert!(output.contains("export SHCLAP_COORD_0_COUNT=\"2\"\n"));
+        assert!(output.contains("export SHCLAP_COORD_1_1=\"3\"\n"));
+        assert!(!output.contains('('));
+    }
+
+    #[test]
+    fn test_output_file_replaces_regular_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("out.sh");
+        std::fs::write(&target, "stale").unwrap();
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            output_file: Some(target.clone()),
+            ..Default::default()
+        };
+        let path = generate_output_with_options(&parsed, "SHCLAP_", None, &options).unwrap();
+
+        assert_eq!(path, target);
+        let content = std::fs::read_to_string(&target).unwrap();
+        assert_eq!(content, "export SHCLAP_VERBOSE=\"true\"\n");
+        // Only the target is left behind, no temp files
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_file_writes_to_fifo() {
+        let dir = tempfile::tempdir().unwrap();
+        let fifo = dir.path().join("out.fifo");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let reader = {
+            let fifo = fifo.clone();
+            std::thread::spawn(move || std::fs::read_to_string(fifo).unwrap())
+        };
+        let path = write_output_file("export SHCLAP_VERBOSE=\"true\"\n", &fifo).unwrap();
+
+        assert_eq!(path, fifo);
+        assert_eq!(reader.join().unwrap(), "export SHCLAP_VERBOSE=\"true\"\n");
+        assert!(is_fifo(&fifo), "the FIFO must not be replaced");
+    }
+
+    fn fish_output(parsed: &HashMap<String, ParsedValue>, subcommand: Option<&str>) -> String {
+        let options = OutputOptions {
+            dialect: ShellDialect::Fish,
+            ..Default::default()
+        };
+        generate_output_string_with_options(parsed, "SHCLAP_", subcommand, &options)
+    }
+
+    #[test]
+    fn test_fish_single_value_output() {
+        let parsed = make_parsed_map(&[("output", ParsedValue::Single("file.txt".to_string()))]);
+        assert_eq!(
+            fish_output(&parsed, None),
+            "set -gx SHCLAP_OUTPUT \"file.txt\"\n"
+        );
+    }
+
+    #[test]
+    fn test_fish_multiple_values_list_output() {
+        let parsed = make_parsed_map(&[(
+            "files",
+            ParsedValue::Multiple(vec!["a.txt".to_string(), "b c.txt".to_string()]),
+        )]);
+        assert_eq!(
+            fish_output(&parsed, None),
+            "set -gx SHCLAP_FILES \"a.txt\" \"b c.txt\"\n"
+        );
+    }
+
+    #[test]
+    fn test_fish_grouped_values_output() {
+        let parsed = make_parsed_map(&[(
+            "coord",
+            ParsedValue::Grouped(vec![
+                vec!["1".to_string(), "2".to_string()],
+                vec!["3".to_string()],
+            ]),
+        )]);
+        assert_eq!(
+            fish_output(&parsed, None),
+            "set -gx SHCLAP_COORD \"1\" \"2\" \"3\"\n\
+             set -gx SHCLAP_COORD_GROUPS \"2\"\n\
+             set -gx SHCLAP_COORD_0 \"1\" \"2\"\n\
+             set -gx SHCLAP_COORD_1 \"3\"\n"
+        );
+    }
+
+    #[test]
+    fn test_fish_escaping() {
+        assert_eq!(escape_fish_value("path with spaces"), "path with spaces");
+        assert_eq!(escape_fish_value("$HOME"), "\\$HOME");
+        assert_eq!(escape_fish_value("say \"hi\""), "say \\\"hi\\\"");
+        assert_eq!(escape_fish_value("C:\\dir"), "C:\\\\dir");
+        assert_eq!(escape_fish_value("a\nb"), "a\"\\n\"b");
+        // Backticks and ! are not special in fish
+        assert_eq!(escape_fish_value("`cmd` !"), "`cmd` !");
+
+        let parsed =
+            make_parsed_map(&[("msg", ParsedValue::Single("it's \"$5\" total".to_string()))]);
+        assert_eq!(
+            fish_output(&parsed, None),
+            "set -gx SHCLAP_MSG \"it's \\\"\\$5\\\" total\"\n"
+        );
+    }
+
+    #[test]
+    fn test_fish_subcommand_first_in_output() {
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        assert_eq!(
+            fish_output(&parsed, Some("deploy")),
+            "set -gx SHCLAP_SUBCOMMAND \"deploy\"\nset -gx SHCLAP_VERBOSE \"true\"\n"
+        );
+    }
+
+    #[test]
+    fn test_fish_unset_xtrace_and_dispatch() {
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            dialect: ShellDialect::Fish,
+            with_xtrace: true,
+            unset_vars: vec!["SHCLAP_VERBOSE".to_string()],
+            dispatch_subcommands: vec!["run-all".to_string()],
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+        assert_eq!(
+            output,
+            "set -e SHCLAP_VERBOSE\n\
+             set fish_trace 1\n\
+             set -gx SHCLAP_VERBOSE \"true\"\n\
+             set -e fish_trace\n\
+             switch \"$SHCLAP_SUBCOMMAND\"\n\
+             \x20   case \"run-all\"\n\
+             \x20       cmd_run_all\n\
+             end\n"
+        );
+    }
+
+    #[test]
+    fn test_fish_help_version_and_error_output() {
+        // fish has no heredocs, so the text is printed as one escaped string
+        assert_eq!(
+            generate_help_output_string_for("Usage: $app \"x\"\n", ShellDialect::Fish),
+            "printf '%s' \"Usage: \\$app \\\"x\\\"\"\\n\"\"\nexit 0\n"
+        );
+        assert_eq!(
+            generate_version_output_string_for("myapp 1.0.0\n", ShellDialect::Fish),
+            "printf '%s' \"myapp 1.0.0\"\\n\"\"\nexit 0\n"
+        );
+        assert_eq!(
+            generate_error_string_for("bad $value", ShellDialect::Fish),
+            "echo \"shclap: bad \\$value\" >&2\nexit 1\n"
+        );
+    }
+
+    #[test]
+    fn test_powershell_help_version_and_error_output() {
+        assert_eq!(
+            generate_help_output_string_for("Usage: $app \"x\"\n", ShellDialect::PowerShell),
+            "[Console]::Out.Write(\"Usage: `$app \"\"x\"\"`n\")\nexit 0\n"
+        );
+        assert_eq!(
+            generate_version_output_string_for("myapp 1.0.0\n", ShellDialect::PowerShell),
+            "[Console]::Out.Write(\"myapp 1.0.0`n\")\nexit 0\n"
+        );
+        assert_eq!(
+            generate_error_string_for("bad $value", ShellDialect::PowerShell),
+            "[Console]::Error.WriteLine(\"shclap: bad `$value\")\nexit 1\n"
+        );
+    }
+
+    fn powershell_output(
+        parsed: &HashMap<String, ParsedValue>,
+        subcommand: Option<&str>,
+    ) -> String {
+        let options = OutputOptions {
+            dialect: ShellDialect::PowerShell,
+            ..Default::default()
+        };
+        generate_output_string_with_options(parsed, "SHCLAP_", subcommand, &options)
+    }
+
+    #[test]
+    fn test_powershell_single_value_output() {
+        let parsed = make_parsed_map(&[("output", ParsedValue::Single("file.txt".to_string()))]);
+        assert_eq!(
+            powershell_output(&parsed, Some("build")),
+            "$env:SHCLAP_SUBCOMMAND = \"build\"\n$env:SHCLAP_OUTPUT = \"file.txt\"\n"
+        );
+    }
+
+    #[test]
+    fn test_powershell_multiple_values_output() {
+        let parsed = make_parsed_map(&[(
+            "files",
+            ParsedValue::Multiple(vec!["a.txt".to_string(), "b c.txt".to_string()]),
+        )]);
+        assert_eq!(
+            powershell_output(&parsed, None),
+            "$env:SHCLAP_FILES = \"a.txt`nb c.txt\"\n"
+        );
+    }
+
+    #[test]
+    fn test_powershell_grouped_values_output() {
+        let parsed = make_parsed_map(&[(
+            "coord",
+            ParsedValue::Grouped(vec![
+                vec!["1".to_string(), "2".to_string()],
+                vec!["3".to_string()],
+            ]),
+        )]);
+        assert_eq!(
+            powershell_output(&parsed, None),
+            "$env:SHCLAP_COORD = \"1`n2`n3\"\n\
+             $env:SHCLAP_COORD_GROUPS = \"2\"\n\
+             $env:SHCLAP_COORD_0 = \"1`n2\"\n\
+             $env:SHCLAP_COORD_1 = \"3\"\n"
+        );
+    }
+
+    #[test]
+    fn test_powershell_escaping() {
+        assert_eq!(escape_powershell_value("$HOME"), "`$HOME");
+        assert_eq!(escape_powershell_value("`cmd`"), "``cmd``");
+        assert_eq!(escape_powershell_value("say \"hi\""), "say \"\"hi\"\"");
+        assert_eq!(escape_powershell_value("a\nb\tc"), "a`nb`tc");
+        assert_eq!(
+            escape_powershell_value("\u{201C}x\u{201D}; rm \u{201E}"),
+            "\u{201C}\u{201C}x\u{201D}\u{201D}; rm \u{201E}\u{201E}"
+        );
+        // Backslashes are path separators on Windows and stay as they are
+        assert_eq!(escape_powershell_value("C:\\dir"), "C:\\dir");
+
+        let parsed = make_parsed_map(&[("msg", ParsedValue::Single("cost: \"$5\"".to_string()))]);
+        assert_eq!(
+            powershell_output(&parsed, None),
+            "$env:SHCLAP_MSG = \"cost: \"\"`$5\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_powershell_unset_xtrace_and_dispatch() {
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            dialect: ShellDialect::PowerShell,
+            with_xtrace: true,
+            unset_vars: vec!["SHCLAP_VERBOSE".to_string()],
+            dispatch_subcommands: vec!["run-all".to_string()],
+            ..Default::default()
+        };
+        let output = generate_output_string_with_options(&parsed, "SHCLAP_", None, &options);
+        assert_eq!(
+            output,
+            "Remove-Item Env:SHCLAP_VERBOSE -ErrorAction SilentlyContinue\n\
+             Set-PSDebug -Trace 1\n\
+             $env:SHCLAP_VERBOSE = \"true\"\n\
+             Set-PSDebug -Off\n\
+             switch ($env:SHCLAP_SUBCOMMAND) {\n\
+             \x20   \"run-all\" { cmd_run_all }\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_self_cleanup_removes_file_last() {
+        let dir = tempfile::tempdir().unwrap();
+        let parsed = make_parsed_map(&[("verbose", ParsedValue::Single("true".to_string()))]);
+        let options = OutputOptions {
+            output_dir: Some(dir.path().to_path_buf()),
+            self_cleanup: true,
+            ..Default::default()
+        };
+        let path = generate_output_with_options(&parsed, "SHCLAP_", None, &options).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let last_line = content.lines().last().unwrap();
+        assert_eq!(last_line, format!("rm -f -- '{}'", path.to_string_lossy()));
+        assert!(content.starts_with("export SHCLAP_VERBOSE=\"true\"\n"));
+        assert!(content.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_cleanup_line_quotes_path() {
+        let path = Path::new("/tmp/it's here");
+        assert_eq!(
+            cleanup_line(path, ShellDialect::Bash),
+            "rm -f -- '/tmp/it'\\''s here'"
+        );
+        assert_eq!(
+            cleanup_line(path, ShellDialect::PowerShell),
+            "Remove-Item -LiteralPath '/tmp/it''s here' -ErrorAction SilentlyContinue"
+        );
+    }
+
+    #[test]
+    fn test_cleanup_line_quotes_shell_metacharacters() {
+        let path = Path::new("/tmp/a;id>/tmp/PWNED;b/.tmp*x");
+        assert_eq!(
+            cleanup_line(path, ShellDialect::Posix),
+            "rm -f -- '/tmp/a;id>/tmp/PWNED;b/.tmp*x'"
+        );
+        assert_eq!(
+            cleanup_line(Path::new("/tmp/a;b\u{2019}c"), ShellDialect::PowerShell),
+            "Remove-Item -LiteralPath '/tmp/a;b\u{2019}\u{2019}c' -ErrorAction SilentlyContinue"
+        );
+        assert_eq!(
+            cleanup_line(Path::new("/tmp/a\\b;c'd"), ShellDialect::Fish),
+            "rm -f -- '/tmp/a\\\\b;c\\'d'"
+        );
+    }
+
+    #[test]
+    fn test_json_output_keeps_value_types() {
+        let config = Config::from_json(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"count","type":"option","value_type":"int"},
+                {"name":"ratio","type":"option","value_type":"double"},
+                {"name":"enabled","type":"option","value_type":"bool"},
+                {"name":"label","type":"option"},
+                {"name":"ports","type":"option","value_type":"int","multiple":true},
+                {"name":"verbose","type":"flag"},
+                {"name":"level","type":"flag","multiple":true}
+            ]}"#,
+        )
+        .unwrap();
+        let parsed = make_parsed_map(&[
+            ("count", ParsedValue::Single("42".to_string())),
+            ("ratio", ParsedValue::Single("0.5".to_string())),
+            ("enabled", ParsedValue::Single("true".to_string())),
+            ("label", ParsedValue::Single("007".to_string())),
+            (
+                "ports",
+                ParsedValue::Multiple(vec!["80".to_string(), "443".to_string()]),
+            ),
+            ("verbose", ParsedValue::Single("false".to_string())),
+            ("level", ParsedValue::Single("3".to_string())),
+        ]);
+
+        let output = generate_json_output_string(&config, &parsed, None);
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "count": 42,
+                "ratio": 0.5,
+                "enabled": true,
+                "label": "007",
+                "ports": [80, 443],
+                "verbose": false,
+                "level": 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_output_subcommand_args_and_fallback() {
+        let config = Config::from_json(
+            r#"{"schema_version":2,"name":"test",
+                "args":[{"name":"port","type":"option"}],
+                "subcommands":[{"name":"serve","args":[
+                    {"name":"port","type":"option","value_type":"int"}
+                ]}]}"#,
+        )
+        .unwrap();
+        let parsed = make_parsed_map(&[
+            ("port", ParsedValue::Single("8080".to_string())),
+            ("extra", ParsedValue::Single("1".to_string())),
+        ]);
+
+        // The subcommand's int arg shadows the top-level string arg
+        let output = generate_json_output_string(&config, &parsed, Some("serve"));
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"subcommand": "serve", "port": 8080, "extra": "1"})
+        );
+
+        // Without the subcommand the top-level string arg applies
+        let output = generate_json_output_string(&config, &parsed, None);
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["port"], "8080");
+    }
+
+    #[test]
+    fn test_json_output_falls_back_to_string() {
+        let config = Config::from_json(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"count","type":"option","value_type":"int"}
+            ]}"#,
+        )
+        .unwrap();
+        // e.g. a default that was never validated by the int parser
+        let parsed = make_parsed_map(&[("count", ParsedValue::Single("many".to_string()))]);
+        let output = generate_json_output_string(&config, &parsed, None);
+        assert!(output.contains("\"count\": \"many\""), "{}", output);
+    }
 }
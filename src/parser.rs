@@ -1,16 +1,25 @@
 //! Argument parsing for target scripts using dynamic Clap.
 
-use crate::config::{ArgConfig, ArgType, Config, SubcommandConfig, ValueType};
-use clap::{error::ErrorKind, Arg, ArgAction, Command};
-use std::collections::HashMap;
+use crate::command::{build_command, check_value};
+use crate::config::{ArgConfig, ArgType, Config, SubcommandConfig, UndefinedEnv, ValueType};
+use crate::help::generate_version;
+use clap::{error::ErrorKind, parser::ValueSource, Arg};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 /// A parsed argument value, which can be single or multiple.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Serializes as `{"kind": "single", "value": "x"}`, with `multiple` and
+/// `grouped` carrying arrays.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
 pub enum ParsedValue {
     /// A single string value
     Single(String),
     /// Multiple string values (from multiple occurrences or delimiter-split)
     Multiple(Vec<String>),
+    /// Values grouped by occurrence (e.g. `--coord 1 2 --coord 3 4`)
+    Grouped(Vec<Vec<String>>),
 }
 
 impl ParsedValue {
@@ -19,26 +28,39 @@ impl ParsedValue {
         match self {
             ParsedValue::Single(s) => s.clone(),
             ParsedValue::Multiple(v) => v.join(" "),
+            ParsedValue::Grouped(groups) => groups.concat().join(" "),
         }
     }
 
     /// Check if this is a multiple value.
     pub fn is_multiple(&self) -> bool {
-        matches!(self, ParsedValue::Multiple(_))
+        matches!(self, ParsedValue::Multiple(_) | ParsedValue::Grouped(_))
     }
 }
 
 /// Successful parse result with values and optional subcommand.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ParseSuccess {
     /// Parsed argument values
     pub values: HashMap<String, ParsedValue>,
     /// Subcommand name if one was matched
+    #[serde(default)]
     pub subcommand: Option<String>,
+    /// Warnings to show the user (e.g. deprecated arguments that were used)
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Number of times each `multiple` option was given, keyed by arg name
+    /// (0 when the value came from a default)
+    #[serde(default)]
+    pub occurrences: HashMap<String, usize>,
 }
 
 /// Outcome of parsing arguments.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Serializes as `{"outcome": "success", "result": {...}}`; the help,
+/// version and error variants carry their message as `result`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", content = "result", rename_all = "lowercase")]
 pub enum ParseOutcome {
     /// Successfully parsed arguments with variable values.
     Success(ParseSuccess),
@@ -68,235 +90,46 @@ impl std::fmt::Display for ParseError {
 
 impl std::error::Error for ParseError {}
 
-/// Build a Clap Command from a Config with an effective name.
-fn build_command(config: &Config, effective_name: &str) -> Command {
-    let mut cmd = Command::new(effective_name.to_string())
-        .disable_help_subcommand(true)
-        .disable_version_flag(false)
-        .disable_help_flag(false);
-
-    // Set version if provided
-    if let Some(ref version) = config.version {
-        cmd = cmd.version(version.clone());
-    }
-
-    // Set description if provided
-    if let Some(ref description) = config.description {
-        cmd = cmd.about(description.clone());
-    }
-
-    let prefix = config.effective_prefix();
-    let schema_version = config.schema_version;
-
-    // Track positional index for ordering
-    let mut positional_index = 1usize;
-
-    // Add arguments from config
-    for arg_config in &config.args {
-        let arg = build_arg(arg_config, &mut positional_index, prefix, schema_version);
-        cmd = cmd.arg(arg);
-    }
-
-    // Add subcommands (schema v2)
-    for subcmd_config in &config.subcommands {
-        let subcmd = build_subcommand(subcmd_config, prefix, schema_version);
-        cmd = cmd.subcommand(subcmd);
-    }
-
-    // Require subcommand if any defined
-    if !config.subcommands.is_empty() {
-        cmd = cmd.subcommand_required(true);
-        cmd = cmd.arg_required_else_help(true);
-    }
-
-    cmd
-}
-
-/// Build a Clap Command for a subcommand config.
-fn build_subcommand(config: &SubcommandConfig, prefix: &str, schema_version: u32) -> Command {
-    let mut cmd = Command::new(config.name.clone());
-
-    if let Some(ref help) = config.help {
-        cmd = cmd.about(help.clone());
-    }
-
-    // Track positional index for ordering
-    let mut positional_index = 1usize;
-
-    // Add arguments
-    for arg_config in &config.args {
-        let arg = build_arg(arg_config, &mut positional_index, prefix, schema_version);
-        cmd = cmd.arg(arg);
-    }
-
-    cmd
-}
-
-/// Build a Clap Arg from an ArgConfig.
-fn build_arg(
-    arg_config: &ArgConfig,
-    positional_index: &mut usize,
-    prefix: &str,
-    schema_version: u32,
-) -> Arg {
-    let mut arg = Arg::new(arg_config.name.clone());
-
-    match arg_config.arg_type {
-        ArgType::Flag => {
-            // For flags, use Count if multiple, SetTrue otherwise
-            if arg_config.multiple {
-                arg = arg.action(ArgAction::Count);
-            } else {
-                arg = arg.action(ArgAction::SetTrue);
-            }
-
-            // Add short option
-            if let Some(short) = arg_config.short {
-                arg = arg.short(short);
-            }
-
-            // Add long option (with fallback to name if neither short nor long specified)
-            if let Some(long) = arg_config.effective_long() {
-                arg = arg.long(long.to_string());
-            }
-        }
-        ArgType::Option => {
-            // For options, use Append if multiple, Set otherwise
-            if arg_config.multiple {
-                arg = arg.action(ArgAction::Append);
-            } else {
-                arg = arg.action(ArgAction::Set);
-            }
-
-            // Add short option
-            if let Some(short) = arg_config.short {
-                arg = arg.short(short);
-            }
-
-            // Add long option (with fallback to name if neither short nor long specified)
-            if let Some(long) = arg_config.effective_long() {
-                arg = arg.long(long.to_string());
-            }
-
-            // Set value name for help display
-            arg = arg.value_name("VALUE");
-
-            // Allow attached values like -ofile.txt
-            arg = arg.allow_hyphen_values(true);
-        }
-        ArgType::Positional => {
-            arg = arg.index(*positional_index);
-            *positional_index += 1;
-
-            // Allow values that look like flags (e.g., after --)
-            arg = arg.allow_hyphen_values(true);
-
-            // For multiple positionals
-            if arg_config.multiple {
-                arg = arg.action(ArgAction::Append);
-            }
-        }
-    }
-
-    // Set required status
-    if arg_config.required {
-        arg = arg.required(true);
-    }
-
-    // Set default value
-    if let Some(ref default) = arg_config.default {
-        arg = arg.default_value(default.clone());
-    }
-
-    // Set help text
-    if let Some(ref help) = arg_config.help {
-        arg = arg.help(help.clone());
-    }
-
-    // Schema v2: Environment variable fallback (auto-env or custom)
-    if let Some(env_var) = arg_config.effective_env(prefix, schema_version) {
-        arg = arg.env(env_var);
-    }
-
-    // Schema v2: num_args range
-    if let Some(ref num_args) = arg_config.num_args {
-        if let Some(range) = parse_num_args_range(num_args) {
-            arg = arg.num_args(range);
-        }
-    }
-
-    // Schema v2: Value delimiter
-    if let Some(delim) = arg_config.delimiter {
-        arg = arg.value_delimiter(delim);
-    }
-
-    // Schema v2: Choices (possible values) - takes precedence over value_type
-    if let Some(ref choices) = arg_config.choices {
-        arg = arg.value_parser(clap::builder::PossibleValuesParser::new(choices.clone()));
-    } else {
-        // Schema v2: Apply value_type parser if no choices specified
-        match arg_config.value_type {
-            ValueType::String => {} // Default, no special parser
-            ValueType::Int => {
-                arg = arg.value_parser(clap::value_parser!(i64));
-            }
-            ValueType::Bool => {
-                arg = arg.value_parser(clap::builder::PossibleValuesParser::new(["true", "false"]));
-            }
-            ValueType::Double => {
-                arg = arg.value_parser(clap::value_parser!(f64));
-            }
-        }
-    }
-
-    arg
-}
-
-/// Parse a num_args string into a Clap ValueRange.
-fn parse_num_args_range(s: &str) -> Option<clap::builder::ValueRange> {
-    let s = s.trim();
-
-    // Single number
-    if let Ok(n) = s.parse::<usize>() {
-        return Some(clap::builder::ValueRange::new(n..=n));
-    }
-
-    // Range formats
-    if let Some(idx) = s.find("..") {
-        let start: usize = s[..idx].parse().ok()?;
-        let rest = &s[idx + 2..];
-
-        if rest.is_empty() {
-            // Unbounded: "N.."
-            return Some(clap::builder::ValueRange::new(start..));
-        }
-        if let Ok(end) = rest.parse::<usize>() {
-            // Exclusive: "N..M"
-            return Some(clap::builder::ValueRange::new(start..end));
-        }
-        if let Some(stripped) = rest.strip_prefix('=') {
-            if let Ok(end) = stripped.parse::<usize>() {
-                // Inclusive: "N..=M"
-                return Some(clap::builder::ValueRange::new(start..=end));
-            }
-        }
-    }
-
-    None
-}
-
-/// Extract parsed values from ArgMatches into a HashMap.
-fn extract_values(args: &[ArgConfig], matches: &clap::ArgMatches) -> HashMap<String, ParsedValue> {
-    let mut results = HashMap::new();
-
+/// Extract parsed values from ArgMatches into `results`.
+///
+/// Fails only when an `expand_env` value references an undefined variable
+/// under `UndefinedEnv::Error`.
+fn extract_values(
+    args: &[ArgConfig],
+    matches: &clap::ArgMatches,
+    undefined_env: &UndefinedEnv,
+    results: &mut HashMap<String, ParsedValue>,
+) -> Result<(), String> {
     for arg_config in args {
         let name = &arg_config.name;
 
+        // Only expand values the user provided, not defaults
+        let expand = arg_config.expand_env
+            && matches!(
+                matches.value_source(name),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            );
+        let lookup = |var: &str| std::env::var(var).ok();
+
         match arg_config.arg_type {
             ArgType::Flag => {
                 if arg_config.multiple {
                     // Count action returns u8
                     let count = matches.get_count(name);
+                    if arg_config
+                        .max_occurrences
+                        .is_some_and(|max| usize::from(count) > max)
+                    {
+                        let display = arg_config
+                            .long_with_prefix()
+                            .or_else(|| arg_config.short_with_prefix())
+                            .unwrap_or_else(|| name.clone());
+                        return Err(format!(
+                            "flag {} specified too many times (max {})",
+                            display,
+                            arg_config.max_occurrences.unwrap_or_default()
+                        ));
+                    }
                     results.insert(name.clone(), ParsedValue::Single(count.to_string()));
                 } else {
                     let value = matches.get_flag(name);
@@ -311,7 +144,51 @@ fn extract_values(args: &[ArgConfig], matches: &clap::ArgMatches) -> HashMap<Str
                 let is_double_type =
                     arg_config.value_type == ValueType::Double && arg_config.choices.is_none();
 
-                if arg_config.multiple {
+                if arg_config.grouped {
+                    // Grouped values: keep each occurrence's values together
+                    let groups: Vec<Vec<String>> = if is_int_type {
+                        matches
+                            .get_occurrences::<i64>(name)
+                            .map(|o| o.map(|g| g.map(|n| n.to_string()).collect()).collect())
+                            .unwrap_or_default()
+                    } else if is_double_type {
+                        matches
+                            .get_occurrences::<f64>(name)
+                            .map(|o| o.map(|g| g.map(|n| n.to_string()).collect()).collect())
+                            .unwrap_or_default()
+                    } else {
+                        matches
+                            .get_occurrences::<String>(name)
+                            .map(|o| o.map(|g| g.cloned().collect()).collect())
+                            .unwrap_or_default()
+                    };
+
+                    let groups = if expand {
+                        groups
+                            .iter()
+                            .map(|group| {
+                                group
+                                    .iter()
+                                    .map(|v| expand_env_vars(v, undefined_env, lookup))
+                                    .collect::<Result<Vec<_>, _>>()
+                            })
+                            .collect::<Result<Vec<_>, _>>()?
+                    } else {
+                        groups
+                    };
+
+                    if !groups.is_empty() {
+                        results.insert(name.clone(), ParsedValue::Grouped(groups));
+                    } else if let Some(ref default) = arg_config.default {
+                        // Grouped defaults skip the value parser, so normalize here
+                        let group = default
+                            .values()
+                            .iter()
+                            .map(|v| arg_config.decimal_point(v).into_owned())
+                            .collect();
+                        results.insert(name.clone(), ParsedValue::Grouped(vec![group]));
+                    }
+                } else if arg_config.multiple {
                     // Multiple values: get all
                     let values: Vec<String> = if is_int_type {
                         matches
@@ -330,10 +207,22 @@ fn extract_values(args: &[ArgConfig], matches: &clap::ArgMatches) -> HashMap<Str
                             .unwrap_or_default()
                     };
 
+                    let values = if expand {
+                        values
+                            .iter()
+                            .map(|v| expand_env_vars(v, undefined_env, lookup))
+                            .collect::<Result<Vec<_>, _>>()?
+                    } else {
+                        values
+                    };
+
                     if !values.is_empty() {
                         results.insert(name.clone(), ParsedValue::Multiple(values));
                     } else if let Some(ref default) = arg_config.default {
-                        results.insert(name.clone(), ParsedValue::Multiple(vec![default.clone()]));
+                        results.insert(
+                            name.clone(),
+                            ParsedValue::Multiple(default.values().to_vec()),
+                        );
                     }
                 } else {
                     // Single value
@@ -346,16 +235,198 @@ fn extract_values(args: &[ArgConfig], matches: &clap::ArgMatches) -> HashMap<Str
                     };
 
                     if let Some(value) = value_opt {
+                        let value = if expand {
+                            expand_env_vars(&value, undefined_env, lookup)?
+                        } else {
+                            value
+                        };
                         results.insert(name.clone(), ParsedValue::Single(value));
                     } else if let Some(ref default) = arg_config.default {
-                        results.insert(name.clone(), ParsedValue::Single(default.clone()));
+                        results.insert(name.clone(), ParsedValue::Single(default.to_string()));
                     }
                 }
             }
         }
+
+        // Schema v2: keep the first of each repeated value
+        if arg_config.unique {
+            if let Some(value) = results.get_mut(name) {
+                dedup_values(value);
+            }
+        }
+
+        // Schema v2: emit the choice's position instead of its text
+        if arg_config.emit_index {
+            if let (Some(choices), Some(value)) = (&arg_config.choices, results.get_mut(name)) {
+                replace_with_choice_index(value, choices);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Remove repeated values, keeping the first of each; grouped values are
+/// deduplicated within each group.
+fn dedup_values(value: &mut ParsedValue) {
+    let dedup = |values: &mut Vec<String>| {
+        let mut seen = HashSet::new();
+        values.retain(|v| seen.insert(v.clone()));
+    };
+    match value {
+        ParsedValue::Single(_) => {}
+        ParsedValue::Multiple(values) => dedup(values),
+        ParsedValue::Grouped(groups) => groups.iter_mut().for_each(dedup),
+    }
+}
+
+/// Replace each value with its zero-based position in `choices`.
+///
+/// Values not found in `choices` (only possible after `expand_env`) are left as-is.
+fn replace_with_choice_index(value: &mut ParsedValue, choices: &[String]) {
+    let index_of = |v: &mut String| {
+        if let Some(index) = choices.iter().position(|choice| choice == v) {
+            *v = index.to_string();
+        }
+    };
+    match value {
+        ParsedValue::Single(v) => index_of(v),
+        ParsedValue::Multiple(values) => values.iter_mut().for_each(index_of),
+        ParsedValue::Grouped(groups) => groups.iter_mut().flatten().for_each(index_of),
+    }
+}
+
+/// Count how many times each `multiple` option was given.
+///
+/// This differs from the number of values when an occurrence takes several
+/// (`num_args`) or is split by `delimiter`.
+fn occurrence_counts(args: &[ArgConfig], matches: &clap::ArgMatches) -> HashMap<String, usize> {
+    args.iter()
+        .filter(|arg| arg.arg_type == ArgType::Option && arg.multiple)
+        .map(|arg| {
+            let count = match matches.value_source(&arg.name) {
+                Some(ValueSource::DefaultValue) | None => 0,
+                Some(_) => matches
+                    .get_raw_occurrences(&arg.name)
+                    .map_or(0, |occurrences| occurrences.count()),
+            };
+            (arg.name.clone(), count)
+        })
+        .collect()
+}
+
+/// Warn about deprecated args that were given on the command line.
+fn deprecation_warnings(args: &[ArgConfig], matches: &clap::ArgMatches) -> Vec<String> {
+    args.iter()
+        .filter(|arg| matches.value_source(&arg.name) == Some(ValueSource::CommandLine))
+        .filter_map(|arg| {
+            let deprecation = arg.deprecation_message()?;
+            let display = arg
+                .long_with_prefix()
+                .or_else(|| arg.short_with_prefix())
+                .unwrap_or_else(|| arg.name.clone());
+            Some(format!("{} is deprecated: {}", display, deprecation))
+        })
+        .collect()
+}
+
+/// The subcommands `matches` selected, outermost first, each with its config
+/// and its own matches.
+fn matched_subcommands<'a>(
+    subcommands: &'a [SubcommandConfig],
+    matches: &'a clap::ArgMatches,
+) -> Vec<(&'a SubcommandConfig, &'a clap::ArgMatches)> {
+    let mut chain = Vec::new();
+    let (mut level, mut matches) = (subcommands, matches);
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        let Some(subcmd) = level.iter().find(|s| s.is_named(name)) else {
+            break;
+        };
+        chain.push((subcmd, sub_matches));
+        (level, matches) = (&subcmd.subcommands, sub_matches);
+    }
+    chain
+}
+
+/// Report an arg given on the command line whose
+/// `conflicts_with_subcommand` names the subcommand matched below it.
+fn subcommand_conflict(
+    args: &[ArgConfig],
+    matches: &clap::ArgMatches,
+    subcommand: &str,
+) -> Option<String> {
+    let arg = args.iter().find(|arg| {
+        arg.conflicts_with_subcommand
+            .iter()
+            .any(|s| s == subcommand)
+            && matches.value_source(&arg.name) == Some(ValueSource::CommandLine)
+    })?;
+    let display = arg
+        .long_with_prefix()
+        .or_else(|| arg.short_with_prefix())
+        .unwrap_or_else(|| arg.name.clone());
+    Some(format!(
+        "the argument '{}' cannot be used with the '{}' subcommand",
+        display, subcommand
+    ))
+}
+
+/// Expand `$VAR` and `${VAR}` references in a value using `lookup`.
+///
+/// A `$` not followed by a valid variable name (or an unclosed `${`) is kept
+/// literally. Undefined variables become empty or an error per `policy`.
+fn expand_env_vars(
+    value: &str,
+    policy: &UndefinedEnv,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        let (var, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) if is_env_var_name(&braced[..end]) => (&braced[..end], end + 2),
+                _ => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            if is_env_var_name(&after[..end]) {
+                (&after[..end], end)
+            } else {
+                ("", 0)
+            }
+        };
+
+        if consumed == 0 {
+            result.push('$');
+            rest = after;
+            continue;
+        }
+
+        match lookup(var) {
+            Some(val) => result.push_str(&val),
+            None if *policy == UndefinedEnv::Error => {
+                return Err(format!("undefined environment variable: {}", var));
+            }
+            None => {}
+        }
+        rest = &after[consumed..];
     }
 
-    results
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Check whether `name` is a valid shell variable name.
+fn is_env_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
 /// Parse command-line arguments according to the config.
@@ -375,36 +446,69 @@ pub fn parse_args(config: &Config, args: &[String], effective_name: &str) -> Par
 
     match cmd.try_get_matches_from(&full_args) {
         Ok(matches) => {
-            // Check for subcommand
-            if let Some((subcmd_name, subcmd_matches)) = matches.subcommand() {
-                // Find the subcommand config
-                if let Some(subcmd_config) =
-                    config.subcommands.iter().find(|s| s.name == subcmd_name)
+            // Each scope's args with its matches: top level, then every
+            // matched subcommand, outermost first
+            let chain = matched_subcommands(&config.subcommands, &matches);
+            let scopes: Vec<(&[ArgConfig], &clap::ArgMatches)> =
+                std::iter::once((config.args.as_slice(), &matches))
+                    .chain(chain.iter().map(|(subcmd, m)| (subcmd.args.as_slice(), *m)))
+                    .collect();
+
+            for ((args, matches), (subcmd, _)) in scopes.iter().zip(&chain) {
+                if let Some(message) = subcommand_conflict(args, matches, &subcmd.name) {
+                    return ParseOutcome::Error(message);
+                }
+            }
+
+            let mut values = HashMap::with_capacity(
+                config.expected_output_count()
+                    + chain
+                        .iter()
+                        .map(|(subcmd, _)| subcmd.args.len())
+                        .sum::<usize>(),
+            );
+            let mut warnings = Vec::new();
+            let mut occurrences = HashMap::new();
+            for (args, matches) in &scopes {
+                if let Err(message) =
+                    extract_values(args, matches, &config.undefined_env, &mut values)
                 {
-                    // Extract main command args
-                    let mut values = extract_values(&config.args, &matches);
-                    // Extract subcommand args
-                    let subcmd_values = extract_values(&subcmd_config.args, subcmd_matches);
-                    values.extend(subcmd_values);
-
-                    return ParseOutcome::Success(ParseSuccess {
-                        values,
-                        subcommand: Some(subcmd_name.to_string()),
-                    });
+                    return ParseOutcome::Error(message);
                 }
+                warnings.extend(deprecation_warnings(args, matches));
+                occurrences.extend(occurrence_counts(args, matches));
             }
 
-            // No subcommand
-            let values = extract_values(&config.args, &matches);
+            // Nested subcommands are joined with dots, e.g. `remote.add`
+            let subcommand = (!chain.is_empty()).then(|| {
+                chain
+                    .iter()
+                    .map(|(subcmd, _)| subcmd.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(".")
+            });
+
             ParseOutcome::Success(ParseSuccess {
                 values,
-                subcommand: None,
+                subcommand,
+                warnings,
+                occurrences,
             })
         }
         Err(e) => {
             match e.kind() {
                 ErrorKind::DisplayHelp => ParseOutcome::Help(e.to_string()),
-                ErrorKind::DisplayVersion => ParseOutcome::Version(e.to_string()),
+                ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand if config.help_if_no_args => {
+                    ParseOutcome::Help(e.to_string())
+                }
+                ErrorKind::DisplayVersion => {
+                    // Extended version info is rendered by shclap, not Clap
+                    if config.version_info.is_some() {
+                        ParseOutcome::Version(generate_version(config, effective_name))
+                    } else {
+                        ParseOutcome::Version(e.to_string())
+                    }
+                }
                 _ => {
                     // Format error message to match expected format
                     let message = format_error_message(&e);
@@ -415,6 +519,156 @@ pub fn parse_args(config: &Config, args: &[String], effective_name: &str) -> Par
     }
 }
 
+/// Parse arguments like `parse_args`, giving up after `timeout`.
+///
+/// Parsing runs on a separate thread. If it doesn't finish in time, returns
+/// `ParseOutcome::Error("argument parsing timed out")`; the thread is left to
+/// finish in the background and its result is discarded.
+#[cfg(feature = "timeout")]
+pub fn parse_args_with_timeout(
+    config: &Config,
+    args: &[String],
+    effective_name: &str,
+    timeout: std::time::Duration,
+) -> ParseOutcome {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let config = config.clone();
+    let args = args.to_vec();
+    let effective_name = effective_name.to_string();
+
+    std::thread::spawn(move || {
+        // The receiver is gone if we already timed out
+        let _ = sender.send(parse_args(&config, &args, &effective_name));
+    });
+
+    receiver
+        .recv_timeout(timeout)
+        .unwrap_or_else(|_| ParseOutcome::Error("argument parsing timed out".to_string()))
+}
+
+/// Find required top-level args that would receive no value from the command
+/// line, the environment, or a default.
+///
+/// Parses with every requirement relaxed, so other errors (unknown options,
+/// invalid values) yield an empty list and are left for `parse_args` to report.
+pub fn missing_required_args<'a>(
+    config: &'a Config,
+    args: &[String],
+    effective_name: &str,
+) -> Vec<&'a ArgConfig> {
+    let cmd = build_command(config, effective_name)
+        .subcommand_required(false)
+        .arg_required_else_help(false)
+        .mut_args(|arg| arg.required(false));
+
+    let mut full_args = vec![effective_name.to_string()];
+    full_args.extend(args.iter().cloned());
+
+    match cmd.try_get_matches_from(&full_args) {
+        Ok(matches) => config
+            .args
+            .iter()
+            .filter(|arg| arg.required && !matches.contains_id(&arg.name))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Parse arguments, reporting every invalid value at once.
+///
+/// Runs a pre-pass with all value parsers relaxed to plain strings and checks
+/// each value with the same checks its value parser makes (`choices`,
+/// `value_type`, `min`/`max`, `path_must_exist`). If any value is
+/// invalid, returns a single `ParseOutcome::Error` listing each offending arg;
+/// otherwise falls through to `parse_args`.
+pub fn parse_args_validating(
+    config: &Config,
+    args: &[String],
+    effective_name: &str,
+) -> ParseOutcome {
+    let errors = collect_value_errors(config, args, effective_name);
+
+    match errors.len() {
+        0 => parse_args(config, args, effective_name),
+        1 => ParseOutcome::Error(errors.into_iter().next().unwrap_or_default()),
+        _ => ParseOutcome::Error(format!("invalid values:\n  {}", errors.join("\n  "))),
+    }
+}
+
+/// Collect a message for every value that fails its arg's value checks.
+///
+/// Requirements are relaxed as in `missing_required_args`, so only value-level
+/// problems are reported here; anything else is left for `parse_args`.
+fn collect_value_errors(config: &Config, args: &[String], effective_name: &str) -> Vec<String> {
+    let relax = |arg: Arg| {
+        let arg = arg.required(false);
+        if arg.get_action().takes_values() {
+            arg.value_parser(clap::value_parser!(String))
+        } else {
+            arg
+        }
+    };
+
+    let mut cmd = build_command(config, effective_name)
+        .subcommand_required(false)
+        .arg_required_else_help(false)
+        .mut_args(relax);
+    cmd = relax_subcommands(cmd, &config.subcommands, &relax);
+
+    let mut full_args = vec![effective_name.to_string()];
+    full_args.extend(args.iter().cloned());
+
+    let matches = match cmd.try_get_matches_from(&full_args) {
+        Ok(matches) => matches,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut errors = Vec::new();
+    check_values(&config.args, &matches, &mut errors);
+    for (subcmd_config, subcmd_matches) in matched_subcommands(&config.subcommands, &matches) {
+        check_values(&subcmd_config.args, subcmd_matches, &mut errors);
+    }
+    errors
+}
+
+/// Apply `relax` to the args of every subcommand, at any depth.
+fn relax_subcommands(
+    mut cmd: clap::Command,
+    subcommands: &[SubcommandConfig],
+    relax: &impl Fn(Arg) -> Arg,
+) -> clap::Command {
+    for subcmd_config in subcommands {
+        cmd = cmd.mut_subcommand(&subcmd_config.name, |sub| {
+            relax_subcommands(sub.mut_args(relax), &subcmd_config.subcommands, relax)
+        });
+    }
+    cmd
+}
+
+/// Check string values in `matches` with each arg's own value checks:
+/// choices, value type, `min`/`max` and `path_must_exist`.
+fn check_values(args: &[ArgConfig], matches: &clap::ArgMatches, errors: &mut Vec<String>) {
+    for arg_config in args {
+        if arg_config.arg_type == ArgType::Flag {
+            continue;
+        }
+
+        let values = match matches.get_many::<String>(&arg_config.name) {
+            Some(values) => values,
+            None => continue,
+        };
+
+        for value in values {
+            if let Err(expected) = check_value(arg_config, value) {
+                errors.push(format!(
+                    "invalid value '{}' for '{}': {}",
+                    value, arg_config.name, expected
+                ));
+            }
+        }
+    }
+}
+
 /// Format Clap error messages to match expected shclap format.
 fn format_error_message(error: &clap::Error) -> String {
     let raw = error.to_string();
@@ -439,6 +693,33 @@ fn format_error_message(error: &clap::Error) -> String {
             || msg.contains("the following required argument")
         {
             // Look for the argument name in the full message
+            // Options missing through `required` or another arg's `requires`
+            // (clap lists them one per line, up to a blank line)
+            let options: Vec<&str> = raw
+                .lines()
+                .skip(1)
+                .map(str::trim)
+                .take_while(|line| !line.is_empty())
+                .map_while(|line| {
+                    line.starts_with('-')
+                        .then(|| line.split_whitespace().next())
+                })
+                .flatten()
+                .collect();
+            match options[..] {
+                [] => {}
+                [opt] => {
+                    return format!("the following required argument was not provided: {}", opt)
+                }
+                _ => {
+                    return format!(
+                        "the following required arguments were not provided: {}",
+                        options.join(", ")
+                    )
+                }
+            }
+
+            // A missing positional, shown as <NAME>
             for line in raw.lines() {
                 let trimmed = line.trim();
                 if trimmed.starts_with('<') {
@@ -451,6 +732,30 @@ fn format_error_message(error: &clap::Error) -> String {
             return "missing required argument".to_string();
         }
 
+        if msg.contains("equal sign is needed") {
+            // Extract the option from "... to '--output=<VALUE>'"
+            if let Some(start) = msg.find("'--") {
+                let rest = &msg[start + 1..];
+                let opt_end = rest.find(['=', '\'']).unwrap_or(rest.len());
+                let opt = &rest[..opt_end];
+                return format!("option {} requires '=': use {}=VALUE", opt, opt);
+            }
+        }
+
+        if msg.contains("cannot be used with") {
+            // "the argument '--json' cannot be used with '--yaml'"; drop any
+            // value names such as '--output <OUTPUT>'
+            let names: Vec<&str> = msg
+                .split('\'')
+                .skip(1)
+                .step_by(2)
+                .filter_map(|quoted| quoted.split_whitespace().next())
+                .collect();
+            if let [first, second] = names[..] {
+                return format!("argument {} cannot be used with {}", first, second);
+            }
+        }
+
         if msg.contains("a value is required") {
             // Extract option name
             for line in raw.lines() {
@@ -703,6 +1008,57 @@ mod tests {
         assert_eq!(result.get("output"), Some(&"out.txt".to_string()));
     }
 
+    #[test]
+    fn test_parse_positional_default() {
+        let config = parse_config(
+            r#"{"name":"test","args":[
+                {"name":"input","type":"positional","required":true},
+                {"name":"mode","type":"positional","default":"fast"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["in.txt"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("input"), Some(&"in.txt".to_string()));
+        assert_eq!(result.get("mode"), Some(&"fast".to_string()));
+
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["in.txt", "slow"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("mode"), Some(&"slow".to_string()));
+    }
+
+    #[test]
+    fn test_parse_multiple_positional_default() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"files","type":"positional","multiple":true,"default":"."}
+            ]}"#,
+        );
+        let result = unwrap_success_full(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert_eq!(
+            result.values.get("files"),
+            Some(&ParsedValue::Multiple(vec![".".to_string()]))
+        );
+    }
+
+    #[test]
+    fn test_parse_default_non_ascii_preserved() {
+        let config = parse_config(
+            r#"{"name":"test","args":[
+                {"name":"dir","long":"dir","type":"option","default":"/srv/données/日本"}
+            ]}"#,
+        );
+        let result = unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert_eq!(result.get("dir"), Some(&"/srv/données/日本".to_string()));
+    }
+
     #[test]
     fn test_parse_default_overridden() {
         let config = parse_config(
@@ -917,6 +1273,20 @@ mod tests {
         assert!(matches!(result, ParseOutcome::Version(_)));
     }
 
+    #[test]
+    fn test_version_flag_with_version_info() {
+        let config = parse_config(
+            r#"{"name":"test","version":"1.0.0","version_info":{"build_date":"2024-05-01","git_hash":"abc1234"}}"#,
+        );
+        let result = parse_args(&config, &to_args(&["--version"]), get_name(&config));
+        assert_eq!(
+            result,
+            ParseOutcome::Version(
+                "test 1.0.0\nbuild date: 2024-05-01\ngit hash: abc1234\n".to_string()
+            )
+        );
+    }
+
     #[test]
     fn test_help_takes_precedence_over_version() {
         let config = parse_config(r#"{"name":"test","version":"1.0.0"}"#);
@@ -958,6 +1328,7 @@ mod tests {
     // Schema v2 tests
 
     #[test]
+    #[allow(clippy::unnecessary_get_then_check)]
     fn test_env_fallback() {
         // Note: env var tests require actual env vars set, which is tricky in unit tests.
         // This test verifies the config parses correctly; actual env fallback is a Clap feature.
@@ -972,6 +1343,114 @@ mod tests {
         assert!(result.values.get("input").is_none());
     }
 
+    /// Sets an environment variable for the lifetime of the guard.
+    struct EnvGuard(&'static str);
+
+    impl EnvGuard {
+        fn set(name: &'static str, value: &str) -> Self {
+            std::env::set_var(name, value);
+            EnvGuard(name)
+        }
+    }
+
+    impl Drop for EnvGuard {
+        fn drop(&mut self) {
+            std::env::remove_var(self.0);
+        }
+    }
+
+    #[test]
+    fn test_auto_env_fallback_reads_prefixed_name() {
+        let _guard = EnvGuard::set("AUTOENV_TEST_OUTPUT_FILE", "from-env.txt");
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","prefix":"AUTOENV_TEST_","args":[
+                {"name":"output-file","long":"output-file","type":"option"},
+                {"name":"mode","long":"mode","type":"option","env":false}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        // No `env` setting: falls back to PREFIX + NAME
+        let result = unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert_eq!(result.get("output-file"), Some(&"from-env.txt".to_string()));
+        assert_eq!(result.get("mode"), None);
+
+        // The command line still wins over the environment
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--output-file", "cli.txt"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("output-file"), Some(&"cli.txt".to_string()));
+    }
+
+    #[test]
+    fn test_env_disabled_ignores_auto_env_var() {
+        let _guard = EnvGuard::set("ENVOFF_TEST_TOKEN", "leaked");
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","prefix":"ENVOFF_TEST_","args":[
+                {"name":"token","long":"token","type":"option","env":false}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success_full(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert!(!result.values.contains_key("token"));
+    }
+
+    #[test]
+    fn test_env_custom_reads_named_var() {
+        let _custom = EnvGuard::set("ENVCUSTOM_TEST_API_TOKEN", "from-custom");
+        let _auto = EnvGuard::set("ENVCUSTOM_TEST_TOKEN", "from-auto");
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","prefix":"ENVCUSTOM_TEST_","args":[
+                {"name":"token","long":"token","type":"option","env":"ENVCUSTOM_TEST_API_TOKEN"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        // The custom name replaces the auto PREFIX+NAME fallback
+        let result = unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert_eq!(result.get("token"), Some(&"from-custom".to_string()));
+    }
+
+    #[test]
+    fn test_auto_env_false_makes_env_opt_in() {
+        let _auto = EnvGuard::set("NOAUTO_TEST_OUTPUT", "from-auto");
+        let _custom = EnvGuard::set("NOAUTO_TEST_API_TOKEN", "from-custom");
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","prefix":"NOAUTO_TEST_","auto_env":false,"args":[
+                {"name":"output","long":"output","type":"option"},
+                {"name":"token","long":"token","type":"option","env":"NOAUTO_TEST_API_TOKEN"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        // Only the arg that names a variable reads the environment
+        let result = unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert_eq!(result.get("output"), None);
+        assert_eq!(result.get("token"), Some(&"from-custom".to_string()));
+    }
+
+    #[test]
+    fn test_strict_treats_empty_env_as_unset() {
+        let _guard = EnvGuard::set("EMPTYENV_TEST_LEVEL", "");
+        let json = r#"{"schema_version":2,"name":"test","prefix":"EMPTYENV_TEST_","args":[
+            {"name":"level","long":"level","type":"option","default":"info"}
+        ]}"#;
+
+        // Lenient: an empty variable is still a value
+        let config = parse_config(json);
+        let result = unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert_eq!(result.get("level"), Some(&String::new()));
+
+        // Strict: the default applies instead
+        let mut config = parse_config(json);
+        config.strict = true;
+        let result = unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert_eq!(result.get("level"), Some(&"info".to_string()));
+    }
+
     #[test]
     fn test_multiple_option_values() {
         let config = parse_config(
@@ -1039,6 +1518,86 @@ mod tests {
         assert_eq!(result.subcommand, Some("init".to_string()));
     }
 
+    #[test]
+    fn test_hidden_subcommand_still_parses() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","subcommands":[
+                {"name":"build"},
+                {"name":"debug-dump","hidden":true,"args":[
+                    {"name":"depth","long":"depth","type":"option"}
+                ]}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["debug-dump", "--depth", "2"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.subcommand, Some("debug-dump".to_string()));
+        assert_eq!(
+            result.values.get("depth"),
+            Some(&ParsedValue::Single("2".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_nested_subcommands() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"verbose","short":"v","type":"flag"}
+            ],"subcommands":[
+                {"name":"remote","args":[
+                    {"name":"dry-run","long":"dry-run","type":"flag"}
+                ],"subcommands":[
+                    {"name":"add","args":[{"name":"url","type":"positional","required":true}]},
+                    {"name":"remove","args":[{"name":"target","type":"positional","required":true}]}
+                ]},
+                {"name":"status"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&[
+                "-v",
+                "remote",
+                "--dry-run",
+                "add",
+                "https://example.com/repo.git",
+            ]),
+            get_name(&config),
+        ));
+        assert_eq!(result.subcommand, Some("remote.add".to_string()));
+        assert_eq!(
+            result.values.get("url"),
+            Some(&ParsedValue::Single(
+                "https://example.com/repo.git".to_string()
+            ))
+        );
+        assert_eq!(
+            result.values.get("dry-run"),
+            Some(&ParsedValue::Single("true".to_string()))
+        );
+        assert_eq!(
+            result.values.get("verbose"),
+            Some(&ParsedValue::Single("true".to_string()))
+        );
+
+        // A parent with children needs one of them
+        let result = parse_args(&config, &to_args(&["remote"]), get_name(&config));
+        assert!(!matches!(result, ParseOutcome::Success(_)));
+
+        // Siblings keep working at every level
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["status"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.subcommand, Some("status".to_string()));
+    }
+
     #[test]
     fn test_subcommand_with_args() {
         let config = parse_config(
@@ -1061,6 +1620,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_subcommand_alias_reports_canonical_name() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","subcommands":[
+                {"name":"remove","aliases":["rm"],"args":[
+                    {"name":"path","type":"positional"}
+                ]}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        for invoked in ["remove", "rm"] {
+            let result = unwrap_success_full(parse_args(
+                &config,
+                &to_args(&[invoked, "notes.txt"]),
+                get_name(&config),
+            ));
+            assert_eq!(result.subcommand, Some("remove".to_string()));
+            assert_eq!(
+                result.values.get("path"),
+                Some(&ParsedValue::Single("notes.txt".to_string()))
+            );
+        }
+    }
+
     #[test]
     fn test_subcommand_with_main_args() {
         let config = parse_config(
@@ -1098,6 +1681,65 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_subcommand_after_help() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","subcommands":[
+                {"name":"deploy","after_help":"Report bugs to ops@example.com","args":[
+                    {"name":"force","long":"force","type":"flag"}
+                ]}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        match parse_args(&config, &to_args(&["deploy", "--help"]), get_name(&config)) {
+            ParseOutcome::Help(text) => {
+                let footer = text.find("Report bugs to ops@example.com").unwrap();
+                assert!(text.find("--force").unwrap() < footer, "{}", text);
+            }
+            other => panic!("Expected Help, got {:?}", other),
+        }
+
+        // The footer doesn't change how the subcommand parses
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["deploy", "--force"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.subcommand, Some("deploy".to_string()));
+    }
+
+    #[test]
+    fn test_help_if_no_args_shows_help() {
+        let config = parse_config(
+            r#"{"name":"test","help_if_no_args":true,"args":[
+                {"name":"output","long":"output","type":"option","default":"out.txt"}
+            ]}"#,
+        );
+        match parse_args(&config, &to_args(&[]), get_name(&config)) {
+            ParseOutcome::Help(text) => assert!(text.contains("--output")),
+            other => panic!("Expected Help, got {:?}", other),
+        }
+
+        // Any argument suppresses the help
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--output", "x"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("output"), Some(&"x".to_string()));
+    }
+
+    #[test]
+    fn test_help_if_no_args_off_uses_defaults() {
+        let config = parse_config(
+            r#"{"name":"test","args":[
+                {"name":"output","long":"output","type":"option","default":"out.txt"}
+            ]}"#,
+        );
+        let result = unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert_eq!(result.get("output"), Some(&"out.txt".to_string()));
+    }
+
     #[test]
     fn test_num_args_range() {
         let config = parse_config(
@@ -1120,17 +1762,36 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_num_args_formats() {
-        // Single number
-        assert!(parse_num_args_range("3").is_some());
-        // Unbounded
-        assert!(parse_num_args_range("1..").is_some());
-        // Exclusive range
-        assert!(parse_num_args_range("2..5").is_some());
-        // Inclusive range
-        assert!(parse_num_args_range("1..=3").is_some());
-        // Invalid
-        assert!(parse_num_args_range("abc").is_none());
+    fn test_grouped_preserves_occurrences() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"coord","long":"coord","type":"option","multiple":true,"num_args":"2","grouped":true,"value_type":"int"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["--coord", "1", "2", "--coord", "3", "4"]),
+            get_name(&config),
+        ));
+        assert_eq!(
+            result.values.get("coord"),
+            Some(&ParsedValue::Grouped(vec![
+                vec!["1".to_string(), "2".to_string()],
+                vec!["3".to_string(), "4".to_string()],
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_grouped_absent_without_values() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"pair","long":"pair","type":"option","multiple":true,"num_args":"2","grouped":true}
+            ]}"#,
+        );
+        let result = unwrap_success_full(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert!(!result.values.contains_key("pair"));
     }
 
     #[test]
@@ -1530,6 +2191,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_value_type_double_rejects_non_finite() {
+        for args in [
+            r#"{"name":"value","long":"value","type":"option","value_type":"double"}"#,
+            r#"{"name":"value","long":"value","type":"option","value_type":"double","decimal_comma":true}"#,
+            r#"{"name":"value","long":"value","type":"option","value_type":"double","max":10}"#,
+        ] {
+            let config = parse_config(&format!(
+                r#"{{"schema_version":2,"name":"test","args":[{}]}}"#,
+                args
+            ));
+            config.validate().unwrap();
+            for value in ["nan", "inf", "-inf", "1e400"] {
+                match parse_args(&config, &to_args(&["--value", value]), get_name(&config)) {
+                    ParseOutcome::Error(msg) => {
+                        assert!(msg.contains("is not a finite number"), "{}", msg)
+                    }
+                    other => panic!("Expected Error for {}, got {:?}", value, other),
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_value_type_double_multiple() {
         // Criterion: multiple: true with "1.1" "2.2" -> ["1.1", "2.2"]
@@ -1608,4 +2292,1287 @@ mod tests {
         let result = unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
         assert_eq!(result.get("threshold"), Some(&"1.5".to_string()));
     }
+
+    #[test]
+    fn test_value_type_json_valid() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"metadata","long":"metadata","type":"option","value_type":"json"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let blob = r#"{"tags": ["a", "b"], "count": 2}"#;
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--metadata", blob]),
+            get_name(&config),
+        ));
+        // The original text is emitted unchanged, not re-serialized
+        assert_eq!(result.get("metadata"), Some(&blob.to_string()));
+    }
+
+    #[test]
+    fn test_value_type_json_malformed() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"metadata","long":"metadata","type":"option","value_type":"json"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let result = parse_args(
+            &config,
+            &to_args(&["--metadata", r#"{"tags": [1, }"#]),
+            get_name(&config),
+        );
+        match result {
+            ParseOutcome::Error(msg) => {
+                assert!(msg.contains("invalid JSON"), "unexpected error: {}", msg);
+                assert!(msg.contains("line 1 column"), "missing location: {}", msg);
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_value_type_base64_valid() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"payload","long":"payload","type":"option","value_type":"base64"},
+                {"name":"token","long":"token","type":"option","value_type":"base64url"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--payload", "aGk/Pz8=", "--token", "aGk_Pz8="]),
+            get_name(&config),
+        ));
+        // The encoded text is emitted, not the decoded bytes
+        assert_eq!(result.get("payload"), Some(&"aGk/Pz8=".to_string()));
+        assert_eq!(result.get("token"), Some(&"aGk_Pz8=".to_string()));
+    }
+
+    #[test]
+    fn test_value_type_base64_invalid_padding() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"payload","long":"payload","type":"option","value_type":"base64"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let result = parse_args(
+            &config,
+            &to_args(&["--payload", "aGVsbG8"]),
+            get_name(&config),
+        );
+        match result {
+            ParseOutcome::Error(msg) => {
+                assert!(msg.contains("invalid base64"), "unexpected error: {}", msg);
+                assert!(msg.contains("aGVsbG8"), "missing value: {}", msg);
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_value_type_hex_normalized() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"hash","long":"hash","type":"option","value_type":"hex"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--hash", "0xDEADbeef"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("hash"), Some(&"deadbeef".to_string()));
+    }
+
+    #[test]
+    fn test_value_type_hex_errors() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"hash","long":"hash","type":"option","value_type":"hex"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        for (value, expected) in [
+            ("abc", "expected an even number of digits"),
+            ("12g4", "'g' is not a hex digit"),
+        ] {
+            match parse_args(&config, &to_args(&["--hash", value]), get_name(&config)) {
+                ParseOutcome::Error(msg) => {
+                    assert!(msg.contains(expected), "unexpected error: {}", msg)
+                }
+                other => panic!("Expected Error for {}, got {:?}", value, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_negative_numbers_only_accepts_negative_value() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"offset","long":"offset","type":"option","negative_numbers_only":true}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--offset", "-5"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("offset"), Some(&"-5".to_string()));
+    }
+
+    #[test]
+    fn test_negative_numbers_only_rejects_flag_as_value() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"output","long":"output","type":"option","negative_numbers_only":true},
+                {"name":"verbose","long":"verbose","type":"flag"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let result = parse_args(
+            &config,
+            &to_args(&["--output", "--verbose"]),
+            get_name(&config),
+        );
+        assert!(
+            matches!(result, ParseOutcome::Error(_)),
+            "Expected Error, got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_hyphen_values_swallow_flag_by_default() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"output","long":"output","type":"option"},
+                {"name":"verbose","long":"verbose","type":"flag"}
+            ]}"#,
+        );
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--output", "--verbose"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("output"), Some(&"--verbose".to_string()));
+        assert_eq!(result.get("verbose"), Some(&"false".to_string()));
+    }
+
+    #[test]
+    fn test_emit_index_for_each_choice() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"level","long":"level","type":"option","choices":["low","med","high"],"emit_index":true}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        for (choice, index) in [("low", "0"), ("med", "1"), ("high", "2")] {
+            let result = unwrap_success(parse_args(
+                &config,
+                &to_args(&["--level", choice]),
+                get_name(&config),
+            ));
+            assert_eq!(result.get("level"), Some(&index.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_emit_index_default_and_multiple() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"level","long":"level","type":"option","choices":["low","high"],"default":"high","emit_index":true},
+                {"name":"tag","long":"tag","type":"option","multiple":true,"choices":["a","b","c"],"emit_index":true}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["--tag", "c", "--tag", "a"]),
+            get_name(&config),
+        ));
+        assert_eq!(
+            result.values.get("level"),
+            Some(&ParsedValue::Single("1".to_string()))
+        );
+        assert_eq!(
+            result.values.get("tag"),
+            Some(&ParsedValue::Multiple(vec![
+                "2".to_string(),
+                "0".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_emit_index_invalid_value_errors() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"level","long":"level","type":"option","choices":["low","med","high"],"emit_index":true}
+            ]}"#,
+        );
+        let result = parse_args(
+            &config,
+            &to_args(&["--level", "extreme"]),
+            get_name(&config),
+        );
+        assert!(
+            matches!(result, ParseOutcome::Error(_)),
+            "Expected Error, got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_allow_abbreviations_unambiguous() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","allow_abbreviations":true,"args":[
+                {"name":"verbose","long":"verbose","type":"flag"},
+                {"name":"output","long":"output","type":"option"}
+            ]}"#,
+        );
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--verb", "--out", "file.txt"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("verbose"), Some(&"true".to_string()));
+        assert_eq!(result.get("output"), Some(&"file.txt".to_string()));
+    }
+
+    #[test]
+    fn test_allow_abbreviations_ambiguous_errors() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","allow_abbreviations":true,"args":[
+                {"name":"verbose","long":"verbose","type":"flag"},
+                {"name":"verify","long":"verify","type":"flag"}
+            ]}"#,
+        );
+        let result = parse_args(&config, &to_args(&["--ver"]), get_name(&config));
+        match result {
+            ParseOutcome::Error(msg) => assert_eq!(msg, "unknown option: --ver"),
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_abbreviations_off_by_default() {
+        let config = parse_config(
+            r#"{"name":"test","args":[{"name":"verbose","long":"verbose","type":"flag"}]}"#,
+        );
+        let result = parse_args(&config, &to_args(&["--verb"]), get_name(&config));
+        assert!(matches!(result, ParseOutcome::Error(_)));
+    }
+
+    #[test]
+    fn test_infer_subcommands_unambiguous() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","infer_subcommands":true,"subcommands":[
+                {"name":"init","args":[{"name":"force","long":"force","type":"flag"}]},
+                {"name":"status","args":[]}
+            ]}"#,
+        );
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["in", "--force"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.subcommand.as_deref(), Some("init"));
+        assert_eq!(
+            result.values.get("force"),
+            Some(&ParsedValue::Single("true".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_infer_subcommands_ambiguous_errors() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","infer_subcommands":true,"subcommands":[
+                {"name":"start","args":[]},
+                {"name":"status","args":[]}
+            ]}"#,
+        );
+        let result = parse_args(&config, &to_args(&["st"]), get_name(&config));
+        match result {
+            ParseOutcome::Error(msg) => assert_eq!(msg, "unrecognized subcommand 'st'"),
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_success_json_round_trip() {
+        let mut values = HashMap::new();
+        values.insert("name".to_string(), ParsedValue::Single("alice".to_string()));
+        values.insert(
+            "files".to_string(),
+            ParsedValue::Multiple(vec!["a.txt".to_string(), "b.txt".to_string()]),
+        );
+        values.insert(
+            "coord".to_string(),
+            ParsedValue::Grouped(vec![vec!["1".to_string(), "2".to_string()]]),
+        );
+        let outcome = ParseOutcome::Success(ParseSuccess {
+            values,
+            subcommand: Some("build".to_string()),
+            warnings: vec!["--old is deprecated".to_string()],
+            occurrences: HashMap::from([("files".to_string(), 2)]),
+        });
+
+        let json = serde_json::to_string(&outcome).unwrap();
+        let restored: ParseOutcome = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, outcome);
+    }
+
+    #[test]
+    fn test_parsed_value_json_form() {
+        let single = serde_json::to_value(ParsedValue::Single("x".to_string())).unwrap();
+        assert_eq!(single, serde_json::json!({"kind": "single", "value": "x"}));
+
+        let multiple = serde_json::to_value(ParsedValue::Multiple(vec![
+            "a".to_string(),
+            "b".to_string(),
+        ]))
+        .unwrap();
+        assert_eq!(
+            multiple,
+            serde_json::json!({"kind": "multiple", "value": ["a", "b"]})
+        );
+
+        let help = serde_json::to_value(ParseOutcome::Help("usage".to_string())).unwrap();
+        assert_eq!(
+            help,
+            serde_json::json!({"outcome": "help", "result": "usage"})
+        );
+    }
+
+    #[test]
+    fn test_conflicts_with_subcommand_triggers() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "all", "long": "all", "type": "flag", "conflicts_with_subcommand": ["single"]}],
+            "subcommands": [{"name": "single"}, {"name": "batch"}]
+        }"#,
+        );
+
+        match parse_args(&config, &to_args(&["--all", "single"]), get_name(&config)) {
+            ParseOutcome::Error(message) => {
+                assert!(message.contains("'--all'"));
+                assert!(message.contains("'single' subcommand"));
+            }
+            other => panic!("Expected conflict error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_conflicts_with_subcommand_not_triggered() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "all", "long": "all", "type": "flag", "conflicts_with_subcommand": ["single"]}],
+            "subcommands": [{"name": "single"}, {"name": "batch"}]
+        }"#,
+        );
+
+        // Another subcommand accepts the flag
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["--all", "batch"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.subcommand.as_deref(), Some("batch"));
+        assert_eq!(
+            result.values["all"],
+            ParsedValue::Single("true".to_string())
+        );
+
+        // The conflicting subcommand is fine without the flag
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["single"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.subcommand.as_deref(), Some("single"));
+    }
+
+    #[test]
+    fn test_int_range_boundaries() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "port", "long": "port", "type": "option", "value_type": "int", "min": 1, "max": 65535}]
+        }"#,
+        );
+
+        for value in ["1", "65535"] {
+            let result = unwrap_success(parse_args(
+                &config,
+                &to_args(&["--port", value]),
+                get_name(&config),
+            ));
+            assert_eq!(result.get("port"), Some(&value.to_string()));
+        }
+
+        for value in ["0", "65536", "70000"] {
+            match parse_args(&config, &to_args(&["--port", value]), get_name(&config)) {
+                ParseOutcome::Error(msg) => assert!(
+                    msg.contains(&format!("value {} out of range (1..=65535)", value)),
+                    "unexpected error: {}",
+                    msg
+                ),
+                other => panic!("Expected error for {}, got {:?}", value, other),
+            }
+        }
+
+        // Non-integers still fail the type check
+        assert!(matches!(
+            parse_args(&config, &to_args(&["--port", "abc"]), get_name(&config)),
+            ParseOutcome::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_double_range_open_ended() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "ratio", "long": "ratio", "type": "option", "value_type": "double", "min": 0.0}]
+        }"#,
+        );
+
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--ratio", "0"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("ratio"), Some(&"0".to_string()));
+
+        match parse_args(&config, &to_args(&["--ratio", "-0.5"]), get_name(&config)) {
+            ParseOutcome::Error(msg) => assert!(msg.contains("value -0.5 out of range (0..)")),
+            other => panic!("Expected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_double_values_are_canonical() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "amount", "long": "amount", "type": "option", "value_type": "double", "multiple": true}]
+        }"#,
+        );
+
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&[
+                "--amount",
+                "1000000.5",
+                "--amount",
+                "1e6",
+                "--amount",
+                "+2.50",
+            ]),
+            get_name(&config),
+        ));
+        assert_eq!(
+            result.values.get("amount"),
+            Some(&ParsedValue::Multiple(vec![
+                "1000000.5".to_string(),
+                "1000000".to_string(),
+                "2.5".to_string(),
+            ]))
+        );
+
+        // Without decimal_comma a comma is not a decimal separator
+        assert!(matches!(
+            parse_args(&config, &to_args(&["--amount", "1,5"]), get_name(&config)),
+            ParseOutcome::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_decimal_comma_normalized_to_point() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "amount", "long": "amount", "type": "option", "value_type": "double", "decimal_comma": true},
+                {"name": "ratio", "long": "ratio", "type": "option", "value_type": "double", "decimal_comma": true, "max": 1.0}
+            ]
+        }"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--amount", "1000000,5", "--ratio", "0,25"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("amount"), Some(&"1000000.5".to_string()));
+        assert_eq!(result.get("ratio"), Some(&"0.25".to_string()));
+
+        // A dot still works, but digit grouping does not
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--amount", "2.5"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("amount"), Some(&"2.5".to_string()));
+        for grouped in ["1.000,5", "1,000,5"] {
+            assert!(matches!(
+                parse_args(&config, &to_args(&["--amount", grouped]), get_name(&config)),
+                ParseOutcome::Error(_)
+            ));
+        }
+        match parse_args(&config, &to_args(&["--ratio", "1,5"]), get_name(&config)) {
+            ParseOutcome::Error(msg) => assert!(msg.contains("value 1,5 out of range"), "{}", msg),
+            other => panic!("Expected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decimal_comma_with_validate_values_and_default() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "amount", "long": "amount", "type": "option", "value_type": "double", "decimal_comma": true},
+                {"name": "ratio", "long": "ratio", "type": "option", "value_type": "double", "decimal_comma": true, "default": "0,5"}
+            ]
+        }"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success(parse_args_validating(
+            &config,
+            &to_args(&["--amount", "1,5"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("amount"), Some(&"1.5".to_string()));
+        assert_eq!(result.get("ratio"), Some(&"0.5".to_string()));
+
+        match parse_args_validating(&config, &to_args(&["--amount", "1,0,5"]), get_name(&config)) {
+            ParseOutcome::Error(msg) => assert!(msg.contains("expected double"), "{}", msg),
+            other => panic!("Expected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_value_type_path_must_exist() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "input", "long": "input", "type": "option", "value_type": "path", "path_must_exist": true}]
+        }"#,
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let file = tempfile::NamedTempFile::new_in(dir.path()).unwrap();
+
+        for existing in [dir.path(), file.path()] {
+            let existing = existing.to_str().unwrap();
+            let result = unwrap_success(parse_args(
+                &config,
+                &to_args(&["--input", existing]),
+                get_name(&config),
+            ));
+            assert_eq!(result.get("input"), Some(&existing.to_string()));
+        }
+
+        let missing = dir.path().join("missing");
+        let missing = missing.to_str().unwrap();
+        match parse_args(&config, &to_args(&["--input", missing]), get_name(&config)) {
+            ParseOutcome::Error(msg) => {
+                assert!(msg.contains(&format!("path does not exist: {}", missing)))
+            }
+            other => panic!("Expected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_value_type_path_without_existence_check() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "output", "type": "positional", "value_type": "path"}]
+        }"#,
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("not-yet-created.txt");
+        let missing = missing.to_str().unwrap();
+
+        let result = unwrap_success(parse_args(&config, &to_args(&[missing]), get_name(&config)));
+        assert_eq!(result.get("output"), Some(&missing.to_string()));
+    }
+
+    #[test]
+    fn test_parse_help_matches_generated_help() {
+        // Parsing and help rendering share one command builder, so `-h` during
+        // a parse shows exactly what `shclap help` prints
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "description": "Test app",
+            "args": [
+                {"name": "count", "short": "c", "type": "option", "value_type": "int", "min": 1, "help": "How many"},
+                {"name": "format", "long": "format", "type": "option", "choices": ["json", "yaml"]},
+                {"name": "input", "type": "positional", "env": "INPUT_FILE"}
+            ],
+            "subcommands": [{"name": "run", "help": "Run it"}]
+        }"#,
+        );
+
+        match parse_args(&config, &to_args(&["--help"]), get_name(&config)) {
+            ParseOutcome::Help(text) => {
+                assert_eq!(text, crate::help::generate_help(&config, get_name(&config)))
+            }
+            other => panic!("Expected help, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_conflicts_with_success() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "json", "long": "json", "type": "flag", "conflicts_with": ["yaml"]},
+                {"name": "yaml", "long": "yaml", "type": "flag"}
+            ]
+        }"#,
+        );
+
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--json"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("json"), Some(&"true".to_string()));
+        assert_eq!(result.get("yaml"), Some(&"false".to_string()));
+    }
+
+    #[test]
+    fn test_conflicts_with_error() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "json", "long": "json", "type": "flag", "conflicts_with": ["output"]},
+                {"name": "output", "long": "output", "type": "option"}
+            ]
+        }"#,
+        );
+
+        match parse_args(
+            &config,
+            &to_args(&["--json", "--output", "out.txt"]),
+            get_name(&config),
+        ) {
+            ParseOutcome::Error(msg) => {
+                assert_eq!(msg, "argument --json cannot be used with --output")
+            }
+            other => panic!("Expected conflict error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_requires_chained() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [
+                {"name": "compress", "long": "compress", "type": "flag", "requires": ["output"]},
+                {"name": "output", "long": "output", "type": "option", "requires": ["format"]},
+                {"name": "format", "long": "format", "type": "option"}
+            ]
+        }"#,
+        );
+
+        // Nothing given: no requirement applies
+        unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
+
+        // clap follows the chain and reports both missing options
+        match parse_args(&config, &to_args(&["--compress"]), get_name(&config)) {
+            ParseOutcome::Error(msg) => assert_eq!(
+                msg,
+                "the following required arguments were not provided: --format, --output"
+            ),
+            other => panic!("Expected error, got {:?}", other),
+        }
+
+        // --output in turn requires --format
+        match parse_args(
+            &config,
+            &to_args(&["--compress", "--output", "out.gz"]),
+            get_name(&config),
+        ) {
+            ParseOutcome::Error(msg) => assert_eq!(
+                msg,
+                "the following required argument was not provided: --format"
+            ),
+            other => panic!("Expected error, got {:?}", other),
+        }
+
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--compress", "--output", "out.gz", "--format", "gzip"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("output"), Some(&"out.gz".to_string()));
+    }
+
+    #[test]
+    fn test_aliases_write_same_variable() {
+        let config = parse_config(
+            r#"{
+            "schema_version": 2,
+            "name": "test",
+            "args": [{"name": "path", "long": "path", "type": "option", "aliases": ["dir", "directory"]}]
+        }"#,
+        );
+
+        for long in ["--path", "--dir", "--directory"] {
+            let result = unwrap_success(parse_args(
+                &config,
+                &to_args(&[long, "x"]),
+                get_name(&config),
+            ));
+            assert_eq!(result.get("path"), Some(&"x".to_string()), "{}", long);
+        }
+
+        // Output uses the canonical name whichever alias was typed
+        let values = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["--directory", "x"]),
+            get_name(&config),
+        ))
+        .values;
+        let output = crate::output::generate_output_string(&values, "SHCLAP_", None);
+        assert_eq!(output, "export SHCLAP_PATH=\"x\"\n");
+    }
+
+    #[test]
+    fn test_max_occurrences_at_and_over_limit() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"verbose","short":"v","long":"verbose","type":"flag","multiple":true,"max_occurrences":3}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success(parse_args(&config, &to_args(&["-vvv"]), get_name(&config)));
+        assert_eq!(result.get("verbose"), Some(&"3".to_string()));
+
+        match parse_args(
+            &config,
+            &to_args(&["-vv", "--verbose", "-v"]),
+            get_name(&config),
+        ) {
+            ParseOutcome::Error(msg) => {
+                assert_eq!(msg, "flag --verbose specified too many times (max 3)")
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_array_default_for_multiple_option() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"tag","long":"tag","type":"option","multiple":true,"default":["a","b c"]}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        let result = parse_args(&config, &[], get_name(&config));
+        match result {
+            ParseOutcome::Success(r) => assert_eq!(
+                r.values.get("tag"),
+                Some(&ParsedValue::Multiple(vec![
+                    "a".to_string(),
+                    "b c".to_string()
+                ]))
+            ),
+            other => panic!("Expected Success, got {:?}", other),
+        }
+
+        // Any value on the command line replaces the whole default
+        let result = parse_args(&config, &to_args(&["--tag", "x"]), get_name(&config));
+        match result {
+            ParseOutcome::Success(r) => assert_eq!(
+                r.values.get("tag"),
+                Some(&ParsedValue::Multiple(vec!["x".to_string()]))
+            ),
+            other => panic!("Expected Success, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_case_insensitive_choices_store_declared_spelling() {
+        let config = Config::from_json(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"format","long":"format","type":"option","multiple":true,"choices":["json","YAML"],"case_insensitive":true},
+                {"name":"level","long":"level","type":"option","choices":["low","high"]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let result = parse_args(
+            &config,
+            &[
+                "--format".into(),
+                "JSON".into(),
+                "--format".into(),
+                "yaml".into(),
+            ],
+            "test",
+        );
+        match result {
+            ParseOutcome::Success(r) => {
+                assert_eq!(
+                    r.values.get("format"),
+                    Some(&ParsedValue::Multiple(vec!["json".into(), "YAML".into()]))
+                );
+            }
+            _ => panic!("Expected success"),
+        }
+
+        // Without the flag, matching stays exact
+        let result = parse_args(&config, &["--level".into(), "HIGH".into()], "test");
+        assert!(matches!(result, ParseOutcome::Error(_)));
+
+        // The validating pre-pass accepts the same spellings
+        let result = parse_args_validating(&config, &["--format".into(), "Json".into()], "test");
+        assert!(matches!(result, ParseOutcome::Success(_)));
+    }
+
+    #[test]
+    fn test_unique_drops_repeated_values() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"file","long":"file","type":"option","multiple":true,"unique":true},
+                {"name":"tag","long":"tag","type":"option","multiple":true}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&[
+                "--file", "b", "--file", "a", "--file", "b", "--tag", "x", "--tag", "x",
+            ]),
+            get_name(&config),
+        ));
+        assert_eq!(
+            result.values.get("file"),
+            Some(&ParsedValue::Multiple(vec!["b".into(), "a".into()]))
+        );
+        assert_eq!(
+            result.values.get("tag"),
+            Some(&ParsedValue::Multiple(vec!["x".into(), "x".into()]))
+        );
+    }
+
+    #[test]
+    fn test_hidden_arg_still_parses() {
+        let _guard = EnvGuard::set("HIDDEN_TEST_TRACE_ID", "from-env");
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","prefix":"HIDDEN_TEST_","args":[
+                {"name":"debug-dump","long":"debug-dump","type":"option","hidden":true,"choices":["on","off"]},
+                {"name":"trace-id","long":"trace-id","type":"option","hidden":true}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--debug-dump", "on"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("debug-dump"), Some(&"on".to_string()));
+        assert_eq!(result.get("trace-id"), Some(&"from-env".to_string()));
+
+        // Choices are still enforced
+        let result = parse_args(
+            &config,
+            &to_args(&["--debug-dump", "maybe"]),
+            get_name(&config),
+        );
+        assert!(matches!(result, ParseOutcome::Error(_)));
+    }
+
+    #[test]
+    fn test_missing_required_args() {
+        let config = parse_config(
+            r#"{"name":"test","args":[
+                {"name":"output","long":"output","type":"option","required":true},
+                {"name":"level","long":"level","type":"option","required":true},
+                {"name":"input","type":"positional","required":true},
+                {"name":"extra","long":"extra","type":"option"}
+            ]}"#,
+        );
+        let missing = missing_required_args(&config, &to_args(&["--level", "3"]), "test");
+        let names: Vec<_> = missing.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["output", "input"]);
+    }
+
+    #[test]
+    fn test_missing_required_args_none_missing() {
+        let config = parse_config(
+            r#"{"name":"test","args":[
+                {"name":"output","long":"output","type":"option","required":true}
+            ]}"#,
+        );
+        let missing = missing_required_args(&config, &to_args(&["--output", "x"]), "test");
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn test_parse_args_validating_reports_all_errors() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"count","long":"count","type":"option","value_type":"int"},
+                {"name":"ratio","long":"ratio","type":"option","value_type":"double"},
+                {"name":"format","long":"format","type":"option","choices":["json","yaml"]}
+            ]}"#,
+        );
+        let args = to_args(&["--count", "abc", "--ratio", "x", "--format", "json"]);
+        match parse_args_validating(&config, &args, "test") {
+            ParseOutcome::Error(msg) => {
+                assert_eq!(
+                    msg,
+                    "invalid values:\n  \
+                     invalid value 'abc' for 'count': expected int\n  \
+                     invalid value 'x' for 'ratio': expected double"
+                );
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_args_validating_reports_range_and_path_errors() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"ratio","long":"ratio","type":"option","value_type":"double"},
+                {"name":"port","long":"port","type":"option","value_type":"int","max":10},
+                {"name":"input","long":"input","type":"option","value_type":"path","path_must_exist":true}
+            ]}"#,
+        );
+        let args = to_args(&[
+            "--ratio",
+            "x",
+            "--port",
+            "99",
+            "--input",
+            "/nonexistent/shclap-test-input",
+        ]);
+        match parse_args_validating(&config, &args, "test") {
+            ParseOutcome::Error(msg) => {
+                assert_eq!(
+                    msg,
+                    "invalid values:\n  \
+                     invalid value 'x' for 'ratio': expected double\n  \
+                     invalid value '99' for 'port': value 99 out of range (..=10)\n  \
+                     invalid value '/nonexistent/shclap-test-input' for 'input': \
+                     path does not exist: /nonexistent/shclap-test-input"
+                );
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_args_validating_single_error() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"format","long":"format","type":"option","choices":["json","yaml"]}
+            ]}"#,
+        );
+        let args = to_args(&["--format", "xml"]);
+        match parse_args_validating(&config, &args, "test") {
+            ParseOutcome::Error(msg) => {
+                assert_eq!(
+                    msg,
+                    "invalid value 'xml' for 'format': expected one of json, yaml"
+                );
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_args_validating_subcommand_values() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test",
+                "args":[{"name":"level","long":"level","type":"option","value_type":"int"}],
+                "subcommands":[{"name":"run","args":[
+                    {"name":"jobs","long":"jobs","type":"option","value_type":"int"}
+                ]}]}"#,
+        );
+        let args = to_args(&["--level", "high", "run", "--jobs", "many"]);
+        match parse_args_validating(&config, &args, "test") {
+            ParseOutcome::Error(msg) => {
+                assert!(msg.contains("'high' for 'level'"));
+                assert!(msg.contains("'many' for 'jobs'"));
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_args_validating_valid_values() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"verbose","short":"v","type":"flag"},
+                {"name":"count","long":"count","type":"option","value_type":"int"}
+            ]}"#,
+        );
+        let args = to_args(&["-v", "--count", "42"]);
+        let result = unwrap_success(parse_args_validating(&config, &args, "test"));
+        assert_eq!(result.get("count"), Some(&"42".to_string()));
+        assert_eq!(result.get("verbose"), Some(&"true".to_string()));
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        let lookup = |var: &str| (var == "HOME").then(|| "/home/me".to_string());
+        let policy = UndefinedEnv::Empty;
+
+        assert_eq!(
+            expand_env_vars("${HOME}/logs", &policy, lookup),
+            Ok("/home/me/logs".to_string())
+        );
+        assert_eq!(
+            expand_env_vars("$HOME/logs", &policy, lookup),
+            Ok("/home/me/logs".to_string())
+        );
+        assert_eq!(
+            expand_env_vars("a$UNSET-b", &policy, lookup),
+            Ok("a-b".to_string())
+        );
+        // Lone or invalid references are kept literally
+        assert_eq!(
+            expand_env_vars("$ 5$ $1 ${oops", &policy, lookup),
+            Ok("$ 5$ $1 ${oops".to_string())
+        );
+    }
+
+    #[test]
+    fn test_expand_env_vars_undefined_error() {
+        let result = expand_env_vars("${MISSING}/x", &UndefinedEnv::Error, |_| None);
+        assert_eq!(
+            result,
+            Err("undefined environment variable: MISSING".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_expand_env_set_variable() {
+        std::env::set_var("SHCLAP_TEST_EXPAND_DIR", "/var/data");
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"log-dir","long":"log-dir","type":"option","expand_env":true,"env":false},
+                {"name":"raw","long":"raw","type":"option","env":false}
+            ]}"#,
+        );
+        config.validate().unwrap();
+        let args = to_args(&[
+            "--log-dir",
+            "${SHCLAP_TEST_EXPAND_DIR}/logs",
+            "--raw",
+            "$SHCLAP_TEST_EXPAND_DIR",
+        ]);
+        let result = unwrap_success(parse_args(&config, &args, get_name(&config)));
+        assert_eq!(result.get("log-dir"), Some(&"/var/data/logs".to_string()));
+        assert_eq!(
+            result.get("raw"),
+            Some(&"$SHCLAP_TEST_EXPAND_DIR".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_expand_env_unset_variable() {
+        let json = r#"{"schema_version":2,"name":"test","undefined_env":"error","args":[
+            {"name":"dir","long":"dir","type":"option","expand_env":true,"env":false}
+        ]}"#;
+        let args = to_args(&["--dir", "$SHCLAP_TEST_EXPAND_UNSET/x"]);
+
+        let config = parse_config(json);
+        match parse_args(&config, &args, get_name(&config)) {
+            ParseOutcome::Error(msg) => {
+                assert_eq!(
+                    msg,
+                    "undefined environment variable: SHCLAP_TEST_EXPAND_UNSET"
+                );
+            }
+            other => panic!("Expected Error, got {:?}", other),
+        }
+
+        let config = parse_config(&json.replace(r#""undefined_env":"error","#, ""));
+        let result = unwrap_success(parse_args(&config, &args, get_name(&config)));
+        assert_eq!(result.get("dir"), Some(&"/x".to_string()));
+    }
+
+    #[test]
+    fn test_parse_expand_env_skips_default() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"dir","long":"dir","type":"option","expand_env":true,"env":false,"default":"$HOME"}
+            ]}"#,
+        );
+        let result = unwrap_success(parse_args(&config, &to_args(&[]), get_name(&config)));
+        assert_eq!(result.get("dir"), Some(&"$HOME".to_string()));
+    }
+
+    #[test]
+    fn test_deprecated_in_favor_of_warns_when_used() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"out","long":"out","type":"option","deprecated_in_favor_of":"output"},
+                {"name":"output","long":"output","type":"option"}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["--out", "x"]),
+            get_name(&config),
+        ));
+        assert_eq!(
+            result.warnings,
+            vec!["--out is deprecated: use --output instead".to_string()]
+        );
+
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["--output", "x"]),
+            get_name(&config),
+        ));
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_deprecated_in_favor_of_in_help() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"out","long":"out","type":"option","help":"Output file","deprecated_in_favor_of":"output"},
+                {"name":"output","long":"output","type":"option"}
+            ]}"#,
+        );
+        match parse_args(&config, &to_args(&["--help"]), get_name(&config)) {
+            ParseOutcome::Help(text) => {
+                assert!(text.contains("Output file [deprecated: use --output instead]"));
+            }
+            other => panic!("Expected Help, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "timeout")]
+    #[test]
+    fn test_parse_args_with_timeout_completes() {
+        let config = parse_config(
+            r#"{"name":"test","args":[{"name":"verbose","short":"v","type":"flag"}]}"#,
+        );
+        let result = unwrap_success(parse_args_with_timeout(
+            &config,
+            &to_args(&["-v"]),
+            "test",
+            std::time::Duration::from_secs(30),
+        ));
+        assert_eq!(result.get("verbose"), Some(&"true".to_string()));
+    }
+
+    #[cfg(feature = "timeout")]
+    #[test]
+    fn test_parse_args_with_timeout_expires() {
+        // Thousands of options and occurrences make parsing slow enough to
+        // overrun a one-microsecond budget
+        let args: Vec<String> = (0..2000)
+            .map(|i| format!(r#"{{"name":"opt{i}","type":"option","multiple":true}}"#))
+            .collect();
+        let config = parse_config(&format!(
+            r#"{{"schema_version":2,"name":"test","args":[{}]}}"#,
+            args.join(",")
+        ));
+        let cli_args: Vec<String> = (0..20000)
+            .flat_map(|i| [format!("--opt{}", i % 2000), i.to_string()])
+            .collect();
+
+        let result = parse_args_with_timeout(
+            &config,
+            &cli_args,
+            "test",
+            std::time::Duration::from_micros(1),
+        );
+        assert_eq!(
+            result,
+            ParseOutcome::Error("argument parsing timed out".to_string())
+        );
+    }
+
+    #[test]
+    fn test_require_equals() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"output","long":"output","type":"option","require_equals":true}
+            ]}"#,
+        );
+        config.validate().unwrap();
+
+        let result = unwrap_success(parse_args(
+            &config,
+            &to_args(&["--output=file"]),
+            get_name(&config),
+        ));
+        assert_eq!(result.get("output"), Some(&"file".to_string()));
+
+        let result = parse_args(&config, &to_args(&["--output", "file"]), get_name(&config));
+        assert_eq!(
+            result,
+            ParseOutcome::Error("option --output requires '=': use --output=VALUE".to_string())
+        );
+    }
+
+    #[test]
+    fn test_occurrence_count_differs_from_value_count() {
+        let config = parse_config(
+            r#"{"schema_version":2,"name":"test","args":[
+                {"name":"point","long":"point","type":"option","multiple":true,"num_args":"2"},
+                {"name":"tag","long":"tag","type":"option","multiple":true,"default":"none"}
+            ]}"#,
+        );
+        let result = unwrap_success_full(parse_args(
+            &config,
+            &to_args(&["--point", "1", "2", "--point", "3", "4"]),
+            get_name(&config),
+        ));
+
+        match result.values.get("point") {
+            Some(ParsedValue::Multiple(v)) => assert_eq!(v.len(), 4),
+            other => panic!("Expected Multiple, got {:?}", other),
+        }
+        assert_eq!(result.occurrences.get("point"), Some(&2));
+        // Defaults aren't occurrences
+        assert_eq!(result.occurrences.get("tag"), Some(&0));
+    }
 }
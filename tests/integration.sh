@@ -239,6 +239,16 @@ else
     fail "CLI --prefix overrides config prefix" "CLI_DEBUG=true" "CLI_DEBUG=${CLI_DEBUG:-unset}"
 fi
 
+# Test: CLI prefix applies to the subcommand variable
+run_test
+unset MYAPP_SUBCOMMAND 2>/dev/null || true
+source "$("$SHCLAP" parse --config '{"schema_version":2,"name":"test","subcommands":[{"name":"init","args":[]}]}' --prefix MYAPP_ -- init)"
+if [[ "${MYAPP_SUBCOMMAND:-}" == "init" ]]; then
+    pass "CLI --prefix applies to subcommand variable (MYAPP_SUBCOMMAND)"
+else
+    fail "CLI --prefix applies to subcommand variable" "MYAPP_SUBCOMMAND=init" "MYAPP_SUBCOMMAND=${MYAPP_SUBCOMMAND:-unset}"
+fi
+
 
 section "6. Help Flag Detection"
 
@@ -611,6 +621,63 @@ else
     fail "Missing subcommand" "Should show usage or available subcommands" "$HELP_OUTPUT"
 fi
 
+# Test: --emit-dispatch calls the matching cmd_<name> function
+run_test
+DISPATCHED=""
+cmd_init() { DISPATCHED="init"; }
+cmd_run_all() { DISPATCHED="run-all"; }
+OUTPUT=$("$SHCLAP" parse --config '{"schema_version":2,"name":"test","subcommands":[
+    {"name":"init"},{"name":"run-all"}
+]}' --emit-dispatch -- run-all)
+if bash -n "$OUTPUT" && source "$OUTPUT" && [[ "$DISPATCHED" == "run-all" ]]; then
+    pass "--emit-dispatch calls cmd_run_all for the run-all subcommand"
+else
+    fail "--emit-dispatch" "DISPATCHED=run-all" "DISPATCHED=${DISPATCHED:-unset}"
+fi
+unset -f cmd_init cmd_run_all
+
+# Test: --stdout prints the exports and leaves no temp file behind
+run_test
+STDOUT_TMPDIR=$(mktemp -d)
+OUTPUT=$(TMPDIR="$STDOUT_TMPDIR" "$SHCLAP" parse --config '{"name":"test","args":[
+    {"name":"output","short":"o","type":"option"}
+]}' --stdout -- -o "out file.txt")
+eval "$OUTPUT"
+LEFTOVER=$(ls -A "$STDOUT_TMPDIR")
+rmdir "$STDOUT_TMPDIR"
+if [[ "$SHCLAP_OUTPUT" == "out file.txt" && -z "$LEFTOVER" ]]; then
+    pass "--stdout prints exports without creating a temp file"
+else
+    fail "--stdout" "SHCLAP_OUTPUT='out file.txt' and no temp file" "SHCLAP_OUTPUT='${SHCLAP_OUTPUT:-}', files: $LEFTOVER"
+fi
+unset SHCLAP_OUTPUT
+
+# Test: --self-cleanup output deletes itself when sourced
+run_test
+OUTPUT=$("$SHCLAP" parse --config '{"name":"test","args":[{"name":"verbose","type":"flag"}]}' --self-cleanup -- --verbose)
+source "$OUTPUT"
+if [[ "$SHCLAP_VERBOSE" == "true" && ! -e "$OUTPUT" ]]; then
+    pass "--self-cleanup removes the output file after sourcing"
+else
+    fail "--self-cleanup" "SHCLAP_VERBOSE=true and $OUTPUT removed" "SHCLAP_VERBOSE=${SHCLAP_VERBOSE:-}, exists: $([[ -e "$OUTPUT" ]] && echo yes || echo no)"
+fi
+unset SHCLAP_VERBOSE
+
+# Test: --self-cleanup quotes an output dir holding shell metacharacters
+run_test
+CLEANUP_TMPDIR=$(mktemp -d)
+CLEANUP_DIR="$CLEANUP_TMPDIR/a;>PWNED;b"
+mkdir "$CLEANUP_DIR"
+OUTPUT=$("$SHCLAP" parse --config '{"name":"test","args":[{"name":"verbose","type":"flag"}]}' --self-cleanup --output-dir "$CLEANUP_DIR" -- --verbose)
+(cd "$CLEANUP_TMPDIR" && source "$OUTPUT") || true
+if [[ ! -e "$CLEANUP_TMPDIR/PWNED" && ! -e "$OUTPUT" ]]; then
+    pass "--self-cleanup removes a file under a dir with ';' without running it"
+else
+    fail "--self-cleanup quoting" "no PWNED and $OUTPUT removed" "$(ls -A "$CLEANUP_TMPDIR")"
+fi
+rm -rf "$CLEANUP_TMPDIR"
+unset SHCLAP_VERBOSE
+
 
 section "14. Schema Version 2 - Validation Errors"
 
@@ -712,6 +779,142 @@ else
 fi
 
 
+# Test: validate-env passes when required env-backed args are set
+run_test
+CONFIG='{"schema_version":2,"name":"deploy","prefix":"DEPLOY_","args":[
+    {"name":"target","type":"option","required":true}
+]}'
+if DEPLOY_TARGET=production "$SHCLAP" validate-env --config "$CONFIG" 2>/dev/null; then
+    pass "shclap validate-env succeeds when the env var is set"
+else
+    fail "validate-env set" "exit 0" "exit $?"
+fi
+
+# Test: validate-env fails and names the missing variable
+run_test
+unset DEPLOY_TARGET 2>/dev/null || true
+set +e
+ERR_OUTPUT=$("$SHCLAP" validate-env --config "$CONFIG" 2>&1)
+EXIT_CODE=$?
+set -e
+if [[ $EXIT_CODE -eq 1 ]] && echo "$ERR_OUTPUT" | grep -q 'DEPLOY_TARGET is not set'; then
+    pass "shclap validate-env exits 1 and names the unset variable"
+else
+    fail "validate-env unset" "exit 1 mentioning DEPLOY_TARGET" "exit $EXIT_CODE: $ERR_OUTPUT"
+fi
+
+# Test: validate-env and parse read the same variables under --prefix
+run_test
+CONFIG='{"schema_version":2,"name":"deploy","args":[
+    {"name":"target","long":"target","type":"option","required":true}
+]}'
+agree() {
+    local validate_ok=no parse_ok=no
+    env "$@" "$SHCLAP" validate-env --prefix APP_ --config "$CONFIG" 2>/dev/null && validate_ok=yes
+    env "$@" "$SHCLAP" parse --stdout --prefix APP_ --config "$CONFIG" | grep -q '^export APP_TARGET="x"$' && parse_ok=yes
+    echo "$validate_ok/$parse_ok"
+}
+WITH_APP=$(agree APP_TARGET=x)
+WITH_DEFAULT=$(agree SHCLAP_TARGET=x)
+if [[ "$WITH_APP" == "yes/yes" && "$WITH_DEFAULT" == "no/no" ]]; then
+    pass "validate-env and parse agree on env fallback names with --prefix"
+else
+    fail "validate-env/parse prefix" "yes/yes and no/no" "APP_TARGET: $WITH_APP, SHCLAP_TARGET: $WITH_DEFAULT"
+fi
+
+# Test: explain-config reports the env fallback name parse actually reads
+run_test
+CONFIG='{"schema_version":2,"name":"app","args":[{"name":"port","long":"port","type":"option"}]}'
+EXPLAINED=$("$SHCLAP" explain-config --prefix APP_ --config "$CONFIG" | grep 'env:' | head -1)
+PARSED=$(APP_PORT=5 "$SHCLAP" parse --stdout --prefix APP_ --config "$CONFIG")
+if [[ "$EXPLAINED" == *"env: APP_PORT"* && "$PARSED" == 'export APP_PORT="5"' ]]; then
+    pass "explain-config env names match what parse reads under --prefix"
+else
+    fail "explain-config prefix" "env: APP_PORT and export APP_PORT=\"5\"" "$EXPLAINED / $PARSED"
+fi
+
+# Test: --shell fish reports help and errors in fish syntax, not a bash heredoc
+run_test
+CONFIG='{"schema_version":2,"name":"app","args":[{"name":"port","long":"port","type":"option","value_type":"int"}]}'
+HELP_OUT=$("$SHCLAP" parse --stdout --shell fish --config "$CONFIG" -- --help)
+ERROR_OUT=$("$SHCLAP" parse --stdout --shell fish --config "$CONFIG" -- --port x)
+if [[ "$HELP_OUT" == "printf '%s' \"Usage: app"* && "$HELP_OUT" != *"<<"* && "$ERROR_OUT" == 'echo "shclap: '*'" >&2'* ]]; then
+    pass "--shell fish emits help and errors in fish syntax"
+else
+    fail "fish help/error" "printf/echo without heredoc" "$HELP_OUT / $ERROR_OUT"
+fi
+
+# Test: --shell powershell reports help and errors in PowerShell syntax
+run_test
+HELP_OUT=$("$SHCLAP" parse --stdout --shell powershell --config "$CONFIG" -- --help)
+ERROR_OUT=$("$SHCLAP" parse --stdout --shell powershell --config "$CONFIG" -- --port x)
+if [[ "$HELP_OUT" == '[Console]::Out.Write("Usage: app'* && "$ERROR_OUT" == '[Console]::Error.WriteLine("shclap: '* ]]; then
+    pass "--shell powershell emits help and errors in PowerShell syntax"
+else
+    fail "powershell help/error" "[Console]::Out.Write / [Console]::Error.WriteLine" "$HELP_OUT / $ERROR_OUT"
+fi
+
+# Test: --format toml prints help and errors as TOML and rejects shell-only flags
+run_test
+HELP_OUT=$("$SHCLAP" parse --format toml --config "$CONFIG" -- --help)
+ERROR_OUT=$("$SHCLAP" parse --format toml --config "$CONFIG" -- --port x) && ERROR_RC=0 || ERROR_RC=$?
+"$SHCLAP" parse --format toml --xtrace --config "$CONFIG" -- --port 1 >/dev/null 2>&1 && CONFLICT_RC=0 || CONFLICT_RC=$?
+if [[ "$HELP_OUT" == 'help = """'* && "$ERROR_OUT" == "error = \"invalid value 'x' for '--port"* && $ERROR_RC -eq 1 && $CONFLICT_RC -eq 2 ]]; then
+    pass "--format toml reports help and errors as TOML and rejects --xtrace"
+else
+    fail "toml help/error" "help = ..., error = ... (exit 1), conflict exit 2" "$HELP_OUT / $ERROR_OUT ($ERROR_RC) / $CONFLICT_RC"
+fi
+
+# Test: --format json prints help and errors as JSON and rejects an arg named subcommand
+run_test
+HELP_OUT=$("$SHCLAP" parse --format json --config "$CONFIG" -- --help)
+ERROR_OUT=$("$SHCLAP" parse --format json --config "$CONFIG" -- --port x) && ERROR_RC=0 || ERROR_RC=$?
+CLASH_CONFIG='{"schema_version":2,"name":"app","args":[{"name":"subcommand","long":"subcommand","type":"option"}],"subcommands":[{"name":"run"}]}'
+CLASH_OUT=$("$SHCLAP" parse --format json --config "$CLASH_CONFIG" -- run) && CLASH_RC=0 || CLASH_RC=$?
+if [[ "$HELP_OUT" == *'"help": "Usage: app'* && "$ERROR_OUT" == *'"error": "invalid value'* && $ERROR_RC -eq 1 \
+    && "$CLASH_OUT" == *"arg named 'subcommand'"* && $CLASH_RC -eq 1 ]]; then
+    pass "--format json reports help and errors as JSON and rejects an arg named subcommand"
+else
+    fail "json help/error" "help/error JSON documents, exit 1 for errors" "$HELP_OUT / $ERROR_OUT ($ERROR_RC) / $CLASH_OUT ($CLASH_RC)"
+fi
+
+# Test: --shell posix rejects an arg whose variable is another arg's _COUNT
+run_test
+COLLIDE_CONFIG='{"schema_version":2,"name":"app","args":[
+    {"name":"file","long":"file","type":"option","multiple":true},
+    {"name":"file_count","long":"file-count","type":"option"}
+]}'
+POSIX_OUT=$("$SHCLAP" parse --stdout --shell posix --config "$COLLIDE_CONFIG" -- --file a --file-count 9)
+BASH_OUT=$("$SHCLAP" parse --stdout --config "$COLLIDE_CONFIG" -- --file a --file-count 9)
+if [[ "$POSIX_OUT" == *"'FILE_COUNT', which is also set for argument 'file'"* && "$BASH_OUT" == *'export SHCLAP_FILE_COUNT="9"'* ]]; then
+    pass "--shell posix rejects file_count next to a multiple file arg"
+else
+    fail "posix _COUNT collision" "posix error, bash export" "$POSIX_OUT / $BASH_OUT"
+fi
+
+# Test: --emit-unset-prefix --shell posix also clears a list's _COUNT
+run_test
+LIST_CONFIG='{"schema_version":2,"name":"app","args":[{"name":"m","long":"m","type":"option","multiple":true}]}'
+eval "$("$SHCLAP" parse --stdout --shell posix --config "$LIST_CONFIG" -- --m a --m b)"
+eval "$("$SHCLAP" parse --stdout --shell posix --emit-unset-prefix --config "$LIST_CONFIG" --)"
+if [[ -z "${SHCLAP_M_COUNT+set}" ]]; then
+    pass "--emit-unset-prefix --shell posix unsets a stale SHCLAP_M_COUNT"
+else
+    fail "posix unset _COUNT" "SHCLAP_M_COUNT unset" "SHCLAP_M_COUNT=$SHCLAP_M_COUNT"
+fi
+unset SHCLAP_M SHCLAP_M_COUNT SHCLAP_M_1 SHCLAP_M_2
+
+# Test: parse --schema-version 1 applies v1 rules to a v2 config
+run_test
+V2_CONFIG='{"schema_version":2,"name":"app","args":[{"name":"files","long":"files","type":"option","multiple":true}]}'
+V1_OUT=$("$SHCLAP" parse --stdout --schema-version 1 --config "$V2_CONFIG" -- --files a)
+V2_OUT=$("$SHCLAP" parse --stdout --config "$V2_CONFIG" -- --files a)
+if [[ "$V1_OUT" == *"field 'multiple' on argument 'files' requires schema_version >= 2"* && "$V2_OUT" == 'export SHCLAP_FILES=("a")' ]]; then
+    pass "parse --schema-version 1 reports v1 validation errors"
+else
+    fail "--schema-version 1" "multiple requires schema_version >= 2" "$V1_OUT / $V2_OUT"
+fi
+
 section "17. Value Type Validation (int and bool)"
 
 # Test: value_type: int with valid integer
@@ -734,6 +937,16 @@ else
     fail "int negative" "SHCLAP_COUNT=-5" "SHCLAP_COUNT=${SHCLAP_COUNT:-unset}"
 fi
 
+# Test: --declare-int output sourced inside a function stays global and exported
+run_test
+OUTPUT=$("$SHCLAP" parse --declare-int --config '{"schema_version":2,"name":"test","args":[{"name":"port","long":"port","type":"option","value_type":"int"}]}' -- --port 8080)
+RESULT=$(bash -c "load() { source '$OUTPUT'; }; load; echo \"\$SHCLAP_PORT \$(bash -c 'echo \$SHCLAP_PORT')\"" 2>&1)
+if [[ "$RESULT" == "8080 8080" ]]; then
+    pass "--declare-int: value sourced in a function is global and exported"
+else
+    fail "declare-int in function" "8080 8080" "$RESULT"
+fi
+
 # Test: value_type: int with non-integer value produces error
 run_test
 OUTPUT=$("$SHCLAP" parse --config '{"schema_version":2,"name":"test","args":[{"name":"count","long":"count","type":"option","value_type":"int"}]}' -- --count abc)